@@ -1,34 +1,43 @@
 use std::sync::Arc;
 
-use crate::graphics::graphics::*;
+use crate::{graphics::graphics::*, settings::settings::Settings};
 use winit::{
     application::ApplicationHandler,
-    dpi::{PhysicalPosition, PhysicalSize},
-    event::{DeviceEvent, KeyEvent, WindowEvent},
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
+    event::{DeviceEvent, ElementState, KeyEvent, MouseButton, WindowEvent},
     event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy},
-    keyboard::PhysicalKey,
-    window::{Window, WindowId},
+    window::{Fullscreen, Window, WindowId},
 };
 
 enum State {
-    Ready(Graphics),
+    Ready(Box<Graphics>),
     Init(Option<EventLoopProxy<Graphics>>),
 }
 
 pub struct App {
     state: State,
+    settings: Settings,
 }
 
 impl App {
-    pub fn new(event_loop: &EventLoop<Graphics>) -> Self {
+    pub fn new(event_loop: &EventLoop<Graphics>, settings: Settings) -> Self {
         Self {
             state: State::Init(Some(event_loop.create_proxy())),
+            settings,
         }
     }
 
     fn update(&mut self) {
-        if let State::Ready(gfx) = &mut self.state {
-            gfx.update();
+        let State::Ready(gfx) = &mut self.state else {
+            return;
+        };
+        let was_captured = gfx.cursor_captured;
+        gfx.update();
+
+        match (was_captured, gfx.cursor_captured) {
+            (false, true) => self.capture_mouse(),
+            (true, false) => self.release_mouse(),
+            _ => {}
         }
     }
 
@@ -58,28 +67,38 @@ impl App {
     }
 
     fn capture_mouse(&mut self) {
-        if let State::Ready(gfx) = &mut self.state {
-            let _ = gfx
-                .window
+        if let State::Ready(gfx) = &mut self.state
+            && let Some(window) = &gfx.window
+        {
+            window
                 .set_cursor_grab(winit::window::CursorGrabMode::Locked)
-                .or_else(|_| {
-                    gfx.window
-                        .set_cursor_grab(winit::window::CursorGrabMode::Confined)
-                })
+                .or_else(|_| window.set_cursor_grab(winit::window::CursorGrabMode::Confined))
                 .expect("Failed to grab cursor");
 
-            gfx.window.set_cursor_visible(false);
+            window.set_cursor_visible(false);
         }
     }
 
     fn release_mouse(&mut self) {
-        if let State::Ready(gfx) = &mut self.state {
-            let _ = gfx
-                .window
+        if let State::Ready(gfx) = &mut self.state
+            && let Some(window) = &gfx.window
+        {
+            window
                 .set_cursor_grab(winit::window::CursorGrabMode::None)
                 .expect("Failed to release cursor");
 
-            gfx.window.set_cursor_visible(true);
+            window.set_cursor_visible(true);
+        }
+    }
+
+    /// Lets go of the cursor when another window takes focus, so that the click bringing this
+    /// one back recaptures it instead of breaking a block.
+    fn focus_lost(&mut self) {
+        if let State::Ready(gfx) = &mut self.state
+            && gfx.cursor_captured
+        {
+            gfx.cursor_captured = false;
+            self.release_mouse();
         }
     }
 
@@ -88,30 +107,44 @@ impl App {
             gfx.handle_keyboard_input(event);
         }
     }
+
+    fn mouse_input(&mut self, button: MouseButton, state: ElementState) {
+        if let State::Ready(gfx) = &mut self.state {
+            gfx.handle_mouse_input(button, state);
+        }
+    }
 }
 
 impl ApplicationHandler<Graphics> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if let State::Init(proxy) = &mut self.state {
-            if let Some(proxy) = proxy.take() {
-                let mut win_attr = Window::default_attributes();
-
-                win_attr = win_attr.with_title("WebGPU example");
+        if let State::Init(proxy) = &mut self.state
+            && let Some(proxy) = proxy.take()
+        {
+            let window_settings = &self.settings.window;
+            let mut win_attr = Window::default_attributes()
+                .with_title(window_settings.title.as_str())
+                .with_inner_size(LogicalSize::new(
+                    window_settings.width,
+                    window_settings.height,
+                ));
+
+            if window_settings.fullscreen {
+                win_attr = win_attr.with_fullscreen(Some(Fullscreen::Borderless(None)));
+            }
 
-                let window = Arc::new(
-                    event_loop
-                        .create_window(win_attr)
-                        .expect("create window err."),
-                );
+            let window = Arc::new(
+                event_loop
+                    .create_window(win_attr)
+                    .expect("create window err."),
+            );
 
-                pollster::block_on(create_graphics(window, proxy));
-            }
+            pollster::block_on(create_graphics(window, proxy, &self.settings));
         }
     }
 
     fn user_event(&mut self, _event_loop: &ActiveEventLoop, gfx: Graphics) {
         gfx.request_redraw();
-        self.state = State::Ready(gfx);
+        self.state = State::Ready(Box::new(gfx));
     }
 
     fn window_event(
@@ -122,44 +155,31 @@ impl ApplicationHandler<Graphics> for App {
     ) {
         match event {
             WindowEvent::Resized(size) => self.resized(size),
+            WindowEvent::Focused(false) => self.focus_lost(),
             WindowEvent::RedrawRequested => {
                 self.update();
                 self.draw();
             }
-            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::CloseRequested => {
+                if let State::Ready(gfx) = &mut self.state {
+                    gfx.save_world();
+                }
+                event_loop.exit();
+            }
             WindowEvent::CursorMoved {
                 device_id: _,
                 position,
             } => self.cursor_moved(&position),
             WindowEvent::MouseInput {
-                device_id,
+                device_id: _,
                 state,
                 button,
-            } => {
-                if button == winit::event::MouseButton::Left
-                    && state == winit::event::ElementState::Pressed
-                {
-                    self.capture_mouse();
-                }
-
-                if button == winit::event::MouseButton::Right
-                    && state == winit::event::ElementState::Pressed
-                {
-                    self.release_mouse();
-                }
-            }
+            } => self.mouse_input(button, state),
             WindowEvent::KeyboardInput {
                 device_id: _,
                 event,
                 is_synthetic: _,
-            } => {
-                if event.physical_key == PhysicalKey::Code(winit::keyboard::KeyCode::Escape)
-                    && event.state == winit::event::ElementState::Pressed
-                {
-                    self.release_mouse();
-                }
-                self.keyboard_input(&event);
-            }
+            } => self.keyboard_input(&event),
             _ => {}
         }
     }
@@ -170,12 +190,9 @@ impl ApplicationHandler<Graphics> for App {
         _device_id: winit::event::DeviceId,
         event: winit::event::DeviceEvent,
     ) {
-        match event {
-            DeviceEvent::MouseMotion { delta } => {
-                let (dx, dy) = delta;
-                self.mouse_motion(dx as f32, dy as f32);
-            }
-            _ => {}
+        if let DeviceEvent::MouseMotion { delta } = event {
+            let (dx, dy) = delta;
+            self.mouse_motion(dx as f32, dy as f32);
         }
     }
 }
@@ -0,0 +1,73 @@
+// Structs shared with `structures.rs`; their layout is checked against the Rust side.
+
+struct Globals {
+    mouse_pos: vec2<f32>,
+    resolution: vec2<u32>,
+    time_passed: f32,
+    frame_time: f32,
+    frame: u32,
+    time_of_day: f32,
+}
+
+struct View {
+    proj_view_rev_z: mat4x4<f32>,
+    inv_proj_view_rev_z: mat4x4<f32>,
+    proj_view: mat4x4<f32>,
+    inv_proj_view: mat4x4<f32>,
+    camera_position: vec4<f32>,
+}
+
+struct Lights {
+    cascades: array<mat4x4<f32>, MAX_CASCADES>,
+    cascade_splits: vec4<f32>,
+    cascade_texel_sizes: vec4<f32>,
+    sun_direction: vec4<f32>,
+    light_direction: vec4<f32>,
+    light_color: vec4<f32>,
+    sky_light: vec4<f32>,
+    zenith_color: vec4<f32>,
+    horizon_color: vec4<f32>,
+    cascade_count: u32,
+    depth_bias: f32,
+    normal_offset: f32,
+    pcf_radius: u32,
+}
+
+struct Fog {
+    color: vec4<f32>,
+    density: f32,
+    height_falloff: f32,
+    base_height: f32,
+    start_distance: f32,
+    end_distance: f32,
+    sky_amount: f32,
+    _pad: vec2<f32>,
+}
+
+struct PostEffects {
+    exposure: f32,
+    bloom_intensity: f32,
+    bloom_threshold: f32,
+    bloom_knee: f32,
+    tonemapper: u32,
+    encode_srgb: u32,
+    ambient_occlusion: u32,
+    _pad: u32,
+}
+
+struct AmbientOcclusion {
+    radius: f32,
+    intensity: f32,
+    bias: f32,
+    sample_count: u32,
+    blur_radius: u32,
+    blur_sharpness: f32,
+    _pad: vec2<u32>,
+}
+
+struct VertexBuffer {
+    position: vec4<f32>,
+    normal: vec4<f32>,
+    uv: vec2<f32>,
+    _pad: array<f32, 6>,
+}
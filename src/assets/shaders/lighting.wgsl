@@ -0,0 +1,55 @@
+// Lighting shared by the passes that shade world geometry. Expects `view`, `lights`, `fog`,
+// `shadow_map` and `shadow_sampler` to be bound by the including shader.
+
+// Fraction of sun or moon light reaching a point: 0 in shadow, 1 lit or past the last cascade.
+fn sun_visibility(world_position : vec3<f32>, normal : vec3<f32>, view_depth : f32) -> f32 {
+  var cascade = 0u;
+  while (cascade < lights.cascade_count && view_depth >= lights.cascade_splits[cascade]) {
+    cascade += 1u;
+  }
+  if (cascade >= lights.cascade_count) {
+    return 1.0;
+  }
+
+  // Pushing the lookup out along the normal keeps flat faces from shadowing themselves.
+  let offset = normal * lights.cascade_texel_sizes[cascade] * lights.normal_offset;
+  let light = lights.cascades[cascade] * vec4<f32>(world_position + offset, 1.0);
+  let uv = light.xy * vec2<f32>(0.5, -0.5) + 0.5;
+  if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || light.z > 1.0) {
+    return 1.0;
+  }
+
+  let texel = 1.0 / vec2<f32>(textureDimensions(shadow_map));
+  let radius = i32(lights.pcf_radius);
+  var lit = 0.0;
+  for (var y = -radius; y <= radius; y++) {
+    for (var x = -radius; x <= radius; x++) {
+      lit += textureSampleCompareLevel(
+        shadow_map,
+        shadow_sampler,
+        uv + vec2<f32>(f32(x), f32(y)) * texel,
+        cascade,
+        light.z - lights.depth_bias,
+      );
+    }
+  }
+  let width = f32(2 * radius + 1);
+  return lit / (width * width);
+}
+
+// How much of a surface is hidden by fog. Height fog is the density integrated along the
+// view ray, which thins out exponentially with height; past `start_distance` terrain also
+// fades out completely, so chunks at the edge of the render distance don't pop in.
+fn fog_amount(world_position : vec3<f32>) -> f32 {
+  let to_point = world_position - view.camera_position.xyz;
+  let distance = length(to_point);
+  let camera_density = fog.density * exp(-fog.height_falloff * (view.camera_position.y - fog.base_height));
+  let rise = fog.height_falloff * to_point.y;
+  var along_ray = 1.0;
+  if (abs(rise) > 0.0001) {
+    along_ray = (1.0 - exp(-rise)) / rise;
+  }
+  let height_fog = 1.0 - exp(-camera_density * distance * along_ray);
+  let fade = smoothstep(fog.start_distance, fog.end_distance, length(to_point.xz));
+  return clamp(max(height_fog, fade), 0.0, 1.0);
+}
@@ -0,0 +1,166 @@
+#include "common.wgsl"
+
+@binding(0) @group(0) var<uniform> effects : PostEffects;
+@binding(0) @group(1) var source : texture_2d<f32>;
+@binding(1) @group(1) var source_sampler : sampler;
+@binding(0) @group(2) var bloom : texture_2d<f32>;
+@binding(1) @group(2) var bloom_sampler : sampler;
+@binding(0) @group(3) var ao : texture_2d<f32>;
+@binding(1) @group(3) var ao_sampler : sampler;
+
+// Declaration order of `Tonemapper`.
+const TONEMAPPER_ACES : u32 = 0u;
+const TONEMAPPER_AGX : u32 = 1u;
+const TONEMAPPER_REINHARD : u32 = 2u;
+// Keeps single very bright pixels from blooming into flickering blobs.
+const MAX_BLOOM_SOURCE : f32 = 64.0;
+
+struct VertexOutput {
+  @builtin(position) clip_position : vec4<f32>,
+  @location(0) uv : vec2<f32>,
+};
+
+// One triangle covering the screen.
+@vertex
+fn main_vertex(@builtin(vertex_index) index : u32) -> VertexOutput {
+  let ndc = vec2<f32>(f32(index == 1u) * 4.0 - 1.0, f32(index == 2u) * 4.0 - 1.0);
+  var out : VertexOutput;
+  out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+  out.uv = ndc * vec2<f32>(0.5, -0.5) + 0.5;
+  return out;
+}
+
+fn sample_source(uv : vec2<f32>, offset : vec2<f32>) -> vec3<f32> {
+  let texel = 1.0 / vec2<f32>(textureDimensions(source));
+  return textureSampleLevel(source, source_sampler, uv + offset * texel, 0.0).rgb;
+}
+
+// 13-tap filter from the next larger level: overlapping 4x4 boxes, which avoids the
+// shimmering of a plain 2x2 box as things move.
+fn downsample(uv : vec2<f32>) -> vec3<f32> {
+  let a = sample_source(uv, vec2<f32>(-2.0, -2.0));
+  let b = sample_source(uv, vec2<f32>(0.0, -2.0));
+  let c = sample_source(uv, vec2<f32>(2.0, -2.0));
+  let d = sample_source(uv, vec2<f32>(-2.0, 0.0));
+  let e = sample_source(uv, vec2<f32>(0.0, 0.0));
+  let f = sample_source(uv, vec2<f32>(2.0, 0.0));
+  let g = sample_source(uv, vec2<f32>(-2.0, 2.0));
+  let h = sample_source(uv, vec2<f32>(0.0, 2.0));
+  let i = sample_source(uv, vec2<f32>(2.0, 2.0));
+  let j = sample_source(uv, vec2<f32>(-1.0, -1.0));
+  let k = sample_source(uv, vec2<f32>(1.0, -1.0));
+  let l = sample_source(uv, vec2<f32>(-1.0, 1.0));
+  let m = sample_source(uv, vec2<f32>(1.0, 1.0));
+  return e * 0.125
+    + (a + c + g + i) * 0.03125
+    + (b + d + f + h) * 0.0625
+    + (j + k + l + m) * 0.125;
+}
+
+// Keeps what is brighter than the threshold, with a quadratic knee instead of a hard cut.
+fn threshold(color : vec3<f32>) -> vec3<f32> {
+  let brightness = max(color.r, max(color.g, color.b));
+  let knee = effects.bloom_threshold * effects.bloom_knee;
+  var soft = clamp(brightness - effects.bloom_threshold + knee, 0.0, 2.0 * knee);
+  soft = soft * soft / (4.0 * knee + 0.00001);
+  let contribution = max(soft, brightness - effects.bloom_threshold) / max(brightness, 0.00001);
+  return color * contribution;
+}
+
+// First bloom level, from the scene.
+@fragment
+fn prefilter_fragment(input : VertexOutput) -> @location(0) vec4<f32> {
+  let color = min(downsample(input.uv) * effects.exposure, vec3<f32>(MAX_BLOOM_SOURCE));
+  return vec4<f32>(threshold(color), 1.0);
+}
+
+@fragment
+fn downsample_fragment(input : VertexOutput) -> @location(0) vec4<f32> {
+  return vec4<f32>(downsample(input.uv), 1.0);
+}
+
+// 3x3 tent filter from the next smaller level, added onto this one.
+@fragment
+fn upsample_fragment(input : VertexOutput) -> @location(0) vec4<f32> {
+  let color = sample_source(input.uv, vec2<f32>(0.0, 0.0)) * 4.0
+    + (sample_source(input.uv, vec2<f32>(-1.0, 0.0))
+      + sample_source(input.uv, vec2<f32>(1.0, 0.0))
+      + sample_source(input.uv, vec2<f32>(0.0, -1.0))
+      + sample_source(input.uv, vec2<f32>(0.0, 1.0))) * 2.0
+    + sample_source(input.uv, vec2<f32>(-1.0, -1.0))
+    + sample_source(input.uv, vec2<f32>(1.0, -1.0))
+    + sample_source(input.uv, vec2<f32>(-1.0, 1.0))
+    + sample_source(input.uv, vec2<f32>(1.0, 1.0));
+  return vec4<f32>(color / 16.0, 1.0);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve.
+fn aces(color : vec3<f32>) -> vec3<f32> {
+  let x = color * 0.6;
+  return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// Benjamin Wrensch's polynomial fit of the AgX base contrast curve.
+fn agx_contrast(x : vec3<f32>) -> vec3<f32> {
+  let x2 = x * x;
+  let x4 = x2 * x2;
+  return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn agx(color : vec3<f32>) -> vec3<f32> {
+  let inset = mat3x3<f32>(
+    0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+    0.0784335999999992, 0.878468636469772, 0.0784336,
+    0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+  );
+  let outset = mat3x3<f32>(
+    1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+    -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+    -0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+  );
+  let min_ev = -12.47393;
+  let max_ev = 4.026069;
+  var x = clamp(log2(max(inset * color, vec3<f32>(1e-10))), vec3<f32>(min_ev), vec3<f32>(max_ev));
+  x = agx_contrast((x - min_ev) / (max_ev - min_ev));
+  // The curve's output is display encoded; decode it back to linear.
+  return pow(max(outset * x, vec3<f32>(0.0)), vec3<f32>(2.2));
+}
+
+// Luminance based, so bright colors keep their hue.
+fn reinhard(color : vec3<f32>) -> vec3<f32> {
+  let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+  return color / (1.0 + luminance);
+}
+
+fn linear_to_srgb(color : vec3<f32>) -> vec3<f32> {
+  let low = color * 12.92;
+  let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+  return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn tonemap_fragment(input : VertexOutput) -> @location(0) vec4<f32> {
+  var scene = textureSampleLevel(source, source_sampler, input.uv, 0.0).rgb * effects.exposure;
+  if (effects.ambient_occlusion != 0u) {
+    scene *= textureSampleLevel(ao, ao_sampler, input.uv, 0.0).r;
+  }
+  let glow = textureSampleLevel(bloom, bloom_sampler, input.uv, 0.0).rgb;
+  let hdr = scene + glow * effects.bloom_intensity;
+
+  var color : vec3<f32>;
+  switch effects.tonemapper {
+    case TONEMAPPER_AGX: {
+      color = agx(hdr);
+    }
+    case TONEMAPPER_REINHARD: {
+      color = reinhard(hdr);
+    }
+    default: {
+      color = aces(hdr);
+    }
+  }
+  if (effects.encode_srgb != 0u) {
+    color = linear_to_srgb(color);
+  }
+  return vec4<f32>(color, 1.0);
+}
@@ -1,27 +1,15 @@
-
-struct Globals {
-    mouse_pos: vec2<f32>,
-    time_passed: f32,
-    frame_time: f32,
-    frame: u32,
-}
-
-struct View {
-    proj_view: mat4x4<f32>,
-    camera_position: vec4<f32>,
-}
-
-struct VertexBuffer {
-    position: vec4<f32>,
-    normal: vec4<f32>,
-    uv: vec2<f32>,
-    _pad: array<f32, 6>,
-}
-
+#include "common.wgsl"
+#include "lighting.wgsl"
 
 @binding(0) @group(0) var<uniform> globals : Globals;
 @binding(0) @group(1) var<uniform> view : View;
+@binding(1) @group(1) var<uniform> lights : Lights;
+@binding(2) @group(1) var shadow_map : texture_depth_2d_array;
+@binding(3) @group(1) var shadow_sampler : sampler_comparison;
+@binding(4) @group(1) var<uniform> fog : Fog;
 @binding(0) @group(2) var<storage> vertices : array<VertexBuffer>;
+@binding(0) @group(3) var atlas_texture : texture_2d<f32>;
+@binding(1) @group(3) var atlas_sampler : sampler;
 
 struct VertexInput {
   @location(0) position : vec4<f32>,
@@ -32,31 +20,79 @@ struct VertexInput {
 struct VertexOutput {
   @builtin(position) clip_position : vec4<f32>,
   @location(0) uv : vec2<f32>,
+  @location(1) normal : vec3<f32>,
+  @location(2) world_position : vec3<f32>,
+  // Distance along the view direction, which picks the shadow cascade.
+  @location(3) view_depth : f32,
 };
 
 @vertex
-fn main_vertex(input : VertexInput, @builtin(vertex_index) vid : u32) -> VertexOutput {
+fn main_vertex(input : VertexInput) -> VertexOutput {
   var out : VertexOutput;
+  out.clip_position = view.proj_view_rev_z * vec4<f32>(input.position.xyz, 1.0);
+  out.uv = input.uv;
+  out.normal = input.normal.xyz;
+  out.world_position = input.position.xyz;
+  out.view_depth = out.clip_position.w;
+  return out;
+}
 
-  let positions = array<vec2<f32>, 3>(
-      vec2<f32>(-1.0, -1.0),
-      vec2<f32>( 3.0, -1.0),
-      vec2<f32>(-1.0,  3.0),
-  );
+// Fixed per-face brightness so block edges still read in ambient light.
+fn face_shade(normal : vec3<f32>) -> f32 {
+  let n = abs(normal);
+  if (n.y > 0.5) {
+    return select(0.5, 1.0, normal.y > 0.0);
+  }
+  return select(0.8, 0.6, n.x > 0.5);
+}
 
-  let uvs = array<vec2<f32>, 3>(
-      vec2<f32>(0.0, 0.0),
-      vec2<f32>(2.0, 0.0),
-      vec2<f32>(0.0, 2.0),
-  );
+struct FragmentOutput {
+  @location(0) color : vec4<f32>,
+  // World-space normal scaled into 0..1, for screen-space effects.
+  @location(1) normal : vec4<f32>,
+};
+
+// Lit and fogged surface color; alpha passes through.
+fn shade(input : VertexOutput, color : vec4<f32>, normal : vec3<f32>) -> vec4<f32> {
+  let direct = max(dot(normal, lights.light_direction.xyz), 0.0);
+  let visibility = sun_visibility(input.world_position, normal, input.view_depth);
+  let light = lights.sky_light.rgb * face_shade(normal)
+    + lights.light_color.rgb * direct * visibility;
+  let lit = color.rgb * light;
+  return vec4<f32>(mix(lit, fog.color.rgb, fog_amount(input.world_position)), color.a);
+}
+
+// Faces drawn from both sides are lit from the side they're seen from.
+fn facing_normal(input : VertexOutput, front_facing : bool) -> vec3<f32> {
+  let normal = normalize(input.normal);
+  return select(-normal, normal, front_facing);
+}
 
-  out.clip_position = view.proj_view * vec4<f32>(positions[vid], 0.0, 1.0);
-  out.uv = uvs[vid];
+@fragment
+fn main_fragment(input : VertexOutput) -> FragmentOutput {
+  let color = textureSample(atlas_texture, atlas_sampler, input.uv);
+  let normal = normalize(input.normal);
+  var out : FragmentOutput;
+  out.color = shade(input, color, normal);
+  out.normal = vec4<f32>(normal * 0.5 + 0.5, 1.0);
+  return out;
+}
 
+@fragment
+fn cutout_fragment(input : VertexOutput, @builtin(front_facing) front_facing : bool) -> FragmentOutput {
+  let color = textureSample(atlas_texture, atlas_sampler, input.uv);
+  if (color.a < 0.5) {
+    discard;
+  }
+  let normal = facing_normal(input, front_facing);
+  var out : FragmentOutput;
+  out.color = shade(input, vec4<f32>(color.rgb, 1.0), normal);
+  out.normal = vec4<f32>(normal * 0.5 + 0.5, 1.0);
   return out;
 }
 
 @fragment
-fn main_fragment(input : VertexOutput) -> @location(0) vec4<f32> {
-  return vec4<f32>(1.0, input.uv.x * sin(globals.time_passed), input.uv.y * cos(globals.time_passed), 1.0);
-}
\ No newline at end of file
+fn translucent_fragment(input : VertexOutput, @builtin(front_facing) front_facing : bool) -> @location(0) vec4<f32> {
+  let color = textureSample(atlas_texture, atlas_sampler, input.uv);
+  return shade(input, color, facing_normal(input, front_facing));
+}
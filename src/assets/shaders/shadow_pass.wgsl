@@ -0,0 +1,31 @@
+// Depth-only pass rendering chunk meshes into one shadow cascade.
+@binding(0) @group(0) var<uniform> cascade : mat4x4<f32>;
+
+@binding(0) @group(1) var atlas_texture : texture_2d<f32>;
+@binding(1) @group(1) var atlas_sampler : sampler;
+
+@vertex
+fn main_vertex(@location(0) position : vec4<f32>) -> @builtin(position) vec4<f32> {
+  return cascade * vec4<f32>(position.xyz, 1.0);
+}
+
+struct CutoutOutput {
+  @builtin(position) position : vec4<f32>,
+  @location(0) uv : vec2<f32>,
+};
+
+// Cutout blocks only cast shadows where the atlas is opaque, as in the render pass.
+@vertex
+fn cutout_vertex(@location(0) position : vec4<f32>, @location(2) uv : vec2<f32>) -> CutoutOutput {
+  var output : CutoutOutput;
+  output.position = cascade * vec4<f32>(position.xyz, 1.0);
+  output.uv = uv;
+  return output;
+}
+
+@fragment
+fn cutout_fragment(input : CutoutOutput) {
+  if (textureSample(atlas_texture, atlas_sampler, input.uv).a < 0.5) {
+    discard;
+  }
+}
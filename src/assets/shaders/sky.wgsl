@@ -0,0 +1,19 @@
+// Sky color without the sun and moon discs or stars, for the sky itself and for what
+// reflects it. Expects `lights` and `fog` to be bound by the including shader.
+
+fn sky_gradient(ray : vec3<f32>) -> vec3<f32> {
+  let sun = lights.sun_direction.xyz;
+
+  // Gradient model: zenith to horizon, sharpening toward the horizon, darker below it.
+  let height = clamp(ray.y, 0.0, 1.0);
+  var color = mix(lights.horizon_color.rgb, lights.zenith_color.rgb, pow(height, 0.5));
+  color *= mix(0.6, 1.0, smoothstep(-0.3, 0.0, ray.y));
+  // The horizon takes the fog color, which faraway terrain fades into.
+  color = mix(fog.color.rgb, color, smoothstep(0.0, 0.1, ray.y));
+
+  // Forward scattering around the sun.
+  let day = smoothstep(-0.1, 0.05, sun.y);
+  let mu = max(dot(ray, sun), 0.0);
+  color += lights.horizon_color.rgb * (pow(mu, 8.0) * 0.4 + pow(mu, 64.0) * 0.6) * day;
+  return color;
+}
@@ -0,0 +1,79 @@
+#include "common.wgsl"
+#include "sky.wgsl"
+
+@binding(0) @group(0) var<uniform> globals : Globals;
+@binding(0) @group(1) var<uniform> view : View;
+@binding(1) @group(1) var<uniform> lights : Lights;
+@binding(4) @group(1) var<uniform> fog : Fog;
+
+const PI : f32 = 3.14159265;
+// Angular radii of the sun and moon discs, in radians.
+const SUN_RADIUS : f32 = 0.03;
+const MOON_RADIUS : f32 = 0.025;
+
+struct VertexOutput {
+  @builtin(position) clip_position : vec4<f32>,
+  @location(0) ndc : vec2<f32>,
+};
+
+// One triangle covering the screen, at the far plane (depth 0 with reverse-Z) so the depth
+// test only lets it through where no terrain was drawn.
+@vertex
+fn main_vertex(@builtin(vertex_index) index : u32) -> VertexOutput {
+  let ndc = vec2<f32>(f32(index == 1u) * 4.0 - 1.0, f32(index == 2u) * 4.0 - 1.0);
+  var out : VertexOutput;
+  out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+  out.ndc = ndc;
+  return out;
+}
+
+fn hash(cell : vec3<f32>) -> f32 {
+  let p = fract(cell * vec3<f32>(0.1031, 0.1030, 0.0973));
+  let q = p + dot(p, p.yxz + 33.33);
+  return fract((q.x + q.y) * q.z);
+}
+
+// Stars fixed to the sky, which turns with the sun around the axis of its path.
+fn stars(ray : vec3<f32>) -> f32 {
+  let angle = (globals.time_of_day - 0.25) * 2.0 * PI;
+  let path_up = vec3<f32>(0.0, cos(SUN_TILT), sin(SUN_TILT));
+  let axis = vec3<f32>(0.0, -sin(SUN_TILT), cos(SUN_TILT));
+  let along = dot(ray, vec3<f32>(1.0, 0.0, 0.0));
+  let up = dot(ray, path_up);
+  let sky_ray = vec3<f32>(
+    along * cos(angle) + up * sin(angle),
+    up * cos(angle) - along * sin(angle),
+    dot(ray, axis),
+  );
+
+  let scaled = sky_ray * 120.0;
+  let cell = floor(scaled);
+  let star = hash(cell);
+  if (star < 0.996) {
+    return 0.0;
+  }
+  let center = cell + 0.5 + (vec3<f32>(hash(cell + 1.0), hash(cell + 2.0), hash(cell + 3.0)) - 0.5) * 0.6;
+  let falloff = 1.0 - smoothstep(0.0, 0.25, length(scaled - center));
+  let twinkle = 0.75 + 0.25 * sin(globals.time_passed * (2.0 + star * 3.0) + star * 100.0);
+  return falloff * twinkle * (star - 0.996) / 0.004;
+}
+
+fn disc(ray : vec3<f32>, direction : vec3<f32>, radius : f32) -> f32 {
+  let cos_angle = dot(ray, direction);
+  return smoothstep(cos(radius * 1.15), cos(radius), cos_angle);
+}
+
+@fragment
+fn main_fragment(input : VertexOutput) -> @location(0) vec4<f32> {
+  let near = view.inv_proj_view_rev_z * vec4<f32>(input.ndc, 1.0, 1.0);
+  let ray = normalize(near.xyz / near.w - view.camera_position.xyz);
+  let sun = lights.sun_direction.xyz;
+  var color = sky_gradient(ray);
+
+  let above_horizon = smoothstep(-0.02, 0.02, ray.y);
+  let night = 1.0 - smoothstep(-0.2, 0.05, sun.y);
+  color += vec3<f32>(0.9, 0.9, 1.0) * stars(ray) * night * above_horizon;
+  color = mix(color, vec3<f32>(1.0, 0.95, 0.85) * 4.0, disc(ray, sun, SUN_RADIUS) * above_horizon);
+  color = mix(color, vec3<f32>(0.75, 0.78, 0.85), disc(ray, -sun, MOON_RADIUS) * above_horizon);
+  return vec4<f32>(mix(color, fog.color.rgb, fog.sky_amount), 1.0);
+}
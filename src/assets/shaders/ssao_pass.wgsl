@@ -0,0 +1,136 @@
+#include "common.wgsl"
+
+@binding(0) @group(0) var<uniform> view : View;
+@binding(1) @group(0) var<uniform> ao : AmbientOcclusion;
+// Bound as a float texture: loading from depth textures isn't supported everywhere.
+@binding(2) @group(0) var depth_texture : texture_2d<f32>;
+@binding(3) @group(0) var normal_texture : texture_2d<f32>;
+// The blur's input; only bound for the blur entry points.
+@binding(4) @group(0) var ao_texture : texture_2d<f32>;
+
+const GOLDEN_ANGLE : f32 = 2.39996323;
+
+struct VertexOutput {
+  @builtin(position) clip_position : vec4<f32>,
+  @location(0) ndc : vec2<f32>,
+};
+
+// One triangle covering the screen.
+@vertex
+fn main_vertex(@builtin(vertex_index) index : u32) -> VertexOutput {
+  let ndc = vec2<f32>(f32(index == 1u) * 4.0 - 1.0, f32(index == 2u) * 4.0 - 1.0);
+  var out : VertexOutput;
+  out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+  out.ndc = ndc;
+  return out;
+}
+
+fn load_depth(pixel : vec2<i32>) -> f32 {
+  let size = vec2<i32>(textureDimensions(depth_texture));
+  return textureLoad(depth_texture, clamp(pixel, vec2<i32>(0), size - 1), 0).r;
+}
+
+// World position of a reverse-Z depth sample. With an infinite reverse-Z projection depth is
+// `near / distance`, so the point is the near plane point scaled by `1 / depth`.
+fn world_position(ndc : vec2<f32>, depth : f32) -> vec3<f32> {
+  let near = view.inv_proj_view_rev_z * vec4<f32>(ndc, 1.0, 1.0);
+  let camera = view.camera_position.xyz;
+  return camera + (near.xyz / near.w - camera) / depth;
+}
+
+// Per-pixel rotation of the sample pattern, so too few samples turn into fine noise the blur
+// removes instead of banding.
+fn interleaved_gradient_noise(pixel : vec2<f32>) -> f32 {
+  return fract(52.9829189 * fract(dot(pixel, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+// Normal-oriented hemisphere sampling: each sample point around the surface counts as
+// occluded when the depth buffer shows geometry in front of it, within the radius.
+@fragment
+fn occlusion_fragment(input : VertexOutput) -> @location(0) vec4<f32> {
+  let pixel = vec2<i32>(input.clip_position.xy);
+  let depth = load_depth(pixel);
+  if (depth <= 0.0) {
+    return vec4<f32>(1.0);
+  }
+  let position = world_position(input.ndc, depth);
+  let normal = normalize(textureLoad(normal_texture, pixel, 0).xyz * 2.0 - 1.0);
+
+  // Orthonormal basis around the normal (Duff et al.).
+  let s = select(-1.0, 1.0, normal.z >= 0.0);
+  let a = -1.0 / (s + normal.z);
+  let b = normal.x * normal.y * a;
+  let tangent = vec3<f32>(1.0 + s * normal.x * normal.x * a, s * b, -s * normal.x);
+  let bitangent = vec3<f32>(b, s + normal.y * normal.y * a, -normal.y);
+
+  // Depth precision drops with distance, most visibly on ground seen at a grazing angle, so
+  // the bias grows with it.
+  let bias = ao.bias * distance(position, view.camera_position.xyz);
+  let rotation = interleaved_gradient_noise(input.clip_position.xy) * 6.28318531;
+  let count = max(ao.sample_count, 1u);
+  var occlusion = 0.0;
+  for (var i = 0u; i < count; i++) {
+    // Spiral over the hemisphere, with samples packed toward the center.
+    let t = (f32(i) + 0.5) / f32(count);
+    let cos_theta = sqrt(1.0 - t);
+    let sin_theta = sqrt(t);
+    let phi = f32(i) * GOLDEN_ANGLE + rotation;
+    let direction = tangent * cos(phi) * sin_theta + bitangent * sin(phi) * sin_theta + normal * cos_theta;
+    let scale = mix(0.1, 1.0, t * t);
+    let sample_position = position + direction * ao.radius * scale;
+
+    let clip = view.proj_view_rev_z * vec4<f32>(sample_position, 1.0);
+    if (clip.w <= 0.0) {
+      continue;
+    }
+    let sample_ndc = clip.xy / clip.w;
+    let size = vec2<f32>(textureDimensions(depth_texture));
+    let sample_pixel = (sample_ndc * vec2<f32>(0.5, -0.5) + 0.5) * size;
+    let scene_depth = load_depth(vec2<i32>(sample_pixel));
+    if (scene_depth <= 0.0) {
+      continue;
+    }
+    let scene_position = world_position(sample_ndc, scene_depth);
+    let camera = view.camera_position.xyz;
+    let in_front = distance(sample_position, camera) - distance(scene_position, camera);
+    let in_range = smoothstep(0.0, 1.0, ao.radius / max(distance(scene_position, position), 0.0001));
+    occlusion += select(0.0, in_range, in_front > bias);
+  }
+  let visibility = pow(1.0 - occlusion / f32(count), ao.intensity);
+  return vec4<f32>(visibility, visibility, visibility, 1.0);
+}
+
+// Separable Gaussian that skips taps across depth discontinuities, so occlusion doesn't
+// bleed from one surface onto another behind it.
+fn blur(pixel : vec2<i32>, offset : vec2<i32>) -> vec4<f32> {
+  let center_depth = load_depth(pixel);
+  if (center_depth <= 0.0) {
+    return vec4<f32>(1.0);
+  }
+  let size = vec2<i32>(textureDimensions(ao_texture));
+  let radius = i32(ao.blur_radius);
+  let sigma = max(f32(radius) * 0.5, 0.5);
+  var total = 0.0;
+  var weights = 0.0;
+  for (var i = -radius; i <= radius; i++) {
+    let tap = clamp(pixel + offset * i, vec2<i32>(0), size - 1);
+    let tap_depth = load_depth(tap);
+    // Depth is inversely proportional to distance, so this is the relative distance change.
+    let relative = abs(center_depth / max(tap_depth, 0.0000001) - 1.0);
+    let weight = exp(-f32(i * i) / (2.0 * sigma * sigma)) * exp(-relative * ao.blur_sharpness);
+    total += textureLoad(ao_texture, tap, 0).r * weight;
+    weights += weight;
+  }
+  let value = total / weights;
+  return vec4<f32>(value, value, value, 1.0);
+}
+
+@fragment
+fn blur_x_fragment(input : VertexOutput) -> @location(0) vec4<f32> {
+  return blur(vec2<i32>(input.clip_position.xy), vec2<i32>(1, 0));
+}
+
+@fragment
+fn blur_y_fragment(input : VertexOutput) -> @location(0) vec4<f32> {
+  return blur(vec2<i32>(input.clip_position.xy), vec2<i32>(0, 1));
+}
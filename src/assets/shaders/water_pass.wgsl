@@ -0,0 +1,184 @@
+#include "common.wgsl"
+#include "lighting.wgsl"
+#include "sky.wgsl"
+
+@binding(0) @group(0) var<uniform> globals : Globals;
+// The scene copy's sources; only bound for the copy entry points.
+@binding(1) @group(0) var color_source : texture_2d<f32>;
+@binding(2) @group(0) var depth_source : texture_2d<f32>;
+@binding(0) @group(1) var<uniform> view : View;
+@binding(1) @group(1) var<uniform> lights : Lights;
+@binding(2) @group(1) var shadow_map : texture_depth_2d_array;
+@binding(3) @group(1) var shadow_sampler : sampler_comparison;
+@binding(4) @group(1) var<uniform> fog : Fog;
+// The opaque scene as it was before the water was drawn, with its distance from the camera
+// in alpha.
+@binding(0) @group(2) var scene : texture_2d<f32>;
+@binding(1) @group(2) var scene_sampler : sampler;
+
+// Fraction of light absorbed per block travelled through water; red goes first.
+const ABSORPTION : vec3<f32> = vec3<f32>(0.45, 0.09, 0.06);
+// Light scattered back out of deep water, in full daylight.
+const SCATTER_COLOR : vec3<f32> = vec3<f32>(0.02, 0.09, 0.12);
+// Reflectance looking straight down at water.
+const FRESNEL_F0 : f32 = 0.02;
+// How far the scene behind is shifted by the waves, in screen fractions.
+const REFRACTION_STRENGTH : f32 = 0.03;
+const REFLECTION_STEPS : u32 = 32u;
+// Thickness a depth buffer sample is assumed to have when a reflected ray passes behind it.
+const REFLECTION_THICKNESS : f32 = 1.5;
+// Treated as the depth of water with nothing but sky behind it.
+const MAX_THICKNESS : f32 = 64.0;
+// Distance stored for the sky; about the largest a 16-bit float holds.
+const SKY_DISTANCE : f32 = 60000.0;
+
+struct CopyOutput {
+  @builtin(position) clip_position : vec4<f32>,
+};
+
+// One triangle covering the screen.
+@vertex
+fn copy_vertex(@builtin(vertex_index) index : u32) -> CopyOutput {
+  let ndc = vec2<f32>(f32(index == 1u) * 4.0 - 1.0, f32(index == 2u) * 4.0 - 1.0);
+  var out : CopyOutput;
+  out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+  return out;
+}
+
+// World position of a reverse-Z depth sample. With an infinite reverse-Z projection depth is
+// `near / distance`, so the point is the near plane point scaled by `1 / depth`.
+fn world_position(uv : vec2<f32>, depth : f32) -> vec3<f32> {
+  let ndc = (uv - 0.5) * vec2<f32>(2.0, -2.0);
+  let near = view.inv_proj_view_rev_z * vec4<f32>(ndc, 1.0, 1.0);
+  let camera = view.camera_position.xyz;
+  return camera + (near.xyz / near.w - camera) / depth;
+}
+
+// Copies the scene so the water can sample it while drawing over the original. Distance
+// stands in for depth, which 16 bits couldn't hold with any precision.
+@fragment
+fn copy_fragment(input : CopyOutput) -> @location(0) vec4<f32> {
+  let pixel = vec2<i32>(input.clip_position.xy);
+  let depth = textureLoad(depth_source, pixel, 0).r;
+  var scene_distance = SKY_DISTANCE;
+  if (depth > 0.0) {
+    let uv = input.clip_position.xy / vec2<f32>(textureDimensions(depth_source));
+    scene_distance = min(distance(world_position(uv, depth), view.camera_position.xyz), SKY_DISTANCE);
+  }
+  return vec4<f32>(textureLoad(color_source, pixel, 0).rgb, scene_distance);
+}
+
+struct VertexInput {
+  @location(0) position : vec4<f32>,
+  @location(1) normal : vec4<f32>,
+  @location(2) uv : vec2<f32>,
+};
+
+struct VertexOutput {
+  @builtin(position) clip_position : vec4<f32>,
+  @location(0) world_position : vec3<f32>,
+  @location(1) view_depth : f32,
+};
+
+@vertex
+fn main_vertex(input : VertexInput) -> VertexOutput {
+  var out : VertexOutput;
+  out.clip_position = view.proj_view_rev_z * vec4<f32>(input.position.xyz, 1.0);
+  out.world_position = input.position.xyz;
+  out.view_depth = out.clip_position.w;
+  return out;
+}
+
+// Surface normal of a few overlapping sine waves moving across the water.
+fn wave_normal(position : vec2<f32>, time : f32) -> vec3<f32> {
+  let directions = array<vec2<f32>, 4>(
+    vec2<f32>(0.8, 0.6),
+    vec2<f32>(-0.6, 0.8),
+    vec2<f32>(0.2, -0.98),
+    vec2<f32>(-0.9, -0.44),
+  );
+  let frequencies = vec4<f32>(0.9, 1.7, 2.9, 4.3);
+  let speeds = vec4<f32>(0.8, 1.1, 1.6, 2.1);
+  let amplitudes = vec4<f32>(0.04, 0.025, 0.012, 0.007);
+  var slope = vec2<f32>(0.0);
+  for (var i = 0; i < 4; i++) {
+    let phase = dot(directions[i], position) * frequencies[i] + time * speeds[i];
+    slope += directions[i] * amplitudes[i] * frequencies[i] * cos(phase);
+  }
+  return normalize(vec3<f32>(-slope.x, 1.0, -slope.y));
+}
+
+// Distance from the camera to the scene at `uv`.
+fn scene_distance(uv : vec2<f32>) -> f32 {
+  let size = vec2<i32>(textureDimensions(scene));
+  let pixel = clamp(vec2<i32>(uv * vec2<f32>(size)), vec2<i32>(0), size - 1);
+  return textureLoad(scene, pixel, 0).a;
+}
+
+// Marches the reflected ray through the scene copy. Returns the color it hits and how much
+// to trust it, fading out toward the screen edges where the scene is missing.
+fn screen_space_reflection(origin : vec3<f32>, ray : vec3<f32>) -> vec4<f32> {
+  let camera = view.camera_position.xyz;
+  var step = 0.25;
+  var position = origin;
+  for (var i = 0u; i < REFLECTION_STEPS; i++) {
+    position += ray * step;
+    step *= 1.15;
+    let clip = view.proj_view_rev_z * vec4<f32>(position, 1.0);
+    if (clip.w <= 0.0) {
+      break;
+    }
+    let uv = clip.xy / clip.w * vec2<f32>(0.5, -0.5) + 0.5;
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0))) {
+      break;
+    }
+    let behind = distance(position, camera) - scene_distance(uv);
+    if (behind > 0.0 && behind < REFLECTION_THICKNESS + step) {
+      let edge = min(min(uv.x, 1.0 - uv.x), min(uv.y, 1.0 - uv.y));
+      let weight = smoothstep(0.0, 0.1, edge);
+      return vec4<f32>(textureSampleLevel(scene, scene_sampler, uv, 0.0).rgb, weight);
+    }
+  }
+  return vec4<f32>(0.0);
+}
+
+@fragment
+fn main_fragment(input : VertexOutput, @builtin(front_facing) front_facing : bool) -> @location(0) vec4<f32> {
+  let camera = view.camera_position.xyz;
+  let to_surface = input.world_position - camera;
+  let surface_distance = length(to_surface);
+  let view_ray = to_surface / surface_distance;
+  let uv = input.clip_position.xy / vec2<f32>(textureDimensions(scene));
+  var normal = wave_normal(input.world_position.xz, globals.time_passed);
+  if (!front_facing) {
+    normal = -normal;
+  }
+
+  // Refraction: the scene behind, shifted by the waves unless that would pick up something
+  // in front of the water.
+  var refracted_uv = clamp(uv + normal.xz * REFRACTION_STRENGTH, vec2<f32>(0.0), vec2<f32>(1.0));
+  if (scene_distance(refracted_uv) < surface_distance) {
+    refracted_uv = uv;
+  }
+  var color = textureSampleLevel(scene, scene_sampler, refracted_uv, 0.0).rgb;
+
+  // From below there is only air behind the surface, the underwater fog doing the rest.
+  if (front_facing) {
+    let thickness = min(scene_distance(refracted_uv) - surface_distance, MAX_THICKNESS);
+    let ambient = lights.sky_light.rgb + lights.light_color.rgb * max(lights.light_direction.y, 0.0);
+    let transmittance = exp(-ABSORPTION * max(thickness, 0.0));
+    color = color * transmittance + SCATTER_COLOR * ambient * (1.0 - transmittance);
+
+    var reflected = reflect(view_ray, normal);
+    reflected = normalize(vec3<f32>(reflected.x, max(reflected.y, 0.01), reflected.z));
+    let hit = screen_space_reflection(input.world_position, reflected);
+    let reflection = mix(sky_gradient(reflected), hit.rgb, hit.a);
+
+    let fresnel = FRESNEL_F0 + (1.0 - FRESNEL_F0) * pow(1.0 - max(dot(-view_ray, normal), 0.0), 5.0);
+    let visibility = sun_visibility(input.world_position, vec3<f32>(0.0, 1.0, 0.0), input.view_depth);
+    let highlight = pow(max(dot(reflected, lights.light_direction.xyz), 0.0), 400.0) * 8.0;
+    color = mix(color, reflection, fresnel) + lights.light_color.rgb * highlight * visibility;
+  }
+
+  return vec4<f32>(mix(color, fog.color.rgb, fog_amount(input.world_position)), 1.0);
+}
@@ -0,0 +1,203 @@
+use std::{fs, io, path::Path};
+
+use glam::Vec3;
+use serde_json::json;
+
+use crate::formats::{image::encode_png, mesh_export::ExportMesh};
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+const FLOAT: u32 = 5126;
+const ARRAY_BUFFER: u32 = 34962;
+const NEAREST: u32 = 9728;
+const CLAMP_TO_EDGE: u32 = 33071;
+
+pub fn save_glb(mesh: &ExportMesh, path: &Path) -> io::Result<()> {
+    fs::write(path, glb_bytes(mesh)?)
+}
+
+/// Binary glTF 2.0 with a single non-indexed triangle primitive and the atlas embedded as a PNG.
+pub fn glb_bytes(mesh: &ExportMesh) -> io::Result<Vec<u8>> {
+    let vertex_count = mesh.vertices.len();
+    let mut bin = Vec::new();
+    let mut views = Vec::new();
+    let mut push_view = |bin: &mut Vec<u8>, data: &[u8], target: Option<u32>| {
+        while !bin.len().is_multiple_of(4) {
+            bin.push(0);
+        }
+        let mut view = json!({ "buffer": 0, "byteOffset": bin.len(), "byteLength": data.len() });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        views.push(view);
+        bin.extend_from_slice(data);
+        views.len() - 1
+    };
+
+    let positions: Vec<f32> = mesh
+        .vertices
+        .iter()
+        .flat_map(|vertex| vertex.position.to_array())
+        .collect();
+    let normals: Vec<f32> = mesh
+        .vertices
+        .iter()
+        .flat_map(|vertex| vertex.normal.to_array())
+        .collect();
+    let uvs: Vec<f32> = mesh
+        .vertices
+        .iter()
+        .flat_map(|vertex| vertex.uv.to_array())
+        .collect();
+    let png = encode_png(mesh.atlas.width, mesh.atlas.height, &mesh.atlas.pixels)?;
+
+    let position_view = push_view(
+        &mut bin,
+        bytemuck::cast_slice(&positions),
+        Some(ARRAY_BUFFER),
+    );
+    let normal_view = push_view(&mut bin, bytemuck::cast_slice(&normals), Some(ARRAY_BUFFER));
+    let uv_view = push_view(&mut bin, bytemuck::cast_slice(&uvs), Some(ARRAY_BUFFER));
+    let image_view = push_view(&mut bin, &png, None);
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+
+    let (min, max) = mesh.vertices.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), vertex| {
+            let position = Vec3::from(vertex.position);
+            (min.min(position), max.max(position))
+        },
+    );
+    let (min, max) = if vertex_count == 0 {
+        (Vec3::ZERO, Vec3::ZERO)
+    } else {
+        (min, max)
+    };
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": env!("CARGO_PKG_NAME") },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "name": "world" }],
+        "meshes": [{
+            "primitives": [{
+                "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+                "material": 0,
+                "mode": 4,
+            }],
+        }],
+        "materials": [{
+            "name": "atlas",
+            "pbrMetallicRoughness": {
+                "baseColorTexture": { "index": 0 },
+                "metallicFactor": 0.0,
+                "roughnessFactor": 1.0,
+            },
+        }],
+        "textures": [{ "source": 0, "sampler": 0 }],
+        "samplers": [{
+            "magFilter": NEAREST,
+            "minFilter": NEAREST,
+            "wrapS": CLAMP_TO_EDGE,
+            "wrapT": CLAMP_TO_EDGE,
+        }],
+        "images": [{ "bufferView": image_view, "mimeType": "image/png" }],
+        "accessors": [
+            {
+                "bufferView": position_view,
+                "componentType": FLOAT,
+                "count": vertex_count,
+                "type": "VEC3",
+                "min": min.to_array(),
+                "max": max.to_array(),
+            },
+            { "bufferView": normal_view, "componentType": FLOAT, "count": vertex_count, "type": "VEC3" },
+            { "bufferView": uv_view, "componentType": FLOAT, "count": vertex_count, "type": "VEC2" },
+        ],
+        "bufferViews": views,
+        "buffers": [{ "byteLength": bin.len() }],
+    });
+
+    let mut json_bytes = serde_json::to_vec(&document).map_err(io::Error::other)?;
+    while !json_bytes.len().is_multiple_of(4) {
+        json_bytes.push(b' ');
+    }
+
+    let total = 12 + 8 + json_bytes.len() + 8 + bin.len();
+    let mut glb = Vec::with_capacity(total);
+    for word in [
+        GLB_MAGIC,
+        2,
+        total as u32,
+        json_bytes.len() as u32,
+        CHUNK_JSON,
+    ] {
+        glb.extend_from_slice(&word.to_le_bytes());
+    }
+    glb.extend_from_slice(&json_bytes);
+    for word in [bin.len() as u32, CHUNK_BIN] {
+        glb.extend_from_slice(&word.to_le_bytes());
+    }
+    glb.extend_from_slice(&bin);
+    Ok(glb)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec3;
+
+    use super::*;
+    use crate::{
+        formats::obj::obj_text,
+        world::{block::BlockId, world::World},
+    };
+
+    fn mesh() -> ExportMesh {
+        let mut world = World::new();
+        world.set_block(IVec3::new(0, 0, 0), BlockId::STONE);
+        world.set_block(IVec3::new(0, 1, 0), BlockId::from_rgb([250, 10, 10]));
+        ExportMesh::from_world(&world)
+    }
+
+    #[test]
+    fn glb_layout_is_consistent() {
+        let glb = glb_bytes(&mesh()).unwrap();
+        let word = |at: usize| u32::from_le_bytes(glb[at..at + 4].try_into().unwrap()) as usize;
+        assert_eq!(word(0), GLB_MAGIC as usize);
+        assert_eq!(word(8), glb.len());
+
+        let json_len = word(12);
+        let document: serde_json::Value = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+        assert_eq!(document["accessors"][0]["count"], 10 * 6);
+        assert_eq!(document["accessors"][0]["max"], json!([1.0, 2.0, 1.0]));
+
+        let bin_len = word(20 + json_len);
+        assert_eq!(
+            bin_len,
+            document["buffers"][0]["byteLength"].as_u64().unwrap() as usize
+        );
+        assert_eq!(20 + json_len + 8 + bin_len, glb.len());
+        for view in document["bufferViews"].as_array().unwrap() {
+            let end = view["byteOffset"].as_u64().unwrap() + view["byteLength"].as_u64().unwrap();
+            assert!(end as usize <= bin_len);
+        }
+    }
+
+    #[test]
+    fn obj_references_every_vertex() {
+        let text = obj_text(&mesh(), "mesh.mtl");
+        assert_eq!(
+            text.lines().filter(|line| line.starts_with("v ")).count(),
+            60
+        );
+        assert_eq!(
+            text.lines().filter(|line| line.starts_with("f ")).count(),
+            20
+        );
+        assert!(text.contains("f 58/58/58 59/59/59 60/60/60"));
+    }
+}
@@ -0,0 +1,92 @@
+use std::{fs, io, path::Path};
+
+/// Encodes tightly packed RGBA8 pixels as a PNG.
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(rgba))
+        .map_err(io::Error::other)?;
+    Ok(bytes)
+}
+
+pub fn save_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+    fs::write(path, encode_png(width, height, rgba)?)
+}
+
+/// Decodes a PNG into its size and RGBA8 pixels.
+pub fn load_png(path: &Path) -> io::Result<(u32, u32, Vec<u8>)> {
+    let decoder = png::Decoder::new(fs::File::open(path)?);
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(io::Error::other)?;
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(io::Error::other(format!(
+            "{} is {:?} {:?}, expected 8-bit RGBA",
+            path.display(),
+            info.color_type,
+            info.bit_depth
+        )));
+    }
+    pixels.truncate(info.buffer_size());
+    Ok((info.width, info.height, pixels))
+}
+
+/// Tightly packed RGBA8 pixels, row by row from the top left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn new(width: u32, height: u32) -> Self {
+        RgbaImage {
+            width,
+            height,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let (width, height, pixels) = load_png(path)?;
+        Ok(RgbaImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        save_png(path, self.width, self.height, &self.pixels)
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = ((y * self.width + x) * 4) as usize;
+        self.pixels[index..index + 4].try_into().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_round_trips() {
+        let mut image = RgbaImage::new(3, 2);
+        image.pixels[4..8].copy_from_slice(&[10, 20, 30, 255]);
+        let path = std::env::temp_dir().join(format!("voxel-image-{}.png", std::process::id()));
+        image.save(&path).unwrap();
+        let loaded = RgbaImage::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, image);
+        assert_eq!(loaded.pixel(1, 0), [10, 20, 30, 255]);
+    }
+}
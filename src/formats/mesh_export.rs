@@ -0,0 +1,86 @@
+use std::{collections::HashSet, ffi::OsStr, fmt, io, path::Path};
+
+use glam::IVec3;
+
+use crate::{
+    formats::{gltf, obj},
+    graphics::structures::VertexBuffer,
+    meshing::{atlas::TextureAtlas, mesher::mesh_chunk, models},
+    world::world::World,
+};
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    UnknownFormat(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(err) => write!(f, "mesh export io error: {err}"),
+            ExportError::UnknownFormat(extension) => {
+                write!(
+                    f,
+                    "unknown mesh format {extension:?}, expected .glb or .obj"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<io::Error> for ExportError {
+    fn from(err: io::Error) -> Self {
+        ExportError::Io(err)
+    }
+}
+
+/// Triangle soup of a set of chunks, in the renderer's vertex format, plus the atlas its UVs
+/// point into.
+#[derive(Debug)]
+pub struct ExportMesh {
+    pub vertices: Vec<VertexBuffer>,
+    pub atlas: TextureAtlas,
+}
+
+impl ExportMesh {
+    /// Meshes the chunks in the inclusive chunk range `min..=max`.
+    pub fn from_region(world: &World, min: IVec3, max: IVec3) -> Self {
+        let chunk_positions: Vec<IVec3> = world
+            .chunks()
+            .map(|(pos, _)| *pos)
+            .filter(|pos| pos.cmpge(min).all() && pos.cmple(max).all())
+            .collect();
+
+        let blocks: HashSet<_> = chunk_positions
+            .iter()
+            .flat_map(|pos| world.chunk(*pos).unwrap().blocks().iter().copied())
+            .collect();
+        let atlas = TextureAtlas::new(blocks.into_iter().flat_map(models::textures));
+
+        let mut sorted = chunk_positions;
+        sorted.sort_by_key(|pos| pos.to_array());
+        let vertices = sorted
+            .into_iter()
+            .flat_map(|pos| mesh_chunk(world, pos, &atlas).into_vertices())
+            .collect();
+        ExportMesh { vertices, atlas }
+    }
+
+    /// Meshes every loaded chunk.
+    pub fn from_world(world: &World) -> Self {
+        Self::from_region(world, IVec3::MIN, IVec3::MAX)
+    }
+
+    /// Writes `.glb` or `.obj` (with `.mtl` and `.png` siblings) depending on the extension.
+    pub fn save(&self, path: &Path) -> Result<(), ExportError> {
+        match path.extension().and_then(OsStr::to_str) {
+            Some("glb") => gltf::save_glb(self, path)?,
+            Some("obj") => obj::save_obj(self, path)?,
+            other => return Err(ExportError::UnknownFormat(other.unwrap_or("").to_string())),
+        }
+        Ok(())
+    }
+}
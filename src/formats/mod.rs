@@ -0,0 +1,5 @@
+pub mod gltf;
+pub mod image;
+pub mod mesh_export;
+pub mod obj;
+pub mod vox;
@@ -0,0 +1,49 @@
+use std::{fmt::Write, fs, io, path::Path};
+
+use crate::formats::{image::save_png, mesh_export::ExportMesh};
+
+/// Writes a Wavefront OBJ with an `.mtl` material and a `.png` atlas next to it, all named
+/// after `path`.
+pub fn save_obj(mesh: &ExportMesh, path: &Path) -> io::Result<()> {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("mesh");
+    let mtl_name = format!("{stem}.mtl");
+    let png_name = format!("{stem}.png");
+
+    fs::write(path, obj_text(mesh, &mtl_name))?;
+    fs::write(path.with_file_name(&mtl_name), mtl_text(&png_name))?;
+    save_png(
+        &path.with_file_name(&png_name),
+        mesh.atlas.width,
+        mesh.atlas.height,
+        &mesh.atlas.pixels,
+    )
+}
+
+pub fn obj_text(mesh: &ExportMesh, mtl_name: &str) -> String {
+    let mut text = format!("mtllib {mtl_name}\no world\n");
+    for vertex in &mesh.vertices {
+        let p = vertex.position;
+        writeln!(text, "v {} {} {}", p.x, p.y, p.z).unwrap();
+    }
+    // OBJ puts the texture origin at the bottom left.
+    for vertex in &mesh.vertices {
+        writeln!(text, "vt {} {}", vertex.uv.x, 1.0 - vertex.uv.y).unwrap();
+    }
+    for vertex in &mesh.vertices {
+        let n = vertex.normal;
+        writeln!(text, "vn {} {} {}", n.x, n.y, n.z).unwrap();
+    }
+    text.push_str("usemtl atlas\n");
+    for triangle in 0..mesh.vertices.len() / 3 {
+        let [a, b, c] = [1, 2, 3].map(|corner| triangle * 3 + corner);
+        writeln!(text, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}").unwrap();
+    }
+    text
+}
+
+fn mtl_text(png_name: &str) -> String {
+    format!("newmtl atlas\nKa 1 1 1\nKd 1 1 1\nKs 0 0 0\nd 1\nillum 1\nmap_Kd {png_name}\n")
+}
@@ -0,0 +1,850 @@
+//! MagicaVoxel `.vox` reading and writing.
+//!
+//! MagicaVoxel is right-handed with Z up; a voxel at `(x, y, z)` becomes the block at
+//! `(x, z, -y - 1)` in our Y-up space.
+
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+use glam::{IVec3, UVec3};
+
+use crate::world::{block::BlockId, world::World};
+
+const VERSION: i32 = 150;
+/// `_r` value for the identity rotation.
+pub const IDENTITY_ROTATION: u8 = 0b0000100;
+pub const MAX_MODEL_SIZE: u32 = 256;
+/// Scene graph depth past which a file is taken to be cyclic.
+const MAX_SCENE_DEPTH: usize = 64;
+/// Nodes visited while flattening a scene, counting a shared node once per path to it. Keeps
+/// nodes that reference the same child many times from expanding without end.
+const MAX_SCENE_VISITS: usize = 1 << 16;
+/// Model instances a scene may place.
+const MAX_INSTANCES: usize = 1 << 16;
+
+#[derive(Debug)]
+pub enum VoxError {
+    Io(io::Error),
+    Invalid(String),
+    TooManyColors(usize),
+    TooLarge(UVec3),
+}
+
+impl fmt::Display for VoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoxError::Io(err) => write!(f, "vox io error: {err}"),
+            VoxError::Invalid(reason) => write!(f, "invalid vox file: {reason}"),
+            VoxError::TooManyColors(count) => {
+                write!(
+                    f,
+                    "{count} distinct blocks don't fit in a 255 color palette"
+                )
+            }
+            VoxError::TooLarge(size) => {
+                write!(f, "model size {size} exceeds {MAX_MODEL_SIZE} per axis")
+            }
+        }
+    }
+}
+
+impl std::error::Error for VoxError {}
+
+impl From<io::Error> for VoxError {
+    fn from(err: io::Error) -> Self {
+        VoxError::Io(err)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VoxVoxel {
+    pub pos: [u8; 3],
+    /// 1..=255, index into the palette shifted by one.
+    pub color_index: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VoxModel {
+    pub size: UVec3,
+    pub voxels: Vec<VoxVoxel>,
+}
+
+/// A placement of a model in the scene, flattened from the scene graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VoxInstance {
+    pub model: usize,
+    pub translation: IVec3,
+    /// Packed rotation in MagicaVoxel's `_r` encoding.
+    pub rotation: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VoxFile {
+    pub models: Vec<VoxModel>,
+    /// `palette[i]` is the RGBA color of color index `i + 1`.
+    pub palette: Vec<[u8; 4]>,
+    pub instances: Vec<VoxInstance>,
+}
+
+impl VoxFile {
+    pub fn load(path: &Path) -> Result<Self, VoxError> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), VoxError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, VoxError> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(4)? != b"VOX " {
+            return Err(VoxError::Invalid("missing VOX header".to_string()));
+        }
+        let _version = reader.i32()?;
+
+        let main = reader.chunk()?;
+        if main.id != *b"MAIN" || !main.content.is_empty() {
+            return Err(VoxError::Invalid("missing MAIN chunk".to_string()));
+        }
+
+        let mut models = Vec::new();
+        let mut size = None;
+        let mut palette = None;
+        let mut nodes = HashMap::new();
+
+        let mut children = Reader {
+            bytes: main.children,
+            position: 0,
+        };
+        while !children.is_empty() {
+            let RawChunk { id, content, .. } = children.chunk()?;
+            let mut content = Reader {
+                bytes: content,
+                position: 0,
+            };
+            match &id {
+                b"SIZE" => {
+                    size = Some(UVec3::new(content.u32()?, content.u32()?, content.u32()?));
+                }
+                b"XYZI" => {
+                    let size = size
+                        .take()
+                        .ok_or_else(|| VoxError::Invalid("XYZI without SIZE".to_string()))?;
+                    let count = content.u32()? as usize;
+                    let voxels = (0..count)
+                        .map(|_| {
+                            let data = content.take(4)?;
+                            Ok(VoxVoxel {
+                                pos: [data[0], data[1], data[2]],
+                                color_index: data[3],
+                            })
+                        })
+                        .collect::<Result<_, VoxError>>()?;
+                    models.push(VoxModel { size, voxels });
+                }
+                b"RGBA" => {
+                    palette = Some(
+                        (0..256)
+                            .map(|_| content.take(4).map(|c| [c[0], c[1], c[2], c[3]]))
+                            .collect::<Result<Vec<_>, _>>()?,
+                    );
+                }
+                b"nTRN" | b"nGRP" | b"nSHP" => {
+                    let node_id = content.i32()?;
+                    nodes.insert(node_id, SceneNode::parse(&id, &mut content)?);
+                }
+                _ => {}
+            }
+        }
+
+        let instances = if nodes.is_empty() {
+            (0..models.len())
+                .map(|model| VoxInstance {
+                    model,
+                    translation: IVec3::ZERO,
+                    rotation: IDENTITY_ROTATION,
+                })
+                .collect()
+        } else {
+            let mut instances = Vec::new();
+            flatten_scene(
+                &nodes,
+                0,
+                IVec3::ZERO,
+                Rotation::IDENTITY,
+                &mut instances,
+                0,
+                &mut 0,
+            )?;
+            instances
+        };
+        if let Some(instance) = instances.iter().find(|i| i.model >= models.len()) {
+            return Err(VoxError::Invalid(format!(
+                "scene references missing model {}",
+                instance.model
+            )));
+        }
+
+        Ok(VoxFile {
+            models,
+            palette: palette.unwrap_or_else(default_palette),
+            instances,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut children = Vec::new();
+        for model in &self.models {
+            let mut size = Vec::new();
+            for axis in model.size.to_array() {
+                size.extend_from_slice(&axis.to_le_bytes());
+            }
+            write_chunk(&mut children, b"SIZE", &size, &[]);
+
+            let mut xyzi = (model.voxels.len() as u32).to_le_bytes().to_vec();
+            for voxel in &model.voxels {
+                xyzi.extend_from_slice(&voxel.pos);
+                xyzi.push(voxel.color_index);
+            }
+            write_chunk(&mut children, b"XYZI", &xyzi, &[]);
+        }
+
+        let implicit_scene = self.instances.len() == self.models.len()
+            && self.instances.iter().enumerate().all(|(i, instance)| {
+                instance.model == i
+                    && instance.translation == IVec3::ZERO
+                    && instance.rotation == IDENTITY_ROTATION
+            });
+        if !implicit_scene {
+            write_scene(&mut children, &self.instances);
+        }
+
+        let mut rgba = Vec::with_capacity(1024);
+        for i in 0..256 {
+            rgba.extend_from_slice(&self.palette.get(i).copied().unwrap_or_default());
+        }
+        write_chunk(&mut children, b"RGBA", &rgba, &[]);
+
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        write_chunk(&mut bytes, b"MAIN", &[], &children);
+        bytes
+    }
+
+    /// Places every instance into `world` so that the scene's minimum corner lands on
+    /// `origin`. Palette colors become the registered block of that exact color, or a color
+    /// block otherwise. Nothing is placed if the scene reaches past the world's coordinates.
+    pub fn import(&self, world: &mut World, origin: IVec3) -> Result<(), VoxError> {
+        let blocks: Vec<BlockId> = self
+            .palette
+            .iter()
+            .map(|color| {
+                BlockId::find_by_color(*color)
+                    .unwrap_or_else(|| BlockId::from_rgb([color[0], color[1], color[2]]))
+            })
+            .collect();
+
+        let out_of_range = || VoxError::Invalid("scene reaches past the world".to_string());
+        let placed: Vec<(IVec3, BlockId)> = self
+            .instances
+            .iter()
+            .flat_map(|instance| {
+                let model = &self.models[instance.model];
+                let rotation = Rotation::decode(instance.rotation);
+                let center = (model.size / 2).as_ivec3();
+                model.voxels.iter().map(move |voxel| {
+                    let local = IVec3::from(voxel.pos.map(i32::from)) - center;
+                    let pos = rotation.apply(local).checked_add(instance.translation);
+                    // Z up to Y up; -1 - y can't overflow.
+                    let pos = pos.map(|pos| IVec3::new(pos.x, pos.z, -1 - pos.y));
+                    (pos, voxel.color_index)
+                })
+            })
+            .filter(|(_, color_index)| *color_index > 0)
+            .map(|(pos, color_index)| Ok((pos.ok_or_else(out_of_range)?, color_index)))
+            .map(|placed| placed.map(|(pos, color_index)| (pos, blocks[color_index as usize - 1])))
+            .collect::<Result<_, VoxError>>()?;
+
+        let min = placed
+            .iter()
+            .map(|(pos, _)| *pos)
+            .reduce(IVec3::min)
+            .unwrap_or_default();
+        let placed = placed
+            .into_iter()
+            .map(|(pos, block)| {
+                let pos = pos.checked_sub(min).and_then(|pos| pos.checked_add(origin));
+                Ok((pos.ok_or_else(out_of_range)?, block))
+            })
+            .collect::<Result<Vec<_>, VoxError>>()?;
+        for (pos, block) in placed {
+            world.set_block(pos, block);
+        }
+        Ok(())
+    }
+
+    /// Captures the blocks in the inclusive box `min..=max` as a single-model file.
+    pub fn export(world: &World, min: IVec3, max: IVec3) -> Result<Self, VoxError> {
+        let extent = (max - min + IVec3::ONE).max(IVec3::ZERO).as_uvec3();
+        let size = UVec3::new(extent.x, extent.z, extent.y);
+        if size.cmpgt(UVec3::splat(MAX_MODEL_SIZE)).any() {
+            return Err(VoxError::TooLarge(size));
+        }
+
+        let mut palette_lookup: HashMap<BlockId, u8> = HashMap::new();
+        let mut palette = Vec::new();
+        let mut voxels = Vec::new();
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    let block = world.get_block(IVec3::new(x, y, z));
+                    if block.is_air() {
+                        continue;
+                    }
+                    let color_index = match palette_lookup.get(&block) {
+                        Some(index) => *index,
+                        None => {
+                            if palette.len() == 255 {
+                                return Err(VoxError::TooManyColors(palette_lookup.len() + 1));
+                            }
+                            palette.push(block.color());
+                            palette_lookup.insert(block, palette.len() as u8);
+                            palette.len() as u8
+                        }
+                    };
+                    voxels.push(VoxVoxel {
+                        pos: [(x - min.x) as u8, (max.z - z) as u8, (y - min.y) as u8],
+                        color_index,
+                    });
+                }
+            }
+        }
+        palette.resize(256, [0, 0, 0, 0]);
+
+        Ok(VoxFile {
+            models: vec![VoxModel { size, voxels }],
+            palette,
+            instances: vec![VoxInstance {
+                model: 0,
+                translation: IVec3::ZERO,
+                rotation: IDENTITY_ROTATION,
+            }],
+        })
+    }
+}
+
+/// Grayscale ramp used when a file has no `RGBA` chunk.
+fn default_palette() -> Vec<[u8; 4]> {
+    (0..256)
+        .map(|i| {
+            let value = 255 - i as u8;
+            [value, value, value, 255]
+        })
+        .collect()
+}
+
+/// Signed permutation matrix, stored by rows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rotation {
+    pub rows: [IVec3; 3],
+}
+
+impl Rotation {
+    pub const IDENTITY: Rotation = Rotation {
+        rows: [IVec3::X, IVec3::Y, IVec3::Z],
+    };
+
+    /// Expands MagicaVoxel's packed `_r` byte. Each row has a single non-zero entry: bits 0-1
+    /// and 2-3 give its column for rows 0 and 1, row 2 takes the remaining column, and bits 4-6
+    /// are the signs.
+    pub fn decode(packed: u8) -> Self {
+        let first = (packed & 0b11).min(2) as usize;
+        let second = ((packed >> 2) & 0b11).min(2) as usize;
+        let third = (3usize.saturating_sub(first + second)).min(2);
+        let sign = |bit: u8| if packed & (1 << bit) != 0 { -1 } else { 1 };
+
+        let mut rows = [IVec3::ZERO; 3];
+        rows[0][first] = sign(4);
+        rows[1][second] = sign(5);
+        rows[2][third] = sign(6);
+        Rotation { rows }
+    }
+
+    pub fn encode(&self) -> u8 {
+        let column = |row: IVec3| (0..3).find(|&i| row[i] != 0).unwrap_or(0) as u8;
+        let negative = |row: IVec3| (row.element_sum() < 0) as u8;
+        column(self.rows[0])
+            | (column(self.rows[1]) << 2)
+            | (negative(self.rows[0]) << 4)
+            | (negative(self.rows[1]) << 5)
+            | (negative(self.rows[2]) << 6)
+    }
+
+    pub fn apply(&self, v: IVec3) -> IVec3 {
+        IVec3::new(
+            self.rows[0].dot(v),
+            self.rows[1].dot(v),
+            self.rows[2].dot(v),
+        )
+    }
+
+    /// Like `apply`, or `None` if a coordinate overflows.
+    pub fn checked_apply(&self, v: IVec3) -> Option<IVec3> {
+        let row =
+            |row: IVec3| (0..3).try_fold(0i32, |sum, i| sum.checked_add(row[i].checked_mul(v[i])?));
+        Some(IVec3::new(
+            row(self.rows[0])?,
+            row(self.rows[1])?,
+            row(self.rows[2])?,
+        ))
+    }
+
+    /// Whether a packed `_r` byte names a rotation: rows 0 and 1 in different columns.
+    pub fn is_valid(packed: u8) -> bool {
+        let first = packed & 0b11;
+        let second = (packed >> 2) & 0b11;
+        first < 3 && second < 3 && first != second
+    }
+
+    /// `self * inner`: applies `inner` first.
+    pub fn then(&self, inner: &Rotation) -> Rotation {
+        let column = |j: usize| IVec3::new(inner.rows[0][j], inner.rows[1][j], inner.rows[2][j]);
+        let rows = self
+            .rows
+            .map(|row| IVec3::new(row.dot(column(0)), row.dot(column(1)), row.dot(column(2))));
+        Rotation { rows }
+    }
+}
+
+#[derive(Debug)]
+enum SceneNode {
+    Transform {
+        child: i32,
+        translation: IVec3,
+        rotation: u8,
+    },
+    Group {
+        children: Vec<i32>,
+    },
+    Shape {
+        models: Vec<i32>,
+    },
+}
+
+impl SceneNode {
+    fn parse(id: &[u8; 4], content: &mut Reader) -> Result<Self, VoxError> {
+        content.dict()?;
+        match id {
+            b"nTRN" => {
+                let child = content.i32()?;
+                let _reserved = content.i32()?;
+                let _layer = content.i32()?;
+                let frames = content.i32()?;
+                let mut translation = IVec3::ZERO;
+                let mut rotation = IDENTITY_ROTATION;
+                for frame in 0..frames {
+                    let attributes = content.dict()?;
+                    if frame > 0 {
+                        continue;
+                    }
+                    if let Some(value) = attributes.get("_t") {
+                        let bad = || VoxError::Invalid(format!("bad translation {value:?}"));
+                        let parts = value
+                            .split_whitespace()
+                            .map(str::parse::<i32>)
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(|_| bad())?;
+                        let [x, y, z] = parts[..] else {
+                            return Err(bad());
+                        };
+                        translation = IVec3::new(x, y, z);
+                    }
+                    if let Some(value) = attributes.get("_r") {
+                        let bad = || VoxError::Invalid(format!("bad rotation {value:?}"));
+                        rotation = value.parse().map_err(|_| bad())?;
+                        if !Rotation::is_valid(rotation) {
+                            return Err(bad());
+                        }
+                    }
+                }
+                Ok(SceneNode::Transform {
+                    child,
+                    translation,
+                    rotation,
+                })
+            }
+            b"nGRP" => {
+                let count = content.i32()?;
+                let children = (0..count)
+                    .map(|_| content.i32())
+                    .collect::<Result<_, _>>()?;
+                Ok(SceneNode::Group { children })
+            }
+            _ => {
+                let count = content.i32()?;
+                let models = (0..count)
+                    .map(|_| {
+                        let model = content.i32()?;
+                        content.dict()?;
+                        Ok(model)
+                    })
+                    .collect::<Result<_, VoxError>>()?;
+                Ok(SceneNode::Shape { models })
+            }
+        }
+    }
+}
+
+fn flatten_scene(
+    nodes: &HashMap<i32, SceneNode>,
+    node_id: i32,
+    translation: IVec3,
+    rotation: Rotation,
+    instances: &mut Vec<VoxInstance>,
+    depth: usize,
+    visits: &mut usize,
+) -> Result<(), VoxError> {
+    if depth > MAX_SCENE_DEPTH {
+        return Err(VoxError::Invalid("scene graph is cyclic".to_string()));
+    }
+    *visits += 1;
+    if *visits > MAX_SCENE_VISITS {
+        return Err(VoxError::Invalid(format!(
+            "scene graph expands to over {MAX_SCENE_VISITS} nodes"
+        )));
+    }
+    let node = nodes
+        .get(&node_id)
+        .ok_or_else(|| VoxError::Invalid(format!("missing scene node {node_id}")))?;
+
+    match node {
+        SceneNode::Transform {
+            child,
+            translation: local_translation,
+            rotation: local_rotation,
+        } => {
+            let translation = rotation
+                .checked_apply(*local_translation)
+                .and_then(|local| translation.checked_add(local))
+                .ok_or_else(|| VoxError::Invalid("scene translation overflows".to_string()))?;
+            flatten_scene(
+                nodes,
+                *child,
+                translation,
+                rotation.then(&Rotation::decode(*local_rotation)),
+                instances,
+                depth + 1,
+                visits,
+            )
+        }
+        SceneNode::Group { children } => children.iter().try_for_each(|child| {
+            flatten_scene(
+                nodes,
+                *child,
+                translation,
+                rotation,
+                instances,
+                depth + 1,
+                visits,
+            )
+        }),
+        SceneNode::Shape { models } => {
+            for model in models {
+                if instances.len() == MAX_INSTANCES {
+                    return Err(VoxError::Invalid(format!(
+                        "scene places over {MAX_INSTANCES} instances"
+                    )));
+                }
+                instances.push(VoxInstance {
+                    model: *model as usize,
+                    translation,
+                    rotation: rotation.encode(),
+                });
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Root transform -> group -> one transform + shape per instance.
+fn write_scene(out: &mut Vec<u8>, instances: &[VoxInstance]) {
+    let group_id = 1;
+    let node = |out: &mut Vec<u8>, id: &[u8; 4], node_id: i32, body: &[u8]| {
+        let mut content = node_id.to_le_bytes().to_vec();
+        content.extend_from_slice(&0i32.to_le_bytes());
+        content.extend_from_slice(body);
+        write_chunk(out, id, &content, &[]);
+    };
+    let transform = |child: i32, attributes: &[(&str, String)]| {
+        let mut body = Vec::new();
+        for value in [child, -1, 0, 1] {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+        write_dict(&mut body, attributes);
+        body
+    };
+
+    node(out, b"nTRN", 0, &transform(group_id, &[]));
+
+    let mut group = (instances.len() as i32).to_le_bytes().to_vec();
+    for i in 0..instances.len() as i32 {
+        group.extend_from_slice(&(2 + i * 2).to_le_bytes());
+    }
+    node(out, b"nGRP", group_id, &group);
+
+    for (i, instance) in instances.iter().enumerate() {
+        let transform_id = 2 + i as i32 * 2;
+        let [x, y, z] = instance.translation.to_array();
+        node(
+            out,
+            b"nTRN",
+            transform_id,
+            &transform(
+                transform_id + 1,
+                &[
+                    ("_t", format!("{x} {y} {z}")),
+                    ("_r", instance.rotation.to_string()),
+                ],
+            ),
+        );
+
+        let mut shape = 1i32.to_le_bytes().to_vec();
+        shape.extend_from_slice(&(instance.model as i32).to_le_bytes());
+        write_dict(&mut shape, &[]);
+        node(out, b"nSHP", transform_id + 1, &shape);
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8], children: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    out.extend_from_slice(content);
+    out.extend_from_slice(children);
+}
+
+fn write_dict(out: &mut Vec<u8>, entries: &[(&str, String)]) {
+    out.extend_from_slice(&(entries.len() as i32).to_le_bytes());
+    for (key, value) in entries {
+        for text in [*key, value.as_str()] {
+            out.extend_from_slice(&(text.len() as i32).to_le_bytes());
+            out.extend_from_slice(text.as_bytes());
+        }
+    }
+}
+
+struct RawChunk<'a> {
+    id: [u8; 4],
+    content: &'a [u8],
+    children: &'a [u8],
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], VoxError> {
+        let slice = self
+            .bytes
+            .get(self.position..self.position + len)
+            .ok_or_else(|| VoxError::Invalid("unexpected end of data".to_string()))?;
+        self.position += len;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, VoxError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, VoxError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, VoxError> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn dict(&mut self) -> Result<HashMap<String, String>, VoxError> {
+        let count = self.u32()?;
+        (0..count)
+            .map(|_| Ok((self.string()?, self.string()?)))
+            .collect()
+    }
+
+    fn chunk(&mut self) -> Result<RawChunk<'a>, VoxError> {
+        let id = self.take(4)?.try_into().unwrap();
+        let content_len = self.u32()? as usize;
+        let children_len = self.u32()? as usize;
+        Ok(RawChunk {
+            id,
+            content: self.take(content_len)?,
+            children: self.take(children_len)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINGLE: &[u8] = include_bytes!("../../tests/fixtures/vox/single.vox");
+    const SCENE: &[u8] = include_bytes!("../../tests/fixtures/vox/scene.vox");
+
+    #[test]
+    fn parses_single_model() {
+        let file = VoxFile::parse(SINGLE).unwrap();
+        assert_eq!(file.models.len(), 1);
+        assert_eq!(file.models[0].size, UVec3::new(3, 2, 4));
+        assert_eq!(file.models[0].voxels.len(), 7);
+        assert_eq!(file.palette[1], [200, 40, 40, 255]);
+        assert_eq!(file.instances[0].rotation, IDENTITY_ROTATION);
+    }
+
+    #[test]
+    fn imports_registered_and_color_blocks() {
+        let mut world = World::new();
+        VoxFile::parse(SINGLE)
+            .unwrap()
+            .import(&mut world, IVec3::ZERO)
+            .unwrap();
+
+        for x in 0..3 {
+            assert_eq!(world.get_block(IVec3::new(x, 0, 1)), BlockId::STONE);
+        }
+        let red = BlockId::from_rgb([200, 40, 40]);
+        assert_eq!(world.get_block(IVec3::new(0, 0, 0)), red);
+        for y in 1..4 {
+            assert_eq!(world.get_block(IVec3::new(0, y, 1)), red);
+        }
+        assert_eq!(world.get_block(IVec3::new(1, 1, 1)), BlockId::AIR);
+    }
+
+    #[test]
+    fn flattens_scene_graph() {
+        let file = VoxFile::parse(SCENE).unwrap();
+        assert_eq!(file.models.len(), 2);
+        assert_eq!(
+            file.instances
+                .iter()
+                .map(|instance| (instance.model, instance.translation))
+                .collect::<Vec<_>>(),
+            [(0, IVec3::ZERO), (1, IVec3::new(10, -4, 2))]
+        );
+
+        let mut world = World::new();
+        file.import(&mut world, IVec3::ZERO).unwrap();
+        let grass = world
+            .chunks()
+            .flat_map(|(_, chunk)| chunk.blocks())
+            .filter(|block| **block == BlockId::GRASS)
+            .count();
+        assert_eq!(grass, 2);
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        for fixture in [SINGLE, SCENE] {
+            let file = VoxFile::parse(fixture).unwrap();
+            assert_eq!(VoxFile::parse(&file.to_bytes()).unwrap(), file);
+        }
+    }
+
+    #[test]
+    fn rotations_round_trip() {
+        for packed in 0..128u8 {
+            if Rotation::is_valid(packed) {
+                assert_eq!(Rotation::decode(packed).encode(), packed);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_scenes_that_fan_out() {
+        // Every group references the next one twice, doubling the paths at each level.
+        let mut nodes: HashMap<i32, SceneNode> = (0..40)
+            .map(|id| {
+                (
+                    id,
+                    SceneNode::Group {
+                        children: vec![id + 1; 2],
+                    },
+                )
+            })
+            .collect();
+        nodes.insert(40, SceneNode::Shape { models: vec![0] });
+
+        let result = flatten_scene(
+            &nodes,
+            0,
+            IVec3::ZERO,
+            Rotation::IDENTITY,
+            &mut Vec::new(),
+            0,
+            &mut 0,
+        );
+        assert!(matches!(result, Err(VoxError::Invalid(_))), "{result:?}");
+    }
+
+    #[test]
+    fn rejects_translations_that_overflow() {
+        let transform = |child, x| SceneNode::Transform {
+            child,
+            translation: IVec3::new(x, 0, 0),
+            rotation: IDENTITY_ROTATION,
+        };
+        let nodes = HashMap::from([
+            (0, transform(1, i32::MAX)),
+            (1, transform(2, 1)),
+            (2, SceneNode::Shape { models: vec![0] }),
+        ]);
+        let result = flatten_scene(
+            &nodes,
+            0,
+            IVec3::ZERO,
+            Rotation::IDENTITY,
+            &mut Vec::new(),
+            0,
+            &mut 0,
+        );
+        assert!(matches!(result, Err(VoxError::Invalid(_))), "{result:?}");
+
+        let mut file = VoxFile::parse(SINGLE).unwrap();
+        file.instances[0].translation = IVec3::new(i32::MAX, i32::MIN, 0);
+        let mut world = World::new();
+        let result = file.import(&mut world, IVec3::ZERO);
+        assert!(matches!(result, Err(VoxError::Invalid(_))), "{result:?}");
+        assert_eq!(world.chunks().count(), 0);
+    }
+
+    #[test]
+    fn world_round_trips_through_vox() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(-3, 5, 7), BlockId::STONE);
+        world.set_block(IVec3::new(-1, 6, 9), BlockId::GRASS);
+        world.set_block(IVec3::new(-2, 7, 8), BlockId::from_rgb([12, 200, 99]));
+        let (min, max) = (IVec3::new(-3, 5, 7), IVec3::new(-1, 7, 9));
+
+        let file = VoxFile::export(&world, min, max).unwrap();
+        let mut imported = World::new();
+        VoxFile::parse(&file.to_bytes())
+            .unwrap()
+            .import(&mut imported, min)
+            .unwrap();
+
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    let pos = IVec3::new(x, y, z);
+                    assert_eq!(imported.get_block(pos), world.get_block(pos), "{pos}");
+                }
+            }
+        }
+    }
+}
@@ -8,6 +8,7 @@ pub struct BindGroupLayouts {
     pub globals: BindGroupLayout,
     pub view: BindGroupLayout,
     pub vertices: BindGroupLayout,
+    pub atlas: BindGroupLayout,
 }
 
 impl BindGroupLayouts {
@@ -39,16 +40,54 @@ impl BindGroupLayouts {
             }),
             view: device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 label: Some("Bind Group Layout View"),
-                entries: &[BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
                     },
-                    count: None,
-                }],
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Depth,
+                            view_dimension: TextureViewDimension::D2Array,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility,
+                        ty: BindingType::Sampler(SamplerBindingType::Comparison),
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
             }),
             vertices: device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 label: Some("Bind Group Layout Vertices"),
@@ -65,10 +104,31 @@ impl BindGroupLayouts {
                     count: None,
                 }],
             }),
+            atlas: device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout Atlas"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: true },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            }),
         }
     }
 
-    pub fn as_slice(&self) -> [&BindGroupLayout; 3] {
-        [&self.globals, &self.view, &self.vertices]
+    pub fn as_slice(&self) -> [&BindGroupLayout; 4] {
+        [&self.globals, &self.view, &self.vertices, &self.atlas]
     }
 }
@@ -1,11 +1,12 @@
 use wgpu::*;
 
-use crate::graphics::{bind_group_layouts, buffers};
+use crate::graphics::{bind_group_layouts, buffers, shadow_pass, textures};
 
 pub struct BindGroups {
     pub globals: BindGroup,
     pub bview: BindGroup,
     pub vertices: BindGroup,
+    pub atlas: BindGroup,
 }
 
 impl BindGroups {
@@ -13,6 +14,8 @@ impl BindGroups {
         device: &Device,
         bind_group_layouts: &bind_group_layouts::BindGroupLayouts,
         buffers: &buffers::Buffers,
+        textures: &textures::Textures,
+        shadow_map: &shadow_pass::ShadowMap,
     ) -> Self {
         Self {
             globals: device.create_bind_group(&BindGroupDescriptor {
@@ -30,14 +33,40 @@ impl BindGroups {
             bview: device.create_bind_group(&BindGroupDescriptor {
                 label: Some("Bind Group View"),
                 layout: &bind_group_layouts.view,
-                entries: &[BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::Buffer(BufferBinding {
-                        buffer: &buffers.view,
-                        offset: 0,
-                        size: None,
-                    }),
-                }],
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::Buffer(BufferBinding {
+                            buffer: &buffers.view,
+                            offset: 0,
+                            size: None,
+                        }),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Buffer(BufferBinding {
+                            buffer: &buffers.lights,
+                            offset: 0,
+                            size: None,
+                        }),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::TextureView(&shadow_map.view),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: BindingResource::Sampler(&shadow_map.sampler),
+                    },
+                    BindGroupEntry {
+                        binding: 4,
+                        resource: BindingResource::Buffer(BufferBinding {
+                            buffer: &buffers.fog,
+                            offset: 0,
+                            size: None,
+                        }),
+                    },
+                ],
             }),
             vertices: device.create_bind_group(&BindGroupDescriptor {
                 label: Some("Bind Group Vertices"),
@@ -51,10 +80,24 @@ impl BindGroups {
                     }),
                 }],
             }),
+            atlas: device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Bind Group Atlas"),
+                layout: &bind_group_layouts.atlas,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(&textures.atlas_view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(&textures.atlas_sampler),
+                    },
+                ],
+            }),
         }
     }
 
-    pub fn as_slice(&self) -> [&BindGroup; 3] {
-        [&self.globals, &self.bview, &self.vertices]
+    pub fn as_slice(&self) -> [&BindGroup; 4] {
+        [&self.globals, &self.bview, &self.vertices, &self.atlas]
     }
 }
@@ -5,6 +5,8 @@ use crate::graphics::structures;
 pub struct Buffers {
     pub globals: Buffer,
     pub view: Buffer,
+    pub lights: Buffer,
+    pub fog: Buffer,
     pub vertices: Buffer,
 }
 
@@ -23,12 +25,40 @@ impl Buffers {
                 usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
                 mapped_at_creation: false,
             }),
-            vertices: device.create_buffer(&BufferDescriptor {
-                label: Some("Vertices Buffer"),
-                size: std::mem::size_of::<structures::VertexBuffer>() as u64 * vertex_count,
-                usage: BufferUsages::VERTEX | BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            lights: device.create_buffer(&BufferDescriptor {
+                label: Some("Lights Buffer"),
+                size: std::mem::size_of::<structures::Lights>() as u64,
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
                 mapped_at_creation: false,
             }),
+            fog: device.create_buffer(&BufferDescriptor {
+                label: Some("Fog Buffer"),
+                size: std::mem::size_of::<structures::Fog>() as u64,
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            vertices: create_vertex_buffer(device, vertex_count),
         }
     }
+
+    /// Grows the vertex buffer to hold at least `vertex_count` vertices. Returns true if the
+    /// buffer was replaced, in which case bind groups referencing it must be recreated.
+    pub fn reserve_vertices(&mut self, device: &wgpu::Device, vertex_count: u64) -> bool {
+        let capacity =
+            self.vertices.size() / std::mem::size_of::<structures::VertexBuffer>() as u64;
+        if vertex_count <= capacity {
+            return false;
+        }
+        self.vertices = create_vertex_buffer(device, vertex_count.next_power_of_two());
+        true
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, vertex_count: u64) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Vertices Buffer"),
+        size: std::mem::size_of::<structures::VertexBuffer>() as u64 * vertex_count,
+        usage: BufferUsages::VERTEX | BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
 }
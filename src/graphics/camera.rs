@@ -1,5 +1,4 @@
 use glam::{Mat4, Quat, Vec2, Vec3};
-use winit::event::KeyEvent;
 
 use crate::graphics::structures::View;
 
@@ -17,17 +16,17 @@ pub struct Camera {
 }
 
 impl Camera {
-    pub fn new(aspect_ratio: f32) -> Self {
-        return Camera {
+    pub fn new(aspect_ratio: f32, fov: f32, sensitivity: f32) -> Self {
+        Camera {
             position: Vec3::ZERO,
             rotation: Quat::IDENTITY,
-            fov: 90.0_f32.to_radians(),
+            fov,
             aspect_ratio,
             yaw: 0.0,
             pitch: 0.0,
             speed: 5.0,
-            sensitivity: 0.002,
-        };
+            sensitivity,
+        }
     }
     pub fn get_view(&self) -> View {
         let proj_view_rev_z =
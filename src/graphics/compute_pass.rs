@@ -1,15 +0,0 @@
-use wgpu::*;
-
-pub struct ComputePass {
-    pipeline: wgpu::ComputePipeline,
-}
-
-impl ComputePass {
-    pub fn new() -> Self {
-        todo!()
-    }
-
-    pub fn encode(&self, encoder: &mut CommandEncoder) {
-        todo!()
-    }
-}
@@ -0,0 +1,163 @@
+use glam::{Vec3, Vec3A};
+
+use crate::{
+    graphics::structures::{Fog, Lights},
+    world::{biome::FogOverride, world::World},
+};
+
+/// Density falloff with height above water; halves roughly every 23 blocks.
+const HEIGHT_FALLOFF: f32 = 0.03;
+/// Height the fog density is measured at: ground level of generated terrain.
+const BASE_HEIGHT: f32 = 0.0;
+/// Fraction of the render distance where terrain starts fading into the fog.
+const FADE_START: f32 = 0.75;
+const WATER_DENSITY: f32 = 0.12;
+/// How far one can see underwater, in blocks.
+const WATER_VISIBILITY: f32 = 40.0;
+/// Underwater fog color in full daylight. Linear RGB.
+const WATER_COLOR: Vec3 = Vec3::new(0.04, 0.16, 0.3);
+/// Rate at which the fog eases toward a new biome's, per second.
+pub const BIOME_BLEND_RATE: f32 = 1.5;
+
+/// What the camera is inside of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Medium {
+    Air,
+    Water,
+}
+
+impl Medium {
+    /// Water if `position` is below the surface of the water in its cell. Waterlogged blocks
+    /// are filled to the top, as they are drawn.
+    pub fn at(world: &World, position: Vec3) -> Medium {
+        let block = world.get_block(position.floor().as_ivec3());
+        let height = position.y - position.y.floor();
+        if block.is_waterlogged() || (block.is_water() && height < block.fluid_height()) {
+            Medium::Water
+        } else {
+            Medium::Air
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FogSettings {
+    pub density: f32,
+    /// Distance at which terrain has fully faded out, in blocks.
+    pub distance: f32,
+}
+
+/// Fog for the camera's medium. In air it takes the sky's horizon color, tinted by the biome,
+/// so terrain at the edge of the render distance blends into the sky; underwater it is dense,
+/// water-colored and hides the sky as well.
+pub fn fog(settings: FogSettings, medium: Medium, biome: FogOverride, lights: &Lights) -> Fog {
+    match medium {
+        Medium::Air => Fog {
+            color: lights
+                .horizon_color
+                .lerp(biome.tint.into(), biome.tint_amount),
+            density: settings.density * biome.density,
+            height_falloff: HEIGHT_FALLOFF,
+            base_height: BASE_HEIGHT,
+            start_distance: settings.distance * FADE_START,
+            end_distance: settings.distance,
+            sky_amount: 0.0,
+            _pad: [0.0; 2],
+        },
+        Medium::Water => {
+            // Water is lit from above, so it darkens with the sky.
+            let light = lights.sky_light + lights.light_color * lights.light_direction.y.max(0.0);
+            Fog {
+                color: Vec3A::from(WATER_COLOR) * light.max_element().min(1.0),
+                density: WATER_DENSITY,
+                height_falloff: 0.0,
+                base_height: BASE_HEIGHT,
+                start_distance: 0.0,
+                end_distance: WATER_VISIBILITY.min(settings.distance),
+                sky_amount: 1.0,
+                _pad: [0.0; 2],
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec3;
+
+    use super::*;
+    use crate::{
+        graphics::lighting::{sky_colors, sky_light, sun_direction},
+        world::{biome::Biome, block::BlockId},
+    };
+
+    const SETTINGS: FogSettings = FogSettings {
+        density: 0.003,
+        distance: 256.0,
+    };
+
+    fn lights(time_of_day: f32) -> Lights {
+        let sun = sun_direction(time_of_day);
+        let (zenith, horizon) = sky_colors(sun);
+        Lights {
+            light_direction: sun.into(),
+            light_color: Vec3A::ONE,
+            sky_light: sky_light(zenith, horizon).into(),
+            horizon_color: horizon.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn air_fog_matches_the_horizon_and_ends_at_the_render_distance() {
+        let lights = lights(0.5);
+        let fog = fog(SETTINGS, Medium::Air, Biome::Plains.fog(), &lights);
+        assert_eq!(fog.color, lights.horizon_color);
+        assert_eq!(fog.end_distance, 256.0);
+        assert!(fog.start_distance < fog.end_distance);
+        assert_eq!(fog.sky_amount, 0.0);
+
+        let swamp = super::fog(SETTINGS, Medium::Air, Biome::Swamp.fog(), &lights);
+        assert!(swamp.density > fog.density);
+        assert_ne!(swamp.color, fog.color);
+    }
+
+    #[test]
+    fn underwater_fog_is_dense_and_hides_the_sky() {
+        let day = fog(SETTINGS, Medium::Water, FogOverride::NONE, &lights(0.5));
+        let air = fog(SETTINGS, Medium::Air, FogOverride::NONE, &lights(0.5));
+        assert!(day.density > 10.0 * air.density);
+        assert!(day.end_distance < air.end_distance);
+        assert_eq!(day.sky_amount, 1.0);
+        assert!(day.color.z > day.color.x);
+
+        let night = fog(SETTINGS, Medium::Water, FogOverride::NONE, &lights(0.0));
+        assert!(night.color.length() < day.color.length());
+    }
+
+    #[test]
+    fn medium_is_the_block_around_the_camera() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(3, -2, 0), BlockId::WATER);
+        assert_eq!(Medium::at(&world, Vec3::new(3.5, -1.2, 0.9)), Medium::Water);
+        assert_eq!(Medium::at(&world, Vec3::new(3.5, -0.9, 0.9)), Medium::Air);
+
+        world.set_block(
+            IVec3::new(0, 0, 0),
+            BlockId::STONE_SLAB.with_waterlogged(true),
+        );
+        assert_eq!(Medium::at(&world, Vec3::new(0.5, 0.9, 0.5)), Medium::Water);
+    }
+
+    #[test]
+    fn flowing_water_only_covers_its_surface_height() {
+        let mut world = World::new();
+        let flowing = BlockId::WATER.with_fluid_state(4, false);
+        world.set_block(IVec3::ZERO, flowing);
+        let surface = flowing.fluid_height();
+        assert!(surface < 1.0);
+        let at = |y| Medium::at(&world, Vec3::new(0.5, y, 0.5));
+        assert_eq!(at(surface - 0.05), Medium::Water);
+        assert_eq!(at(surface + 0.05), Medium::Air);
+    }
+}
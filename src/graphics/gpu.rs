@@ -0,0 +1,79 @@
+use std::fmt;
+
+use wgpu::*;
+
+#[derive(Debug)]
+pub enum GraphicsError {
+    Surface(CreateSurfaceError),
+    Adapter(RequestAdapterError),
+    Device(RequestDeviceError),
+}
+
+impl fmt::Display for GraphicsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphicsError::Surface(err) => write!(f, "could not create a surface: {err}"),
+            GraphicsError::Adapter(err) => write!(f, "could not get an adapter (GPU): {err}"),
+            GraphicsError::Device(err) => write!(f, "could not get a device: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for GraphicsError {}
+
+/// Instance, adapter, device and queue; everything needed to render, independent of any
+/// window or surface.
+pub struct Gpu {
+    pub instance: Instance,
+    pub adapter: Adapter,
+    pub device: Device,
+    pub queue: Queue,
+}
+
+impl Gpu {
+    pub async fn new(
+        instance: Instance,
+        options: &RequestAdapterOptions<'_, '_>,
+    ) -> Result<Self, GraphicsError> {
+        let adapter = instance
+            .request_adapter(options)
+            .await
+            .map_err(GraphicsError::Adapter)?;
+
+        let (device, queue) = adapter
+            .request_device(&DeviceDescriptor {
+                label: None,
+                required_features: Features::empty(),
+                required_limits: adapter.limits(),
+                memory_hints: MemoryHints::Performance,
+                trace: Default::default(),
+                experimental_features: ExperimentalFeatures::default(),
+            })
+            .await
+            .map_err(GraphicsError::Device)?;
+
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+        })
+    }
+
+    /// GPU without a surface. With `force_fallback_adapter` this picks a software
+    /// rasterizer such as lavapipe or llvmpipe, which needs no display.
+    pub async fn headless(
+        power_preference: PowerPreference,
+        force_fallback_adapter: bool,
+    ) -> Result<Self, GraphicsError> {
+        Self::new(
+            Instance::default(),
+            &RequestAdapterOptions {
+                power_preference,
+                force_fallback_adapter,
+                compatible_surface: None,
+            },
+        )
+        .await
+    }
+}
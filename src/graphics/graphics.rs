@@ -1,138 +1,243 @@
-use std::{sync::Arc, time::Instant};
+use std::{
+    collections::BTreeSet,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
 
 use glam::Vec2;
 use wgpu::*;
-use winit::{dpi::PhysicalSize, event_loop::EventLoopProxy, keyboard::PhysicalKey, window::Window};
+use winit::{
+    dpi::PhysicalSize,
+    event::{ElementState, MouseButton},
+    event_loop::EventLoopProxy,
+    keyboard::PhysicalKey,
+    window::Window,
+};
 
-use crate::graphics::{
-    bind_group_layouts, bind_groups, buffers,
-    camera::Camera,
-    render_pass,
-    structures::{Globals, Metadata, View},
+use crate::{
+    formats::image::RgbaImage,
+    graphics::{
+        bind_group_layouts, bind_groups, buffers,
+        camera::Camera,
+        fog::{self, FogSettings, Medium},
+        gpu::{Gpu, GraphicsError},
+        lighting::{self, ShadowSettings},
+        offscreen::{OffscreenTarget, ReadbackError},
+        post_pass::{self, BLOOM_KNEE, BLOOM_TEXTURES, HDR_FORMAT, PostPass},
+        render_graph::{OUTPUT, RenderGraph, TextureDesc, TexturePool, TextureSize},
+        render_pass::{self, NORMAL_FORMAT, RenderPass, RenderTargets},
+        shaders::{self, SHADER_DIR, ShaderError, ShaderWatcher},
+        shadow_pass::{self, ShadowMap, ShadowPass},
+        sky_pass::{self, SkyPass},
+        ssao_pass::{self, AO_FORMAT, SsaoPass, SsaoTargets},
+        structures::{
+            AmbientOcclusion, Fog, Globals, Lights, Metadata, PostEffects, VertexBuffer, View,
+        },
+        textures::Textures,
+        water_pass::{self, WaterPass, WaterTargets},
+    },
+    input::{action::Action, input_map::InputMap, state::InputState},
+    meshing::world_mesh::WorldMesh,
+    physics::{
+        aabb::Aabb,
+        player::{MovementMode, Player, PlayerInput},
+        raycast::raycast,
+    },
+    settings::settings::Settings,
+    simulation::{clock::SystemClock, simulation::Simulation, world_time::WorldTime},
+    world::{
+        biome::{Biome, FogOverride},
+        block::{BlockId, RenderLayer},
+        chunk::CHUNK_SIZE,
+        placement::{cycle_held_block, placement},
+        storage::{
+            error::StorageError,
+            metadata::{PlayerState, WorldMetadata},
+            world_save::WorldSave,
+        },
+        world::World,
+    },
 };
 
-pub async fn create_graphics(window: Arc<Window>, proxy: EventLoopProxy<Graphics>) {
+/// Gamepad look speed, in mouse-motion units per second at full stick deflection.
+const GAMEPAD_LOOK_SPEED: f32 = 1500.0;
+/// How far from the eye blocks can be broken and placed.
+const REACH: f32 = 6.0;
+
+pub async fn create_graphics(
+    window: Arc<Window>,
+    proxy: EventLoopProxy<Graphics>,
+    settings: &Settings,
+) {
     let instance = Instance::default();
     let surface = instance.create_surface(Arc::clone(&window)).unwrap();
 
-    let adapter = instance
-        .request_adapter(&RequestAdapterOptions {
-            power_preference: PowerPreference::default(),
+    let gpu = Gpu::new(
+        instance,
+        &RequestAdapterOptions {
+            power_preference: settings.graphics.adapter.into(),
             force_fallback_adapter: false,
             compatible_surface: Some(&surface),
-        })
-        .await
-        .expect("Could not get an adapter (GPU).");
-
-    let (device, queue) = adapter
-        .request_device(&DeviceDescriptor {
-            label: None,
-            required_features: Features::empty(),
-            required_limits: adapter.limits(),
-            memory_hints: MemoryHints::Performance,
-            trace: Default::default(),
-            experimental_features: ExperimentalFeatures::default(),
-        })
-        .await
-        .expect("Failed to get device");
+        },
+    )
+    .await
+    .unwrap_or_else(|err| panic!("{err}"));
 
     let size = window.inner_size();
-    let width = size.width.max(1);
-    let height = size.height.max(1);
-    let surface_config = surface.get_default_config(&adapter, width, height).unwrap();
-
-    surface.configure(&device, &surface_config);
-
-    let (depth_texture, depth_texture_view) = create_depth_texture(&device, &surface_config);
-
-    let bind_group_layouts_compute = bind_group_layouts::BindGroupLayouts::new(
-        &device,
-        bind_group_layouts::BindGroupUsage::Compute,
-    );
-    let bind_group_layouts_render = bind_group_layouts::BindGroupLayouts::new(
-        &device,
-        bind_group_layouts::BindGroupUsage::Render,
-    );
-    let render_pass = render_pass::RenderPass::new(&device, &bind_group_layouts_render.as_slice());
-    let buffers = buffers::Buffers::new(&device, 3);
-    let bind_groups_compute =
-        bind_groups::BindGroups::new(&device, &bind_group_layouts_compute, &buffers);
-    let bind_groups_render =
-        bind_groups::BindGroups::new(&device, &bind_group_layouts_render, &buffers);
-
-    let gfx = Graphics {
-        window: window.clone(),
-        instance,
-        surface,
-        surface_config,
-        adapter,
-        device,
-        queue,
-
-        depth_texture,
-        depth_texture_view,
-
-        camera: Camera::new(width as f32 / height as f32),
-        metadata: Metadata::new(),
-        globals: Globals {
-            resolution: [width, height],
-            ..Default::default()
-        },
-        view: Default::default(),
+    let mut surface_config = surface
+        .get_default_config(&gpu.adapter, size.width.max(1), size.height.max(1))
+        .unwrap();
+    surface_config.present_mode = settings.present_mode();
+    surface.configure(&gpu.device, &surface_config);
 
-        render_pass,
-        buffers,
-        bind_group_layouts_compute,
-        bind_group_layouts_render,
-        bind_groups_compute,
-        bind_groups_render,
-    };
+    let (world_save, world, world_metadata) = load_world(settings);
+    let mut gfx = Graphics::new(gpu, surface_config, settings, world, world_metadata);
+    gfx.window = Some(window);
+    gfx.update_title();
+    gfx.surface = Some(surface);
+    gfx.world_save = world_save;
 
     let _ = proxy.send_event(gfx);
 }
 
-fn create_depth_texture(
-    device: &Device,
-    surface_config: &wgt::SurfaceConfiguration<Vec<TextureFormat>>,
-) -> (Texture, TextureView) {
-    let depth_texture = device.create_texture(&TextureDescriptor {
-        label: Some("Depth Texture"),
-        size: Extent3d {
-            width: surface_config.width,
-            height: surface_config.height,
-            depth_or_array_layers: 1,
-        },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: TextureDimension::D2,
-        format: TextureFormat::Depth32Float,
+/// Graphics rendering into an offscreen texture of the given size, on a software adapter when
+/// `force_fallback_adapter` is set. Frames are read back with [`Graphics::capture`].
+pub async fn create_headless_graphics(
+    settings: &Settings,
+    width: u32,
+    height: u32,
+    force_fallback_adapter: bool,
+) -> Result<Graphics, GraphicsError> {
+    let gpu = Gpu::headless(settings.graphics.adapter.into(), force_fallback_adapter).await?;
+    let (world_save, world, world_metadata) = load_world(settings);
+    let surface_config = offscreen_surface_config(width, height);
+    let mut gfx = Graphics::new(gpu, surface_config, settings, world, world_metadata);
+    gfx.world_save = world_save;
+    Ok(gfx)
+}
+
+/// Frame configuration for rendering without a surface.
+pub fn offscreen_surface_config(width: u32, height: u32) -> SurfaceConfiguration {
+    SurfaceConfiguration {
         usage: TextureUsages::RENDER_ATTACHMENT,
-        view_formats: &[],
+        format: TextureFormat::Rgba8UnormSrgb,
+        width: width.max(1),
+        height: height.max(1),
+        present_mode: PresentMode::AutoNoVsync,
+        desired_maximum_frame_latency: 2,
+        alpha_mode: CompositeAlphaMode::Opaque,
+        view_formats: vec![],
+    }
+}
+
+/// Opens the configured world directory and loads the world from it. A world that can't be
+/// read is left alone: the fresh one generated in its place is not saved over it.
+pub fn load_world(settings: &Settings) -> (Option<WorldSave>, World, WorldMetadata) {
+    let world_save = WorldSave::open(&settings.world.path)
+        .inspect_err(|err| log::error!("{err}, the world will not be saved"))
+        .ok();
+    let loaded = world_save.as_ref().map(|save| -> Result<_, StorageError> {
+        let Some(metadata) = save.load_metadata()? else {
+            return Ok(None);
+        };
+        Ok(Some((save.load_world()?, metadata)))
     });
+    match loaded {
+        Some(Ok(Some((world, metadata)))) => return (world_save, world, metadata),
+        Some(Err(err)) => {
+            log::error!("{err}, generating a new world that will not be saved");
+            let (world, metadata) = generate_world(settings);
+            return (None, world, metadata);
+        }
+        _ => {}
+    }
+    let (world, metadata) = generate_world(settings);
+    (world_save, world, metadata)
+}
 
-    let depth_texture_view = depth_texture.create_view(&TextureViewDescriptor::default());
-    (depth_texture, depth_texture_view)
+/// A flat world. There is no terrain generation: the seed only picks the biomes and the
+/// random block ticks.
+fn generate_world(settings: &Settings) -> (World, WorldMetadata) {
+    let metadata = WorldMetadata::new(settings.world.seed, [0.5, 2.0, 0.5]);
+    (World::flat(2), metadata)
+}
+
+/// Swaps in a reloaded pipeline, or logs why it failed and keeps the current one.
+fn replace_pipeline<T>(current: &mut T, loaded: Result<T, ShaderError>, shader: &str) {
+    match loaded {
+        Ok(pipeline) => {
+            *current = pipeline;
+            log::info!("reloaded {shader}");
+        }
+        Err(err) => log::error!("{err}\nkeeping the previous pipeline"),
+    }
 }
 
 pub struct Graphics {
-    pub window: Arc<Window>,
+    /// `None` when running headless.
+    pub window: Option<Arc<Window>>,
     pub instance: Instance,
-    pub surface: Surface<'static>,
+    /// `None` when running headless; frames then go to `offscreen`.
+    pub surface: Option<Surface<'static>>,
+    /// Size and color format of the frames, also used without a surface.
     pub surface_config: SurfaceConfiguration,
     pub adapter: Adapter,
     pub device: Device,
     pub queue: Queue,
 
-    pub depth_texture: Texture,
-    pub depth_texture_view: TextureView,
+    /// Transient render graph textures such as depth, kept between frames.
+    pub texture_pool: TexturePool,
+    pub offscreen: Option<OffscreenTarget>,
+    pub screenshot_dir: PathBuf,
+    /// The window title, before any status is added to it.
+    pub title: String,
 
     pub camera: Camera,
     pub metadata: Metadata,
     pub globals: Globals,
     pub view: View,
+    pub lights: Lights,
+    pub shadow_settings: ShadowSettings,
+    pub fog: Fog,
+    pub fog_settings: FogSettings,
+    /// Fog override of the biomes around the camera, eased toward the current one.
+    pub biome_fog: FogOverride,
+    pub post_effects: PostEffects,
+    /// `None` when SSAO is off.
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+
+    pub input: InputState,
+    #[cfg(feature = "gamepad")]
+    pub gamepads: Option<crate::input::gamepad::Gamepads>,
+    /// Where rebound controls are saved.
+    pub input_map_path: PathBuf,
+    /// The action last shown as waiting for a new binding.
+    pub rebinding: Option<Action>,
+    /// Whether the window holds the cursor for mouse look; blocks are only edited then, so the
+    /// click that captures it doesn't also break one.
+    pub cursor_captured: bool,
+    /// Block the place action puts down.
+    pub held_block: BlockId,
+    pub simulation: Simulation,
+    pub world_save: Option<WorldSave>,
+    pub world_metadata: WorldMetadata,
+    pub world_mesh: WorldMesh,
+    /// Where each render layer is in the vertex buffer, indexed by `RenderLayer as usize`.
+    pub vertex_ranges: [Range<u32>; RenderLayer::ALL.len()],
 
-    pub render_pass: render_pass::RenderPass,
+    pub render_pass: RenderPass,
+    pub shadow_pass: ShadowPass,
+    pub sky_pass: SkyPass,
+    pub post_pass: PostPass,
+    pub ssao_pass: SsaoPass,
+    pub water_pass: WaterPass,
+    pub shadow_map: ShadowMap,
+    /// Set in debug builds with shader hot-reload enabled.
+    pub shader_watcher: Option<ShaderWatcher>,
     pub buffers: buffers::Buffers,
+    pub textures: Textures,
     pub bind_group_layouts_compute: bind_group_layouts::BindGroupLayouts,
     pub bind_group_layouts_render: bind_group_layouts::BindGroupLayouts,
     pub bind_groups_compute: bind_groups::BindGroups,
@@ -140,34 +245,242 @@ pub struct Graphics {
 }
 
 impl Graphics {
+    /// Builds everything but the window and surface, which the caller attaches when it has
+    /// them. Without a surface every frame is rendered offscreen.
+    pub fn new(
+        gpu: Gpu,
+        surface_config: SurfaceConfiguration,
+        settings: &Settings,
+        mut world: World,
+        world_metadata: WorldMetadata,
+    ) -> Self {
+        let Gpu {
+            instance,
+            adapter,
+            device,
+            queue,
+        } = gpu;
+        let (width, height) = (surface_config.width, surface_config.height);
+
+        let bind_group_layouts_compute = bind_group_layouts::BindGroupLayouts::new(
+            &device,
+            bind_group_layouts::BindGroupUsage::Compute,
+        );
+        let bind_group_layouts_render = bind_group_layouts::BindGroupLayouts::new(
+            &device,
+            bind_group_layouts::BindGroupUsage::Render,
+        );
+        let render_pass =
+            RenderPass::new(&device, &bind_group_layouts_render.as_slice(), HDR_FORMAT);
+        let sky_pass = SkyPass::new(
+            &device,
+            &bind_group_layouts_render.as_slice()[..2],
+            HDR_FORMAT,
+        );
+        let post_pass = PostPass::new(&device, surface_config.format);
+        let ssao_pass = SsaoPass::new(&device);
+        let water_pass = WaterPass::new(&device, &bind_group_layouts_render.as_slice()[..2]);
+        let ambient_occlusion = ssao_pass::ambient_occlusion(settings.graphics.ssao);
+        let post_effects = PostEffects {
+            exposure: settings.graphics.exposure.exp2(),
+            bloom_intensity: settings.graphics.bloom_intensity,
+            bloom_threshold: settings.graphics.bloom_threshold,
+            bloom_knee: BLOOM_KNEE,
+            tonemapper: settings.graphics.tonemapper as u32,
+            encode_srgb: !surface_config.format.is_srgb() as u32,
+            ambient_occlusion: ambient_occlusion.is_some() as u32,
+            ..Default::default()
+        };
+        let shadow_settings = ShadowSettings {
+            cascades: settings.graphics.shadow_cascades,
+            map_size: settings.graphics.shadow_map_size,
+            distance: settings.graphics.shadow_distance,
+        };
+        let fog_settings = FogSettings {
+            density: settings.graphics.fog_density,
+            distance: (settings.graphics.render_distance * CHUNK_SIZE as u32) as f32,
+        };
+        let shadow_pass = ShadowPass::new(&device, &bind_group_layouts_render.atlas);
+        let shadow_map =
+            ShadowMap::new(&device, shadow_settings.map_size, shadow_settings.cascades);
+
+        let shader_watcher = if cfg!(debug_assertions) && settings.debug.hot_reload_shaders {
+            ShaderWatcher::new(Path::new(SHADER_DIR))
+                .inspect_err(|err| log::warn!("not watching shaders in {SHADER_DIR}: {err}"))
+                .ok()
+        } else {
+            None
+        };
+
+        let input_map =
+            InputMap::load_or_default(&settings.controls.input_map).unwrap_or_else(|err| {
+                log::error!("{err}, using default bindings");
+                InputMap::default()
+            });
+
+        let world_mesh = WorldMesh::new(&mut world);
+        let vertices = world_mesh.vertices();
+        let vertex_ranges = world_mesh.layer_ranges();
+        let mut buffers = buffers::Buffers::new(&device, 3);
+        buffers.reserve_vertices(&device, vertices.len() as u64);
+        queue.write_buffer(&buffers.vertices, 0, bytemuck::cast_slice(&vertices));
+        let textures = Textures::new(&device, &queue, &world_mesh.atlas);
+        let bind_groups_compute = bind_groups::BindGroups::new(
+            &device,
+            &bind_group_layouts_compute,
+            &buffers,
+            &textures,
+            &shadow_map,
+        );
+        let bind_groups_render = bind_groups::BindGroups::new(
+            &device,
+            &bind_group_layouts_render,
+            &buffers,
+            &textures,
+            &shadow_map,
+        );
+
+        let mut player = Player::new(world_metadata.player.position.into());
+        player.velocity = world_metadata.player.velocity.into();
+        player.mode = world_metadata.player.mode();
+
+        let mut camera = Camera::new(
+            width as f32 / height as f32,
+            settings.graphics.fov.to_radians(),
+            settings.controls.mouse_sensitivity,
+        );
+        camera.yaw = world_metadata.player.yaw;
+        camera.pitch = world_metadata.player.pitch;
+        camera.update_rotation(Vec2::ZERO);
+        camera.position = player.eye_position();
+        let column = camera.position.floor().as_ivec3();
+        let biome_fog = Biome::at(world_metadata.seed, column.x, column.z).fog();
+
+        let mut simulation =
+            Simulation::new(SystemClock::new(), settings.world.tick_rate, world, player);
+        simulation.tick_count = world_metadata.tick_count;
+        simulation.block_updates.seed = world_metadata.seed;
+        simulation.time = WorldTime::new(
+            world_metadata.day,
+            world_metadata.time_of_day,
+            settings.world.day_length,
+            settings.world.tick_rate,
+        );
+
+        Self {
+            window: None,
+            instance,
+            surface: None,
+            surface_config,
+            adapter,
+            device,
+            queue,
+
+            texture_pool: TexturePool::new(),
+            offscreen: None,
+            screenshot_dir: settings.graphics.screenshot_dir.clone(),
+            title: settings.window.title.clone(),
+
+            camera,
+            metadata: Metadata::new(),
+            globals: Globals {
+                resolution: [width, height],
+                ..Default::default()
+            },
+            view: Default::default(),
+            lights: Default::default(),
+            shadow_settings,
+            fog: Default::default(),
+            fog_settings,
+            biome_fog,
+            post_effects,
+            ambient_occlusion,
+
+            input: InputState::new(input_map),
+            #[cfg(feature = "gamepad")]
+            gamepads: crate::input::gamepad::Gamepads::new(),
+            input_map_path: settings.controls.input_map.clone(),
+            rebinding: None,
+            cursor_captured: false,
+            held_block: BlockId::STONE,
+            simulation,
+            world_save: None,
+            world_metadata,
+            world_mesh,
+            vertex_ranges,
+
+            render_pass,
+            shadow_pass,
+            sky_pass,
+            post_pass,
+            ssao_pass,
+            water_pass,
+            shadow_map,
+            shader_watcher,
+            buffers,
+            textures,
+            bind_group_layouts_compute,
+            bind_group_layouts_render,
+            bind_groups_compute,
+            bind_groups_render,
+        }
+    }
+
     pub fn set_mouse_pos(&mut self, mouse_pos: Vec2) {
-        self.globals.mouse_pos = mouse_pos.into();
+        self.globals.mouse_pos = mouse_pos;
     }
 
     pub fn handle_mouse_motion(&mut self, delta_x: f32, delta_y: f32) {
-        self.metadata.delta_mouse += Vec2::new(delta_x, delta_y);
+        self.input.handle_mouse_motion(Vec2::new(delta_x, delta_y));
     }
 
     pub fn handle_keyboard_input(&mut self, event: &winit::event::KeyEvent) {
         let PhysicalKey::Code(key_code) = event.physical_key else {
             return;
         };
+        self.input.handle_key(key_code, event.state);
+    }
 
-        if event.state == winit::event::ElementState::Pressed {
-            self.metadata.keyboard_state.insert(key_code);
-        } else {
-            self.metadata.keyboard_state.remove(&key_code);
+    pub fn handle_mouse_input(&mut self, button: MouseButton, state: ElementState) {
+        self.input.handle_mouse_button(button, state);
+    }
+
+    /// Writes unsaved chunks and the current player state to the world directory.
+    pub fn save_world(&mut self) {
+        let Some(world_save) = &self.world_save else {
+            return;
+        };
+
+        let player = &self.simulation.player;
+        self.world_metadata.tick_count = self.simulation.tick_count;
+        self.world_metadata.day = self.simulation.time.day;
+        self.world_metadata.time_of_day = self.simulation.time.time_of_day(0.0);
+        self.world_metadata.player = PlayerState {
+            position: player.position.into(),
+            velocity: player.velocity.into(),
+            flying: player.mode == MovementMode::Flying,
+            yaw: self.camera.yaw,
+            pitch: self.camera.pitch,
+        };
+
+        match world_save.save(&mut self.simulation.world, &self.world_metadata) {
+            Ok(chunks) => log::info!("saved {chunks} chunks to {}", world_save.dir().display()),
+            Err(err) => log::error!("failed to save world: {err}"),
         }
     }
 
     pub fn request_redraw(&self) {
-        self.window.request_redraw();
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
     }
 
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         self.surface_config.width = new_size.width.max(1);
         self.surface_config.height = new_size.height.max(1);
-        self.surface.configure(&self.device, &self.surface_config);
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.surface_config);
+        }
 
         self.camera = Camera {
             aspect_ratio: self.surface_config.width as f32 / self.surface_config.height as f32,
@@ -175,23 +488,242 @@ impl Graphics {
         };
 
         self.globals.resolution = [self.surface_config.width, self.surface_config.height];
-
-        let (depth_texture, depth_texture_view) =
-            create_depth_texture(&self.device, &self.surface_config);
-        self.depth_texture = depth_texture;
-        self.depth_texture_view = depth_texture_view;
+        self.texture_pool.clear();
     }
 
-    pub fn run_cs(&self, command_encoder: &mut CommandEncoder) {}
+    /// Declares this frame's passes with the resources they read and write; the graph works
+    /// out their order and the transient textures they need.
+    pub fn render_graph<'a>(&'a self, target: &'a TextureView) -> RenderGraph<'a> {
+        let mut graph = RenderGraph::new();
+        graph.import_texture(OUTPUT, target);
+        graph.import_buffer("vertices", &self.buffers.vertices);
+        graph.import_texture("shadow_map", &self.shadow_map.view);
+        graph.create_texture(
+            "depth",
+            TextureDesc::frame(
+                TextureFormat::Depth32Float,
+                TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            ),
+        );
+        let color_usage = TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING;
+        graph.create_texture("hdr", TextureDesc::frame(HDR_FORMAT, color_usage));
+        graph.create_texture("normals", TextureDesc::frame(NORMAL_FORMAT, color_usage));
+        let bloom_levels = self.bloom_levels();
+        for (i, name) in BLOOM_TEXTURES[..bloom_levels].iter().enumerate() {
+            let desc = TextureDesc {
+                size: TextureSize::Downscaled {
+                    levels: i as u32 + 1,
+                },
+                format: HDR_FORMAT,
+                usage: color_usage,
+            };
+            graph.create_texture(name, desc);
+        }
 
-    pub fn run_rs(&self, command_encoder: &mut CommandEncoder, frame: &mut SurfaceTexture) {
-        self.render_pass.encode(
-            command_encoder,
-            &frame.texture.create_view(&TextureViewDescriptor::default()),
-            &self.depth_texture_view,
-            &self.bind_groups_render.as_slice(),
-            &self.buffers.vertices,
+        graph.add_pass(
+            "shadow",
+            &["vertices"],
+            &["shadow_map"],
+            |resources, encoder| {
+                self.shadow_pass.encode(
+                    encoder,
+                    &self.shadow_map,
+                    self.lights.cascade_count,
+                    resources.buffer("vertices"),
+                    &self.vertex_ranges,
+                    &self.bind_groups_render.atlas,
+                );
+            },
         );
+        graph.add_pass(
+            "world",
+            &["vertices", "shadow_map"],
+            &["hdr", "normals", "depth"],
+            |resources, encoder| {
+                let targets = RenderTargets {
+                    color: resources.texture("hdr"),
+                    normals: resources.texture("normals"),
+                    depth: resources.texture("depth"),
+                };
+                self.render_pass.encode(
+                    encoder,
+                    targets,
+                    &self.bind_groups_render.as_slice(),
+                    resources.buffer("vertices"),
+                    &self.vertex_ranges,
+                );
+            },
+        );
+        graph.add_pass("sky", &["depth"], &["hdr"], |resources, encoder| {
+            self.sky_pass.encode(
+                encoder,
+                resources.texture("hdr"),
+                resources.texture("depth"),
+                &self.bind_groups_render.as_slice()[..2],
+            );
+        });
+        let water = self.vertex_ranges[RenderLayer::Water as usize].clone();
+        if !water.is_empty() {
+            graph.create_texture("scene", TextureDesc::frame(HDR_FORMAT, color_usage));
+            graph.add_pass(
+                "scene_copy",
+                &["hdr", "depth"],
+                &["scene"],
+                |resources, encoder| {
+                    self.water_pass.encode_scene_copy(
+                        encoder,
+                        &self.device,
+                        resources.texture("hdr"),
+                        resources.texture("depth"),
+                        &self.bind_groups_render.bview,
+                        resources.texture("scene"),
+                    );
+                },
+            );
+            graph.add_pass(
+                "water",
+                &["vertices", "shadow_map", "scene"],
+                &["hdr", "depth"],
+                move |resources, encoder| {
+                    let targets = WaterTargets {
+                        color: resources.texture("hdr"),
+                        depth: resources.texture("depth"),
+                        scene: resources.texture("scene"),
+                    };
+                    self.water_pass.encode(
+                        encoder,
+                        &self.device,
+                        targets,
+                        &self.bind_groups_render.as_slice()[..2],
+                        resources.buffer("vertices"),
+                        water.clone(),
+                    );
+                },
+            );
+        }
+        // After the sky, which would otherwise cover translucent faces in front of it as
+        // they leave the depth buffer untouched.
+        graph.add_pass(
+            "translucent",
+            &["vertices", "shadow_map", "depth"],
+            &["hdr"],
+            |resources, encoder| {
+                self.render_pass.encode_translucent(
+                    encoder,
+                    resources.texture("hdr"),
+                    resources.texture("depth"),
+                    &self.bind_groups_render.as_slice(),
+                    resources.buffer("vertices"),
+                    self.vertex_ranges[RenderLayer::Translucent as usize].clone(),
+                );
+            },
+        );
+
+        // Without SSAO the tonemapper is given the scene in its place and doesn't read it.
+        let ao = match self.ambient_occlusion {
+            Some(_) => "ao",
+            None => "hdr",
+        };
+        if self.ambient_occlusion.is_some() {
+            graph.create_texture("ao", TextureDesc::frame(AO_FORMAT, color_usage));
+            graph.create_texture("ao_blur", TextureDesc::frame(AO_FORMAT, color_usage));
+            graph.add_pass(
+                "ssao",
+                &["depth", "normals"],
+                &["ao", "ao_blur"],
+                |resources, encoder| {
+                    let targets = SsaoTargets {
+                        depth: resources.texture("depth"),
+                        normals: resources.texture("normals"),
+                        ao: resources.texture("ao"),
+                        scratch: resources.texture("ao_blur"),
+                    };
+                    self.ssao_pass
+                        .encode(encoder, &self.device, &self.buffers.view, targets);
+                },
+            );
+        }
+
+        // Without bloom the tonemapper gets the scene as its bloom input, at zero intensity.
+        let bloom = match bloom_levels {
+            0 => "hdr",
+            _ => BLOOM_TEXTURES[0],
+        };
+        if bloom_levels > 0 {
+            let levels = &BLOOM_TEXTURES[..bloom_levels];
+            graph.add_pass("bloom", &["hdr"], levels, move |resources, encoder| {
+                let views: Vec<&TextureView> =
+                    levels.iter().map(|name| resources.texture(name)).collect();
+                self.post_pass.encode_bloom(
+                    encoder,
+                    &self.device,
+                    resources.texture("hdr"),
+                    &views,
+                );
+            });
+        }
+        graph.add_pass(
+            "tonemap",
+            &["hdr", bloom, ao],
+            &[OUTPUT],
+            move |resources, encoder| {
+                self.post_pass.encode_tonemap(
+                    encoder,
+                    &self.device,
+                    resources.texture("hdr"),
+                    resources.texture(bloom),
+                    resources.texture(ao),
+                    resources.texture(OUTPUT),
+                );
+            },
+        );
+        graph
+    }
+
+    /// Bloom mip levels this frame, 0 when bloom is off.
+    fn bloom_levels(&self) -> usize {
+        if self.post_effects.bloom_intensity <= 0.0 {
+            return 0;
+        }
+        post_pass::bloom_levels((self.surface_config.width, self.surface_config.height))
+    }
+
+    /// Remeshes edited chunks and uploads the world mesh, replacing the atlas texture and
+    /// vertex buffer when they no longer fit.
+    pub fn update_world_mesh(&mut self) {
+        let world = &mut self.simulation.world;
+        if world.remesh_chunks().is_empty() {
+            return;
+        }
+        let atlas_changed = self.world_mesh.update(world);
+
+        let vertices = self.world_mesh.vertices();
+        let buffer_replaced = self
+            .buffers
+            .reserve_vertices(&self.device, vertices.len() as u64);
+        self.queue
+            .write_buffer(&self.buffers.vertices, 0, bytemuck::cast_slice(&vertices));
+        self.vertex_ranges = self.world_mesh.layer_ranges();
+
+        if atlas_changed {
+            self.textures = Textures::new(&self.device, &self.queue, &self.world_mesh.atlas);
+        }
+        if atlas_changed || buffer_replaced {
+            self.bind_groups_compute = bind_groups::BindGroups::new(
+                &self.device,
+                &self.bind_group_layouts_compute,
+                &self.buffers,
+                &self.textures,
+                &self.shadow_map,
+            );
+            self.bind_groups_render = bind_groups::BindGroups::new(
+                &self.device,
+                &self.bind_group_layouts_render,
+                &self.buffers,
+                &self.textures,
+                &self.shadow_map,
+            );
+        }
     }
 
     pub fn update_uniforms(&self) {
@@ -199,82 +731,361 @@ impl Graphics {
             .write_buffer(&self.buffers.globals, 0, bytemuck::bytes_of(&self.globals));
         self.queue
             .write_buffer(&self.buffers.view, 0, bytemuck::bytes_of(&self.view));
+        self.queue
+            .write_buffer(&self.buffers.lights, 0, bytemuck::bytes_of(&self.lights));
+        self.queue
+            .write_buffer(&self.buffers.fog, 0, bytemuck::bytes_of(&self.fog));
+        self.shadow_pass.write_cascades(&self.queue, &self.lights);
+        let mut post_effects = self.post_effects;
+        if self.bloom_levels() == 0 {
+            post_effects.bloom_intensity = 0.0;
+        }
+        self.post_pass.write_effects(&self.queue, &post_effects);
+        if let Some(ambient_occlusion) = &self.ambient_occlusion {
+            self.ssao_pass.write_params(&self.queue, ambient_occlusion);
+        }
     }
 
-    pub fn update(&mut self) {
-        let delta_time = (Instant::now() - self.metadata.prev_frame_start_insant).as_secs_f32();
-        let shift_multiplier = if self
-            .metadata
-            .keyboard_state
-            .contains(&winit::keyboard::KeyCode::ShiftLeft)
-        {
-            5.0
-        } else {
-            1.0
+    /// Rebuilds the pipelines whose shaders changed on disk. A shader that fails to compile
+    /// is logged and the previous pipeline kept.
+    pub fn reload_shaders(&mut self) {
+        let Some(watcher) = &self.shader_watcher else {
+            return;
         };
-
-        let mut direction = glam::Vec3::ZERO;
-        if self
-            .metadata
-            .keyboard_state
-            .contains(&winit::keyboard::KeyCode::KeyW)
-        {
-            direction += self.camera.forward();
+        let changed = watcher.changed();
+        if changed.is_empty() {
+            return;
         }
-        if self
-            .metadata
-            .keyboard_state
-            .contains(&winit::keyboard::KeyCode::KeyS)
+        let stale = |files: &BTreeSet<String>| !changed.is_disjoint(files);
+        if !stale(&self.render_pass.files)
+            && !stale(&self.shadow_pass.files)
+            && !stale(&self.sky_pass.files)
+            && !stale(&self.post_pass.files)
+            && !stale(&self.ssao_pass.files)
+            && !stale(&self.water_pass.files)
         {
-            direction -= self.camera.forward();
+            log::debug!("{changed:?} changed, but no pipeline uses them");
+            return;
         }
-        if self
-            .metadata
-            .keyboard_state
-            .contains(&winit::keyboard::KeyCode::KeyA)
-        {
-            direction -= self.camera.right();
+
+        let layouts = self.bind_group_layouts_render.as_slice();
+        if stale(&self.render_pass.files) {
+            let loaded = RenderPass::load(&self.device, &layouts, HDR_FORMAT, &shaders::read);
+            replace_pipeline(&mut self.render_pass, loaded, render_pass::SHADER);
         }
-        if self
-            .metadata
-            .keyboard_state
-            .contains(&winit::keyboard::KeyCode::KeyD)
-        {
-            direction += self.camera.right();
+        if stale(&self.shadow_pass.files) {
+            let loaded = ShadowPass::load(
+                &self.device,
+                &self.bind_group_layouts_render.atlas,
+                &shaders::read,
+            );
+            replace_pipeline(&mut self.shadow_pass, loaded, shadow_pass::SHADER);
+        }
+        if stale(&self.sky_pass.files) {
+            let loaded = SkyPass::load(&self.device, &layouts[..2], HDR_FORMAT, &shaders::read);
+            replace_pipeline(&mut self.sky_pass, loaded, sky_pass::SHADER);
+        }
+        if stale(&self.post_pass.files) {
+            let format = self.surface_config.format;
+            let loaded = PostPass::load(&self.device, format, &shaders::read);
+            replace_pipeline(&mut self.post_pass, loaded, post_pass::SHADER);
+        }
+        if stale(&self.ssao_pass.files) {
+            let loaded = SsaoPass::load(&self.device, &shaders::read);
+            replace_pipeline(&mut self.ssao_pass, loaded, ssao_pass::SHADER);
+        }
+        if stale(&self.water_pass.files) {
+            let loaded = WaterPass::load(&self.device, &layouts[..2], &shaders::read);
+            replace_pipeline(&mut self.water_pass, loaded, water_pass::SHADER);
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.reload_shaders();
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepads) = &mut self.gamepads {
+            gamepads.poll(&mut self.input);
         }
-        if direction.length_squared() > 0.0 {
-            direction = direction.normalize();
+        self.input.update();
+        if self.input.just_pressed(Action::Rebind) {
+            self.input.begin_rebind_all();
         }
+        self.update_rebind();
 
-        self.camera.position += direction * self.camera.speed * delta_time * shift_multiplier;
-        self.camera.update_rotation(self.metadata.delta_mouse);
-        self.metadata.delta_mouse = Vec2::ZERO;
+        if self.input.just_pressed(Action::Screenshot) {
+            self.save_screenshot();
+        }
+
+        if self.input.just_pressed(Action::ToggleFly) {
+            self.simulation.player.toggle_mode();
+        }
+        let step = self.input.just_pressed(Action::NextBlock) as i32
+            - self.input.just_pressed(Action::PreviousBlock) as i32;
+        if step != 0 {
+            self.held_block = cycle_held_block(self.held_block, step);
+            self.update_title();
+        }
+        if self.cursor_captured {
+            self.edit_blocks();
+        }
+        if self.input.just_pressed(Action::ReleaseCursor) {
+            self.cursor_captured = false;
+        } else if self.input.just_pressed(Action::CaptureCursor) {
+            self.cursor_captured = true;
+        }
+
+        let direction = self.camera.forward()
+            * self.input.axis(Action::MoveBackward, Action::MoveForward)
+            + self.camera.right() * self.input.axis(Action::MoveLeft, Action::MoveRight);
+
+        let input = PlayerInput {
+            direction,
+            vertical: self.input.axis(Action::Descend, Action::Jump),
+            jump: self.input.pressed(Action::Jump),
+            sprint: self.input.pressed(Action::Sprint),
+        };
+        self.simulation.update(&input);
+        self.update_world_mesh();
+
+        let look = Vec2::new(
+            self.input.axis(Action::LookLeft, Action::LookRight),
+            self.input.axis(Action::LookUp, Action::LookDown),
+        ) * GAMEPAD_LOOK_SPEED
+            * self.globals.frame_time;
+
+        self.camera.position = self.simulation.interpolated_eye_position();
+        self.camera.update_rotation(self.input.mouse_delta() + look);
     }
 
-    pub fn draw(&mut self) {
+    /// Shows which action the next input will be bound to, and saves the controls once every
+    /// action has had its turn.
+    fn update_rebind(&mut self) {
+        let rebinding = self.input.rebinding();
+        if rebinding == self.rebinding {
+            return;
+        }
+        self.rebinding = rebinding;
+        if rebinding.is_none() {
+            match self.input.map.save(&self.input_map_path) {
+                Ok(()) => log::info!("saved controls to {}", self.input_map_path.display()),
+                Err(err) => log::error!("{}: {err}", self.input_map_path.display()),
+            }
+        }
+        self.update_title();
+    }
+
+    /// The window title, followed by the action being rebound or else the held block.
+    pub fn update_title(&self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        let status = match self.rebinding {
+            Some(action) => {
+                let skip = self.input.map.bindings(Action::Rebind);
+                format!("press a key or button for {action:?}, or {skip:?} to skip")
+            }
+            None => format!("holding {}", self.held_block.properties().name),
+        };
+        window.set_title(&format!("{} - {status}", self.title));
+    }
+
+    /// Breaks or places the block the camera looks at. Broken blocks leave their water behind;
+    /// placed blocks are turned to the camera and never put where the player stands.
+    fn edit_blocks(&mut self) {
+        let breaking = self.input.just_pressed(Action::Break);
+        if !breaking && !self.input.just_pressed(Action::Place) {
+            return;
+        }
+        let look = self.camera.forward();
+        let world = &self.simulation.world;
+        let Some(hit) = raycast(world, self.camera.position, look, REACH) else {
+            return;
+        };
+        if breaking {
+            let waterlogged = world.get_block(hit.pos).is_waterlogged();
+            let left = if waterlogged {
+                BlockId::WATER
+            } else {
+                BlockId::AIR
+            };
+            self.simulation.set_block(hit.pos, left);
+            return;
+        }
+        let (pos, block) = placement(world, &hit, look, self.held_block);
+        let replaced = world.get_block(pos);
+        let player = self.simulation.player.aabb();
+        let blocked = Aabb::block(pos, block).any(|collider| player.intersects(&collider));
+        if (replaced.is_air() || replaced.fluid().is_some() || pos == hit.pos) && !blocked {
+            self.simulation.set_block(pos, block);
+        }
+    }
+
+    /// Advances the frame counters and uploads this frame's uniforms.
+    fn begin_frame(&mut self) {
         let now = Instant::now();
 
         self.globals.frame += 1;
         self.globals.time_passed = (now - self.metadata.start_instant).as_secs_f32();
         self.globals.frame_time = (now - self.metadata.prev_frame_start_insant).as_secs_f32();
         self.metadata.prev_frame_start_insant = now;
+        self.blend_biome_fog();
 
+        self.prepare_frame();
+    }
+
+    /// Works out the frame's view, lights and fog from the camera and the world as they are
+    /// and uploads them, without advancing time.
+    fn prepare_frame(&mut self) {
         self.view = self.camera.get_view();
+        self.sort_translucent();
+        self.globals.time_of_day = self.simulation.time_of_day();
+        self.lights =
+            lighting::lights(&self.camera, self.globals.time_of_day, self.shadow_settings);
+        let medium = Medium::at(&self.simulation.world, self.camera.position);
+        self.fog = fog::fog(self.fog_settings, medium, self.biome_fog, &self.lights);
         self.update_uniforms();
+    }
+
+    /// Keeps the translucent faces in the vertex buffer ordered back to front from the camera.
+    fn sort_translucent(&mut self) {
+        if !self.world_mesh.sort_translucent(self.camera.position) {
+            return;
+        }
+        let range = &self.vertex_ranges[RenderLayer::Translucent as usize];
+        let offset = range.start as u64 * std::mem::size_of::<VertexBuffer>() as u64;
+        let vertices = self.world_mesh.translucent_vertices();
+        self.queue.write_buffer(
+            &self.buffers.vertices,
+            offset,
+            bytemuck::cast_slice(&vertices),
+        );
+    }
+
+    /// Eases the fog toward the camera's biome over a moment. Entering or leaving water
+    /// switches the fog at once instead, see `prepare_frame`.
+    fn blend_biome_fog(&mut self) {
+        let position = self.camera.position.floor().as_ivec3();
+        let biome = Biome::at(self.world_metadata.seed, position.x, position.z);
+        let blend = 1.0 - (-fog::BIOME_BLEND_RATE * self.globals.frame_time).exp();
+        self.biome_fog = self.biome_fog.lerp(biome.fog(), blend);
+    }
+
+    fn render_to(&mut self, target: &TextureView) {
+        let mut pool = std::mem::take(&mut self.texture_pool);
+        let frame_size = (self.surface_config.width, self.surface_config.height);
+        let result =
+            self.render_graph(target)
+                .execute(&self.device, &self.queue, &mut pool, frame_size);
+        if let Err(err) = result {
+            log::error!("failed to render frame: {err}");
+        }
+        self.texture_pool = pool;
+    }
 
-        let mut frame = self
-            .surface
+    /// The offscreen target, recreated if the frame size or format changed since it was last
+    /// used.
+    fn offscreen_target(&mut self) -> &OffscreenTarget {
+        let config = &self.surface_config;
+        let stale = self.offscreen.as_ref().is_none_or(|target| {
+            target.width() != config.width
+                || target.height() != config.height
+                || target.format() != config.format
+        });
+        if stale {
+            self.offscreen = Some(OffscreenTarget::new(
+                &self.device,
+                config.width,
+                config.height,
+                config.format,
+            ));
+        }
+        self.offscreen.as_ref().unwrap()
+    }
+
+    pub fn draw(&mut self) {
+        self.begin_frame();
+
+        let Some(surface) = &self.surface else {
+            let target = self.offscreen_target();
+            let view = target.view.clone();
+            self.render_to(&view);
+            return;
+        };
+
+        let frame = surface
             .get_current_texture()
             .expect("Failed to acquire next swap chain texture.");
+        self.render_to(&frame.texture.create_view(&TextureViewDescriptor::default()));
+        frame.present();
+    }
+
+    /// Renders the current frame into an offscreen texture and reads it back, without
+    /// advancing time.
+    pub fn capture(&mut self) -> Result<RgbaImage, ReadbackError> {
+        self.prepare_frame();
+        let view = self.offscreen_target().view.clone();
+        self.render_to(&view);
+        let target = self.offscreen.as_ref().unwrap();
+        target.read_rgba(&self.device, &self.queue)
+    }
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor { label: None });
+    /// Captures a frame into the screenshot directory, named after the current time.
+    pub fn save_screenshot(&mut self) {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = self.screenshot_dir.join(format!("screenshot-{millis}.png"));
+        let image = match self.capture() {
+            Ok(image) => image,
+            Err(err) => {
+                log::error!("failed to capture a screenshot: {err}");
+                return;
+            }
+        };
+        match image.save(&path) {
+            Ok(()) => log::info!("saved screenshot to {}", path.display()),
+            Err(err) => log::error!("failed to save screenshot {}: {err}", path.display()),
+        }
+    }
+}
 
-        self.run_cs(&mut encoder);
-        self.run_rs(&mut encoder, &mut frame);
+#[cfg(test)]
+mod tests {
+    use std::fs;
 
-        self.queue.submit(Some(encoder.finish()));
-        frame.present();
+    use super::*;
+    use crate::world::storage::metadata::METADATA_FILE;
+
+    #[test]
+    fn unreadable_worlds_are_not_saved_over() {
+        let dir = std::env::temp_dir().join(format!(
+            "rust-voxel-blocks-corrupt-save-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        WorldMetadata::new(7, [1.0, 2.0, 3.0]).save(&dir).unwrap();
+        let metadata_text = fs::read_to_string(dir.join(METADATA_FILE)).unwrap();
+        fs::write(dir.join("r.0.0.0.vxr"), b"garbage").unwrap();
+
+        let mut settings = Settings::default();
+        settings.world.path = dir.clone();
+        let (world_save, world, _) = load_world(&settings);
+        assert!(world_save.is_none());
+        assert!(world.chunks().count() > 0);
+
+        assert_eq!(
+            fs::read_to_string(dir.join(METADATA_FILE)).unwrap(),
+            metadata_text
+        );
+        assert_eq!(fs::read(dir.join("r.0.0.0.vxr")).unwrap(), b"garbage");
+        fs::remove_dir_all(&dir).unwrap();
+
+        // A directory with nothing in it yet is a new world, saved as usual.
+        let (world_save, mut world, metadata) = load_world(&settings);
+        world_save.unwrap().save(&mut world, &metadata).unwrap();
+        assert!(dir.join(METADATA_FILE).exists());
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
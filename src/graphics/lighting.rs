@@ -0,0 +1,266 @@
+use std::f32::consts::TAU;
+
+use glam::{Mat4, Vec3};
+
+use crate::graphics::{
+    camera::Camera,
+    structures::{Lights, MAX_CASCADES},
+};
+
+/// Tilt of the sun's path away from the zenith, so noon shadows aren't straight down.
+pub const SUN_TILT: f32 = 0.45;
+/// How far behind a cascade, toward the sun, shadow casters are still captured.
+const CASTER_DISTANCE: f32 = 128.0;
+/// Blend between logarithmic (1) and uniform (0) cascade splits.
+const SPLIT_LAMBDA: f32 = 0.75;
+/// Near distance the logarithmic splits start from.
+const SPLIT_NEAR: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowSettings {
+    pub cascades: u32,
+    pub map_size: u32,
+    pub distance: f32,
+}
+
+/// Unit vector toward the sun. `time_of_day` runs from 0 to 1: midnight at 0, sunrise in +X
+/// at 0.25, noon at 0.5 and sunset in -X at 0.75.
+pub fn sun_direction(time_of_day: f32) -> Vec3 {
+    let angle = (time_of_day - 0.25) * TAU;
+    let (sin, cos) = angle.sin_cos();
+    Vec3::new(cos, sin * SUN_TILT.cos(), sin * SUN_TILT.sin())
+}
+
+/// Sky color at the zenith and at the horizon for a sun direction: blue by day, reddened
+/// toward the horizon around sunrise and sunset, and dark blue at night. Linear RGB.
+pub fn sky_colors(sun_direction: Vec3) -> (Vec3, Vec3) {
+    let elevation = sun_direction.y;
+    let daylight = smoothstep(-0.2, 0.15, elevation);
+    let twilight =
+        (1.0 - smoothstep(0.0, 0.35, elevation.abs())) * smoothstep(-0.2, 0.0, elevation);
+
+    let zenith = Vec3::new(0.002, 0.004, 0.012)
+        .lerp(Vec3::new(0.12, 0.32, 0.85), daylight)
+        .lerp(Vec3::new(0.15, 0.2, 0.45), twilight * 0.6);
+    let horizon = Vec3::new(0.01, 0.015, 0.03)
+        .lerp(Vec3::new(0.55, 0.7, 0.9), daylight)
+        .lerp(Vec3::new(0.9, 0.45, 0.2), twilight * 0.8);
+    (zenith, horizon)
+}
+
+/// Ambient light from a sky with these colors: their average, half desaturated, plus a little
+/// moonlight so nights aren't pitch black.
+pub fn sky_light(zenith: Vec3, horizon: Vec3) -> Vec3 {
+    let average = (zenith + horizon) * 0.5;
+    let luminance = average.dot(Vec3::new(0.2126, 0.7152, 0.0722));
+    Vec3::splat(luminance).lerp(average, 0.5) * 0.8 + Vec3::new(0.02, 0.025, 0.04)
+}
+
+/// Direction toward and color of the light casting shadows: the sun while it is up, warm and
+/// dim near the horizon, otherwise the moon.
+pub fn direct_light(sun_direction: Vec3) -> (Vec3, Vec3) {
+    let elevation = sun_direction.y;
+    if elevation >= 0.0 {
+        let warmth = 1.0 - smoothstep(0.0, 0.4, elevation);
+        let color = Vec3::new(1.0, 0.96, 0.9).lerp(Vec3::new(1.0, 0.6, 0.35), warmth);
+        (sun_direction, color * smoothstep(-0.02, 0.1, elevation))
+    } else {
+        let color = Vec3::new(0.12, 0.14, 0.2);
+        (-sun_direction, color * smoothstep(-0.02, 0.1, -elevation))
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// View distances where each cascade ends, from the practical split scheme: a blend of
+/// logarithmic splits, which keep texel density even, and uniform ones.
+pub fn cascade_splits(count: u32, distance: f32) -> Vec<f32> {
+    (1..=count)
+        .map(|i| {
+            let fraction = i as f32 / count as f32;
+            let log = SPLIT_NEAR * (distance / SPLIT_NEAR).powf(fraction);
+            let uniform = distance * fraction;
+            SPLIT_LAMBDA * log + (1.0 - SPLIT_LAMBDA) * uniform
+        })
+        .collect()
+}
+
+/// Bounding sphere of the slice of the camera frustum between view distances `near` and
+/// `far`. Its radius only depends on the projection, so it doesn't change as the camera turns.
+fn slice_bounds(camera: &Camera, near: f32, far: f32) -> (Vec3, f32) {
+    let tan_y = (camera.fov * 0.5).tan();
+    let tan_x = tan_y * camera.aspect_ratio;
+    let corners = [near, far].into_iter().flat_map(|depth| {
+        [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+            .map(|(x, y)| Vec3::new(x * tan_x * depth, y * tan_y * depth, -depth))
+    });
+    let corners: Vec<Vec3> = corners.collect();
+    let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+    let radius = corners
+        .iter()
+        .map(|corner| corner.distance(center))
+        .fold(0.0, f32::max);
+    // Rounded up so float noise in the radius can't change the texel size between frames.
+    let radius = (radius * 16.0).ceil() / 16.0;
+    (camera.position + camera.rotation * center, radius.max(1.0))
+}
+
+/// Orthographic light projection covering a bounding sphere, with its origin snapped to
+/// whole shadow map texels so shadow edges don't crawl as the camera moves. Depth is 0 at
+/// the sun and 1 behind the sphere.
+pub fn cascade_matrix(center: Vec3, radius: f32, sun_direction: Vec3, map_size: u32) -> Mat4 {
+    let up = if sun_direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    let light_view = Mat4::look_to_rh(Vec3::ZERO, -sun_direction, up);
+
+    let texel = 2.0 * radius / map_size as f32;
+    let center = light_view.transform_point3(center);
+    let x = (center.x / texel).floor() * texel;
+    let y = (center.y / texel).floor() * texel;
+    let projection = Mat4::orthographic_rh(
+        x - radius,
+        x + radius,
+        y - radius,
+        y + radius,
+        -center.z - radius - CASTER_DISTANCE,
+        -center.z + radius,
+    );
+    projection * light_view
+}
+
+/// Sun or moon light, sky light and shadow cascades for the current camera and time of day.
+pub fn lights(camera: &Camera, time_of_day: f32, shadows: ShadowSettings) -> Lights {
+    let sun_direction = sun_direction(time_of_day);
+    let (light_direction, light_color) = direct_light(sun_direction);
+    let (zenith, horizon) = sky_colors(sun_direction);
+    let count = shadows.cascades.min(MAX_CASCADES as u32);
+
+    let mut lights = Lights {
+        sun_direction: sun_direction.into(),
+        light_direction: light_direction.into(),
+        light_color: light_color.into(),
+        sky_light: sky_light(zenith, horizon).into(),
+        zenith_color: zenith.into(),
+        horizon_color: horizon.into(),
+        cascade_count: count,
+        depth_bias: 0.0005,
+        normal_offset: 1.5,
+        pcf_radius: 1,
+        ..Default::default()
+    };
+    let mut near = 0.0;
+    for (i, far) in cascade_splits(count, shadows.distance)
+        .into_iter()
+        .enumerate()
+    {
+        let (center, radius) = slice_bounds(camera, near, far);
+        lights.cascades[i] = cascade_matrix(center, radius, light_direction, shadows.map_size);
+        lights.cascade_splits[i] = far;
+        lights.cascade_texel_sizes[i] = 2.0 * radius / shadows.map_size as f32;
+        near = far;
+    }
+    // Unused cascades never match a view depth.
+    for i in count as usize..MAX_CASCADES {
+        lights.cascade_splits[i] = f32::MAX;
+    }
+    lights
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec2, Vec4, Vec4Swizzles};
+
+    use super::*;
+
+    fn camera(position: Vec3, yaw: f32) -> Camera {
+        let mut camera = Camera::new(16.0 / 9.0, 70f32.to_radians(), 0.002);
+        camera.position = position;
+        camera.yaw = yaw;
+        camera.update_rotation(Vec2::ZERO);
+        camera
+    }
+
+    #[test]
+    fn sun_rises_in_the_east_and_peaks_at_noon() {
+        assert!(sun_direction(0.25).abs_diff_eq(Vec3::X, 1e-5));
+        assert!(sun_direction(0.75).abs_diff_eq(-Vec3::X, 1e-5));
+        assert!(sun_direction(0.5).y > 0.85);
+        assert!(sun_direction(0.0).y < -0.85);
+    }
+
+    #[test]
+    fn the_moon_takes_over_at_night() {
+        let (direction, color) = direct_light(sun_direction(0.0));
+        assert!(direction.y > 0.85);
+        assert!(color.max_element() < 0.25);
+        let (direction, _) = direct_light(sun_direction(0.5));
+        assert!(direction.abs_diff_eq(sun_direction(0.5), 1e-6));
+
+        let sky = |time_of_day| {
+            let (zenith, horizon) = sky_colors(sun_direction(time_of_day));
+            sky_light(zenith, horizon)
+        };
+        let (night, day) = (sky(0.0), sky(0.5));
+        assert!(night.max_element() < 0.1);
+        assert!(day.min_element() > 0.25);
+    }
+
+    #[test]
+    fn horizon_reddens_at_sunset() {
+        let (_, noon) = sky_colors(sun_direction(0.5));
+        let (_, sunset) = sky_colors(sun_direction(0.75));
+        assert!(noon.z > noon.x);
+        assert!(sunset.x > sunset.z);
+    }
+
+    #[test]
+    fn splits_grow_and_end_at_the_shadow_distance() {
+        let splits = cascade_splits(4, 96.0);
+        assert!(splits.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(splits[0] > SPLIT_NEAR && splits[0] < 96.0 / 4.0);
+        assert!((splits[3] - 96.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cascades_cover_their_frustum_slice() {
+        let camera = camera(Vec3::new(10.0, 20.0, -5.0), 0.7);
+        let shadows = ShadowSettings {
+            cascades: 3,
+            map_size: 1024,
+            distance: 64.0,
+        };
+        let lights = lights(&camera, 0.4, shadows);
+        let mut near = 0.0;
+        for i in 0..3 {
+            let far = lights.cascade_splits[i];
+            for depth in [near, far] {
+                let point = camera.position + camera.forward() * depth;
+                let clip = lights.cascades[i] * point.extend(1.0);
+                assert!(clip.xy().abs().max_element() <= 1.0, "cascade {i}: {clip}");
+                assert!((0.0..=1.0).contains(&clip.z), "cascade {i}: {clip}");
+            }
+            near = far;
+        }
+        assert_eq!(lights.cascade_splits[3], f32::MAX);
+    }
+
+    #[test]
+    fn snapping_moves_cascades_in_whole_texels() {
+        let map_size = 512;
+        let direction = sun_direction(0.4);
+        let project = |center: Vec3| {
+            let clip = cascade_matrix(center, 16.0, direction, map_size) * Vec4::W;
+            clip.xy() * 0.5 * map_size as f32
+        };
+        let a = project(Vec3::new(3.0, 1.0, 2.0));
+        let b = project(Vec3::new(3.013, 1.007, 2.21));
+        let moved = b - a;
+        assert!(moved.abs_diff_eq(moved.round(), 1e-2), "{moved}");
+    }
+}
@@ -2,7 +2,21 @@ pub mod bind_group_layouts;
 pub mod bind_groups;
 pub mod buffers;
 pub mod camera;
-pub mod compute_pass;
+pub mod fog;
+pub mod gpu;
+#[allow(clippy::module_inception)]
 pub mod graphics;
+pub mod lighting;
+pub mod offscreen;
+pub mod post_pass;
+pub mod preprocessor;
+pub mod render_graph;
 pub mod render_pass;
+pub mod shader_layout;
+pub mod shaders;
+pub mod shadow_pass;
+pub mod sky_pass;
+pub mod ssao_pass;
 pub mod structures;
+pub mod textures;
+pub mod water_pass;
@@ -0,0 +1,212 @@
+use std::fmt;
+
+use wgpu::*;
+
+use crate::formats::image::RgbaImage;
+
+#[derive(Debug)]
+pub enum ReadbackError {
+    UnsupportedFormat(TextureFormat),
+}
+
+impl fmt::Display for ReadbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadbackError::UnsupportedFormat(format) => {
+                write!(f, "cannot read back {format:?} as RGBA8")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReadbackError {}
+
+/// Color texture that can be rendered into instead of a surface and read back to the CPU.
+pub struct OffscreenTarget {
+    pub texture: Texture,
+    pub view: TextureView,
+}
+
+impl OffscreenTarget {
+    pub fn new(device: &Device, width: u32, height: u32, format: TextureFormat) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        Self { texture, view }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.height()
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.texture.format()
+    }
+
+    /// Copies the texture into a staging buffer and blocks until it can be read, converting
+    /// each texel to RGBA8 with `to_rgba8`.
+    pub fn read_rgba(&self, device: &Device, queue: &Queue) -> Result<RgbaImage, ReadbackError> {
+        let format = self.format();
+        let texel_bytes = match format {
+            TextureFormat::Rgba16Float => 8,
+            _ if is_readable(format) => 4,
+            _ => return Err(ReadbackError::UnsupportedFormat(format)),
+        };
+
+        let (width, height) = (self.width(), self.height());
+        let row_bytes = width * texel_bytes;
+        let padded_row_bytes = row_bytes.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+        let staging = device.create_buffer(&BufferDescriptor {
+            label: Some("Offscreen Readback Buffer"),
+            size: (padded_row_bytes * height) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Offscreen Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            TexelCopyBufferInfo {
+                buffer: &staging,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            self.texture.size(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        slice.map_async(MapMode::Read, |result| {
+            result.expect("Failed to map readback buffer")
+        });
+        device
+            .poll(PollType::wait_indefinitely())
+            .expect("Failed to wait for readback");
+
+        let mut image = RgbaImage::new(width, height);
+        {
+            let mapped = slice.get_mapped_range();
+            for (row, padded) in image
+                .pixels
+                .chunks_exact_mut(width as usize * 4)
+                .zip(mapped.chunks_exact(padded_row_bytes as usize))
+            {
+                let texels = padded[..row_bytes as usize].chunks_exact(texel_bytes as usize);
+                for (pixel, texel) in row.chunks_exact_mut(4).zip(texels) {
+                    pixel.copy_from_slice(&to_rgba8(format, texel));
+                }
+            }
+        }
+        staging.unmap();
+        Ok(image)
+    }
+}
+
+/// Whether `to_rgba8` converts texels of `format`. These are the formats surfaces offer;
+/// sRGB encoding is already applied to the non-sRGB ones by the post pass.
+fn is_readable(format: TextureFormat) -> bool {
+    matches!(
+        format,
+        TextureFormat::Rgba8Unorm
+            | TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Bgra8Unorm
+            | TextureFormat::Bgra8UnormSrgb
+            | TextureFormat::Rgb10a2Unorm
+            | TextureFormat::Rgba16Float
+    )
+}
+
+/// One texel of a readable format as 8-bit RGBA, keeping its encoding. Float channels are
+/// clamped to `0..=1`.
+fn to_rgba8(format: TextureFormat, texel: &[u8]) -> [u8; 4] {
+    let unorm = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    match format {
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            [texel[2], texel[1], texel[0], texel[3]]
+        }
+        TextureFormat::Rgb10a2Unorm => {
+            let bits = u32::from_le_bytes(texel.try_into().unwrap());
+            let channel = |shift: u32, max: u32| unorm(((bits >> shift) & max) as f32 / max as f32);
+            [
+                channel(0, 0x3ff),
+                channel(10, 0x3ff),
+                channel(20, 0x3ff),
+                channel(30, 0x3),
+            ]
+        }
+        TextureFormat::Rgba16Float => {
+            let channel = |i: usize| {
+                unorm(f16_to_f32(u16::from_le_bytes([
+                    texel[2 * i],
+                    texel[2 * i + 1],
+                ])))
+            };
+            [channel(0), channel(1), channel(2), channel(3)]
+        }
+        _ => texel[..4].try_into().unwrap(),
+    }
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_surface_formats_to_rgba8() {
+        let rgba = [10, 20, 30, 255];
+        assert_eq!(to_rgba8(TextureFormat::Rgba8UnormSrgb, &rgba), rgba);
+        assert_eq!(
+            to_rgba8(TextureFormat::Bgra8Unorm, &[30, 20, 10, 255]),
+            rgba
+        );
+
+        let rgb10a2 = 0x3ffu32 | (0x200 << 10) | (3 << 30);
+        assert_eq!(
+            to_rgba8(TextureFormat::Rgb10a2Unorm, &rgb10a2.to_le_bytes()),
+            [255, 128, 0, 255]
+        );
+
+        // 1.0, 0.5, -2.0 and 0.25 as half floats.
+        let half: Vec<u8> = [0x3c00u16, 0x3800, 0xc000, 0x3400]
+            .iter()
+            .flat_map(|channel| channel.to_le_bytes())
+            .collect();
+        assert_eq!(
+            to_rgba8(TextureFormat::Rgba16Float, &half),
+            [255, 128, 0, 64]
+        );
+    }
+}
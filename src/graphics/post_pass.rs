@@ -0,0 +1,343 @@
+use std::{collections::BTreeSet, io};
+
+use wgpu::*;
+
+use crate::graphics::{
+    shaders::{self, ShaderError},
+    structures::PostEffects,
+};
+
+pub const SHADER: &str = "post_pass.wgsl";
+
+/// Format the scene is rendered in before tonemapping.
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Render graph textures of the bloom mip chain, starting at half the frame size.
+pub const BLOOM_TEXTURES: [&str; 6] = [
+    "bloom_1", "bloom_2", "bloom_3", "bloom_4", "bloom_5", "bloom_6",
+];
+
+/// Soft knee below the bloom threshold, relative to it.
+pub const BLOOM_KNEE: f32 = 0.5;
+
+/// Number of bloom levels for a frame size: as many as fit, stopping before a level would
+/// be smaller than 2 texels.
+pub fn bloom_levels(frame_size: (u32, u32)) -> usize {
+    let smaller_side = frame_size.0.min(frame_size.1).max(1);
+    (smaller_side.ilog2() as usize)
+        .saturating_sub(1)
+        .min(BLOOM_TEXTURES.len())
+}
+
+/// Turns the HDR scene into the final frame: a bloom mip chain is built by downsampling the
+/// bright parts of the scene and upsampling them back with additive blending, then the scene
+/// plus bloom is darkened by the ambient occlusion, exposed and tonemapped into the output
+/// format.
+pub struct PostPass {
+    prefilter: RenderPipeline,
+    downsample: RenderPipeline,
+    upsample: RenderPipeline,
+    tonemap: RenderPipeline,
+    effects_buffer: Buffer,
+    effects_bind_group: BindGroup,
+    source_layout: BindGroupLayout,
+    sampler: Sampler,
+    /// Shader files the pipelines were built from, including includes.
+    pub files: BTreeSet<String>,
+}
+
+impl PostPass {
+    pub fn new(device: &Device, output_format: TextureFormat) -> Self {
+        Self::load(device, output_format, &shaders::embedded)
+            .unwrap_or_else(|err| panic!("{SHADER}: {err}"))
+    }
+
+    pub fn load(
+        device: &Device,
+        output_format: TextureFormat,
+        load: &dyn Fn(&str) -> io::Result<String>,
+    ) -> Result<Self, ShaderError> {
+        let shader = shaders::compile(SHADER, load, &[])?;
+        device.push_error_scope(ErrorFilter::Validation);
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Post Pass Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader.source.as_str().into()),
+        });
+
+        let effects_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout Post Effects"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let source_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout Post Source"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let effects_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Post Effects Buffer"),
+            size: std::mem::size_of::<PostEffects>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let effects_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Bind Group Post Effects"),
+            layout: &effects_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: effects_buffer.as_entire_binding(),
+            }],
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Post Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bloom_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
+            bind_group_layouts: &[&effects_layout, &source_layout],
+            push_constant_ranges: &[],
+        });
+        let tonemap_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Tonemap Pipeline Layout"),
+            bind_group_layouts: &[
+                &effects_layout,
+                &source_layout,
+                &source_layout,
+                &source_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |label, layout, entry_point, format, blend| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(layout),
+                vertex: VertexState {
+                    module: &module,
+                    entry_point: Some("main_vertex"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &module,
+                    entry_point: Some(entry_point),
+                    targets: &[Some(ColorTargetState {
+                        format,
+                        blend: Some(blend),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                multiview: None,
+                cache: Default::default(),
+            })
+        };
+        let additive = BlendState {
+            color: BlendComponent {
+                src_factor: BlendFactor::One,
+                dst_factor: BlendFactor::One,
+                operation: BlendOperation::Add,
+            },
+            alpha: BlendComponent::REPLACE,
+        };
+        let prefilter = pipeline(
+            "Bloom Prefilter Pipeline",
+            &bloom_layout,
+            "prefilter_fragment",
+            HDR_FORMAT,
+            BlendState::REPLACE,
+        );
+        let downsample = pipeline(
+            "Bloom Downsample Pipeline",
+            &bloom_layout,
+            "downsample_fragment",
+            HDR_FORMAT,
+            BlendState::REPLACE,
+        );
+        let upsample = pipeline(
+            "Bloom Upsample Pipeline",
+            &bloom_layout,
+            "upsample_fragment",
+            HDR_FORMAT,
+            additive,
+        );
+        let tonemap = pipeline(
+            "Tonemap Pipeline",
+            &tonemap_layout,
+            "tonemap_fragment",
+            output_format,
+            BlendState::REPLACE,
+        );
+
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(err) => Err(ShaderError::Pipeline(err)),
+            None => Ok(Self {
+                prefilter,
+                downsample,
+                upsample,
+                tonemap,
+                effects_buffer,
+                effects_bind_group,
+                source_layout,
+                sampler,
+                files: shader.files().into_iter().map(str::to_string).collect(),
+            }),
+        }
+    }
+
+    pub fn write_effects(&self, queue: &Queue, effects: &PostEffects) {
+        queue.write_buffer(&self.effects_buffer, 0, bytemuck::bytes_of(effects));
+    }
+
+    /// Builds the bloom chain from `hdr` into `levels`, largest first. The first level ends
+    /// up holding the bloom of every level.
+    pub fn encode_bloom(
+        &self,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+        hdr: &TextureView,
+        levels: &[&TextureView],
+    ) {
+        let mut source = hdr;
+        for (i, target) in levels.iter().enumerate() {
+            let pipeline = if i == 0 {
+                &self.prefilter
+            } else {
+                &self.downsample
+            };
+            self.draw(
+                encoder,
+                device,
+                pipeline,
+                &[source],
+                target,
+                LoadOp::Clear(Color::BLACK),
+            );
+            source = target;
+        }
+        for pair in levels.windows(2).rev() {
+            self.draw(
+                encoder,
+                device,
+                &self.upsample,
+                &[pair[1]],
+                pair[0],
+                LoadOp::Load,
+            );
+        }
+    }
+
+    /// Exposes and tonemaps `hdr` plus `bloom` into `target`, applying `ao` when
+    /// `PostEffects::ambient_occlusion` is set.
+    pub fn encode_tonemap(
+        &self,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+        hdr: &TextureView,
+        bloom: &TextureView,
+        ao: &TextureView,
+        target: &TextureView,
+    ) {
+        let load = LoadOp::Clear(Color::BLACK);
+        let sources = [hdr, bloom, ao];
+        self.draw(encoder, device, &self.tonemap, &sources, target, load);
+    }
+
+    fn draw(
+        &self,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+        pipeline: &RenderPipeline,
+        sources: &[&TextureView],
+        target: &TextureView,
+        load: LoadOp<Color>,
+    ) {
+        // Graph textures can be reallocated between frames, so their bind groups are made
+        // as they are drawn.
+        let source_bind_groups: Vec<BindGroup> = sources
+            .iter()
+            .map(|source| {
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("Bind Group Post Source"),
+                    layout: &self.source_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(source),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(&self.sampler),
+                        },
+                    ],
+                })
+            })
+            .collect();
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Post Pass Descriptor"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations {
+                    load,
+                    store: StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &self.effects_bind_group, &[]);
+        for (i, bind_group) in source_bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(i as u32 + 1, bind_group, &[]);
+        }
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloom_chain_stops_at_two_texels() {
+        assert_eq!(bloom_levels((256, 160)), 6);
+        assert_eq!(bloom_levels((40, 16)), 3);
+        assert_eq!(bloom_levels((1, 1)), 0);
+    }
+}
@@ -0,0 +1,372 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+};
+
+/// Where a line of preprocessed output came from. Lines are 1-based.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLine {
+    pub file: String,
+    pub line: usize,
+}
+
+impl fmt::Display for SourceLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+#[derive(Debug)]
+pub enum PreprocessError {
+    Io {
+        file: String,
+        at: Option<SourceLine>,
+        err: io::Error,
+    },
+    Syntax {
+        at: SourceLine,
+        message: String,
+    },
+    IncludeCycle {
+        at: SourceLine,
+        file: String,
+    },
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreprocessError::Io {
+                file,
+                at: None,
+                err,
+            } => write!(f, "failed to read {file}: {err}"),
+            PreprocessError::Io {
+                file,
+                at: Some(at),
+                err,
+            } => write!(f, "{at}: failed to include {file}: {err}"),
+            PreprocessError::Syntax { at, message } => write!(f, "{at}: {message}"),
+            PreprocessError::IncludeCycle { at, file } => {
+                write!(f, "{at}: {file} includes itself")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// Preprocessor output, with the origin of every line for mapping diagnostics back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreprocessedShader {
+    pub source: String,
+    pub lines: Vec<SourceLine>,
+}
+
+impl PreprocessedShader {
+    /// Origin of a 1-based line of `source`.
+    pub fn origin(&self, line: usize) -> Option<&SourceLine> {
+        self.lines.get(line.checked_sub(1)?)
+    }
+
+    /// Every file that went into this shader, for deciding what to rebuild when one changes.
+    pub fn files(&self) -> BTreeSet<&str> {
+        self.lines.iter().map(|line| line.file.as_str()).collect()
+    }
+}
+
+/// Resolves `#include "file"` (each file at most once), `#define NAME [value]`, `#undef`,
+/// `#ifdef`/`#ifndef`/`#else`/`#endif`. Defined names with a value are substituted wherever
+/// they appear as a whole identifier, which is how pipelines inject constants.
+pub struct Preprocessor<'a> {
+    load: &'a dyn Fn(&str) -> io::Result<String>,
+    defines: BTreeMap<String, String>,
+}
+
+struct Condition {
+    /// Whether the enclosing block is emitted.
+    parent_active: bool,
+    taken: bool,
+    seen_else: bool,
+    at: SourceLine,
+}
+
+struct State {
+    defines: BTreeMap<String, String>,
+    included: BTreeSet<String>,
+    stack: Vec<String>,
+    output: PreprocessedShader,
+}
+
+impl<'a> Preprocessor<'a> {
+    pub fn new(load: &'a dyn Fn(&str) -> io::Result<String>) -> Self {
+        Self {
+            load,
+            defines: BTreeMap::new(),
+        }
+    }
+
+    /// Defines `name` for every file, as if by `#define name value`. An empty value only
+    /// enables `#ifdef name`.
+    pub fn define(mut self, name: &str, value: impl ToString) -> Self {
+        self.defines.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn run(&self, file: &str) -> Result<PreprocessedShader, PreprocessError> {
+        let mut state = State {
+            defines: self.defines.clone(),
+            included: BTreeSet::new(),
+            stack: Vec::new(),
+            output: PreprocessedShader {
+                source: String::new(),
+                lines: Vec::new(),
+            },
+        };
+        self.process(file, None, &mut state)?;
+        Ok(state.output)
+    }
+
+    fn process(
+        &self,
+        file: &str,
+        included_at: Option<SourceLine>,
+        state: &mut State,
+    ) -> Result<(), PreprocessError> {
+        if state.stack.iter().any(|open| open == file) {
+            return Err(PreprocessError::IncludeCycle {
+                at: included_at.unwrap(),
+                file: file.to_string(),
+            });
+        }
+        if !state.included.insert(file.to_string()) {
+            return Ok(());
+        }
+        let source = (self.load)(file).map_err(|err| PreprocessError::Io {
+            file: file.to_string(),
+            at: included_at,
+            err,
+        })?;
+        state.stack.push(file.to_string());
+
+        let mut conditions: Vec<Condition> = Vec::new();
+        for (index, text) in source.lines().enumerate() {
+            let at = SourceLine {
+                file: file.to_string(),
+                line: index + 1,
+            };
+            let active = conditions
+                .last()
+                .is_none_or(|condition| condition.parent_active && condition.taken);
+            let syntax = |message: &str| PreprocessError::Syntax {
+                at: at.clone(),
+                message: message.to_string(),
+            };
+
+            let Some(directive) = text.trim_start().strip_prefix('#') else {
+                if active {
+                    state
+                        .output
+                        .source
+                        .push_str(&substitute(text, &state.defines));
+                    state.output.source.push('\n');
+                    state.output.lines.push(at);
+                }
+                continue;
+            };
+            let (keyword, argument) = directive
+                .split_once(char::is_whitespace)
+                .map_or((directive, ""), |(keyword, rest)| (keyword, rest.trim()));
+
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let name = identifier(argument).ok_or_else(|| syntax("expected a name"))?;
+                    let defined = state.defines.contains_key(name);
+                    conditions.push(Condition {
+                        parent_active: active,
+                        taken: defined == (keyword == "ifdef"),
+                        seen_else: false,
+                        at,
+                    });
+                }
+                "else" => {
+                    let condition = conditions
+                        .last_mut()
+                        .ok_or_else(|| syntax("#else without #ifdef"))?;
+                    if condition.seen_else {
+                        return Err(syntax("second #else for the same #ifdef"));
+                    }
+                    condition.seen_else = true;
+                    condition.taken = !condition.taken;
+                }
+                "endif" => {
+                    conditions
+                        .pop()
+                        .ok_or_else(|| syntax("#endif without #ifdef"))?;
+                }
+                _ if !active => {}
+                "define" => {
+                    let (name, value) = argument
+                        .split_once(char::is_whitespace)
+                        .map_or((argument, ""), |(name, value)| (name, value.trim()));
+                    let name = identifier(name).ok_or_else(|| syntax("expected a name"))?;
+                    state.defines.insert(name.to_string(), value.to_string());
+                }
+                "undef" => {
+                    let name = identifier(argument).ok_or_else(|| syntax("expected a name"))?;
+                    state.defines.remove(name);
+                }
+                "include" => {
+                    let name = argument
+                        .strip_prefix('"')
+                        .and_then(|rest| rest.strip_suffix('"'))
+                        .filter(|name| !name.is_empty())
+                        .ok_or_else(|| syntax("expected #include \"file\""))?;
+                    self.process(name, Some(at), state)?;
+                }
+                _ => return Err(syntax(&format!("unknown directive #{keyword}"))),
+            }
+        }
+
+        if let Some(condition) = conditions.pop() {
+            return Err(PreprocessError::Syntax {
+                at: condition.at,
+                message: "#ifdef without #endif".to_string(),
+            });
+        }
+        state.stack.pop();
+        Ok(())
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn identifier(text: &str) -> Option<&str> {
+    let valid = !text.is_empty()
+        && !text.starts_with(|c: char| c.is_ascii_digit())
+        && text.chars().all(is_identifier_char);
+    valid.then_some(text)
+}
+
+/// Replaces whole identifiers that have a non-empty definition.
+fn substitute(line: &str, defines: &BTreeMap<String, String>) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find(is_identifier_char) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c| !is_identifier_char(c)).unwrap_or(rest.len());
+        let word = &rest[..end];
+        match defines.get(word) {
+            Some(value) if !value.is_empty() => output.push_str(value),
+            _ => output.push_str(word),
+        }
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(files: &[(&'static str, &'static str)]) -> impl Fn(&str) -> io::Result<String> {
+        move |name| {
+            files
+                .iter()
+                .find(|(file, _)| *file == name)
+                .map(|(_, source)| source.to_string())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found"))
+        }
+    }
+
+    #[test]
+    fn includes_each_file_once_and_tracks_origins() {
+        let load = files(&[
+            (
+                "main.wgsl",
+                "#include \"a.wgsl\"\n#include \"common.wgsl\"\nfn main() {}",
+            ),
+            ("a.wgsl", "#include \"common.wgsl\"\nfn a() {}"),
+            ("common.wgsl", "struct Common { x: f32 }"),
+        ]);
+        let shader = Preprocessor::new(&load).run("main.wgsl").unwrap();
+        assert_eq!(
+            shader.source,
+            "struct Common { x: f32 }\nfn a() {}\nfn main() {}\n"
+        );
+        assert_eq!(
+            shader.origin(2),
+            Some(&SourceLine {
+                file: "a.wgsl".into(),
+                line: 2
+            })
+        );
+        assert_eq!(shader.origin(3).unwrap().to_string(), "main.wgsl:3");
+        assert_eq!(
+            shader.files(),
+            BTreeSet::from(["a.wgsl", "common.wgsl", "main.wgsl"])
+        );
+    }
+
+    #[test]
+    fn evaluates_conditionals() {
+        let load = files(&[(
+            "main.wgsl",
+            "#define LOCAL\n\
+             #ifdef LOCAL\nlocal\n#else\nno local\n#endif\n\
+             #ifndef SHADOWS\nno shadows\n#else\n#ifdef LOCAL\nshadows\n#endif\n#endif",
+        )]);
+        let plain = Preprocessor::new(&load).run("main.wgsl").unwrap();
+        assert_eq!(plain.source, "local\nno shadows\n");
+
+        let shadows = Preprocessor::new(&load)
+            .define("SHADOWS", "")
+            .run("main.wgsl")
+            .unwrap();
+        assert_eq!(shadows.source, "local\nshadows\n");
+        assert_eq!(shadows.origin(2).unwrap().line, 11);
+    }
+
+    #[test]
+    fn substitutes_whole_identifiers() {
+        let load = files(&[(
+            "main.wgsl",
+            "#define SCALE 2.0\nconst CASCADES: u32 = CASCADE_COUNT;\nlet x = SCALE * SCALED;",
+        )]);
+        let shader = Preprocessor::new(&load)
+            .define("CASCADE_COUNT", 4)
+            .run("main.wgsl")
+            .unwrap();
+        assert_eq!(
+            shader.source,
+            "const CASCADES: u32 = 4;\nlet x = 2.0 * SCALED;\n"
+        );
+    }
+
+    #[test]
+    fn reports_errors_at_their_origin() {
+        let load = files(&[
+            ("main.wgsl", "fn main() {}\n#include \"lib.wgsl\""),
+            ("lib.wgsl", "\n#ifdef A\n"),
+            ("cycle.wgsl", "#include \"cycle2.wgsl\""),
+            ("cycle2.wgsl", "#include \"cycle.wgsl\""),
+            ("bad.wgsl", "#include <x>\n"),
+            ("missing.wgsl", "\n\n#include \"nope.wgsl\""),
+        ]);
+        let error = |file| Preprocessor::new(&load).run(file).unwrap_err().to_string();
+        assert_eq!(error("main.wgsl"), "lib.wgsl:2: #ifdef without #endif");
+        assert_eq!(
+            error("cycle.wgsl"),
+            "cycle2.wgsl:1: cycle.wgsl includes itself"
+        );
+        assert_eq!(error("bad.wgsl"), "bad.wgsl:1: expected #include \"file\"");
+        assert_eq!(
+            error("missing.wgsl"),
+            "missing.wgsl:3: failed to include nope.wgsl: not found"
+        );
+    }
+}
@@ -0,0 +1,557 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use wgpu::*;
+
+/// Name of the texture every frame ends up in: the surface texture, or the offscreen target.
+pub const OUTPUT: &str = "output";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureSize {
+    /// The frame size, following the surface through resizes.
+    Frame,
+    /// The frame size halved `levels` times, but at least one texel, e.g. for mip chains.
+    Downscaled { levels: u32 },
+    Fixed {
+        width: u32,
+        height: u32,
+        layers: u32,
+    },
+}
+
+/// A transient texture, allocated by the graph for the frame. Textures with equal
+/// descriptions whose lifetimes don't overlap share memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureDesc {
+    pub size: TextureSize,
+    pub format: TextureFormat,
+    pub usage: TextureUsages,
+}
+
+impl TextureDesc {
+    pub fn frame(format: TextureFormat, usage: TextureUsages) -> Self {
+        Self {
+            size: TextureSize::Frame,
+            format,
+            usage,
+        }
+    }
+
+    fn extent(&self, frame_size: (u32, u32)) -> Extent3d {
+        let (width, height, depth_or_array_layers) = match self.size {
+            TextureSize::Frame => (frame_size.0, frame_size.1, 1),
+            TextureSize::Downscaled { levels } => (
+                (frame_size.0 >> levels).max(1),
+                (frame_size.1 >> levels).max(1),
+                1,
+            ),
+            TextureSize::Fixed {
+                width,
+                height,
+                layers,
+            } => (width, height, layers),
+        };
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RenderGraphError {
+    UnknownResource {
+        pass: &'static str,
+        resource: &'static str,
+    },
+    /// A resource is read but no pass writes it and it wasn't imported.
+    NeverWritten {
+        pass: &'static str,
+        resource: &'static str,
+    },
+    Cycle(Vec<&'static str>),
+}
+
+impl fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderGraphError::UnknownResource { pass, resource } => {
+                write!(f, "pass {pass} uses undeclared resource {resource}")
+            }
+            RenderGraphError::NeverWritten { pass, resource } => {
+                write!(f, "pass {pass} reads {resource}, which no pass writes")
+            }
+            RenderGraphError::Cycle(passes) => {
+                write!(f, "passes {passes:?} depend on each other")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenderGraphError {}
+
+enum Resource<'a> {
+    Transient(TextureDesc),
+    Texture(&'a TextureView),
+    Buffer(&'a Buffer),
+}
+
+type RunPass<'a> = Box<dyn FnOnce(&PassResources, &mut CommandEncoder) + 'a>;
+
+struct PassNode<'a> {
+    name: &'static str,
+    reads: Vec<&'static str>,
+    writes: Vec<&'static str>,
+    run: RunPass<'a>,
+}
+
+/// Views of the resources a pass declared.
+pub struct PassResources<'r> {
+    textures: HashMap<&'static str, &'r TextureView>,
+    buffers: HashMap<&'static str, &'r Buffer>,
+}
+
+impl PassResources<'_> {
+    pub fn texture(&self, name: &str) -> &TextureView {
+        self.textures
+            .get(name)
+            .unwrap_or_else(|| panic!("no texture named {name} in the render graph"))
+    }
+
+    pub fn buffer(&self, name: &str) -> &Buffer {
+        self.buffers
+            .get(name)
+            .unwrap_or_else(|| panic!("no buffer named {name} in the render graph"))
+    }
+}
+
+/// Execution order of the passes, and the physical texture slot of each transient texture.
+#[derive(Debug, PartialEq)]
+pub struct Plan {
+    pub order: Vec<usize>,
+    pub slots: BTreeMap<&'static str, usize>,
+    pub slot_descs: Vec<TextureDesc>,
+}
+
+/// The passes of one frame. Built every frame, since passes borrow whatever they draw with;
+/// the textures live on in a [`TexturePool`].
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    resources: BTreeMap<&'static str, Resource<'a>>,
+    passes: Vec<PassNode<'a>>,
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn import_texture(&mut self, name: &'static str, view: &'a TextureView) {
+        self.resources.insert(name, Resource::Texture(view));
+    }
+
+    pub fn import_buffer(&mut self, name: &'static str, buffer: &'a Buffer) {
+        self.resources.insert(name, Resource::Buffer(buffer));
+    }
+
+    pub fn create_texture(&mut self, name: &'static str, desc: TextureDesc) {
+        self.resources.insert(name, Resource::Transient(desc));
+    }
+
+    /// Adds a pass. Passes run after every pass that writes what they read; passes writing
+    /// the same resource run in the order they were added.
+    pub fn add_pass(
+        &mut self,
+        name: &'static str,
+        reads: &[&'static str],
+        writes: &[&'static str],
+        run: impl FnOnce(&PassResources, &mut CommandEncoder) + 'a,
+    ) {
+        self.passes.push(PassNode {
+            name,
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            run: Box::new(run),
+        });
+    }
+
+    pub fn plan(&self) -> Result<Plan, RenderGraphError> {
+        let passes: Vec<_> = self
+            .passes
+            .iter()
+            .map(|pass| (pass.name, pass.reads.as_slice(), pass.writes.as_slice()))
+            .collect();
+        let transients: BTreeMap<_, _> = self
+            .resources
+            .iter()
+            .filter_map(|(name, resource)| match resource {
+                Resource::Transient(desc) => Some((*name, *desc)),
+                _ => None,
+            })
+            .collect();
+        let imported = self
+            .resources
+            .keys()
+            .filter(|name| !transients.contains_key(*name))
+            .copied()
+            .collect::<Vec<_>>();
+        plan(&passes, &transients, &imported)
+    }
+
+    /// Orders the passes, allocates transient textures from `pool`, records every pass into
+    /// a single command encoder and submits it.
+    pub fn execute(
+        self,
+        device: &Device,
+        queue: &Queue,
+        pool: &mut TexturePool,
+        frame_size: (u32, u32),
+    ) -> Result<SubmissionIndex, RenderGraphError> {
+        let plan = self.plan()?;
+        let slot_views = pool.acquire(device, &plan.slot_descs, frame_size);
+
+        let mut textures = HashMap::new();
+        let mut buffers = HashMap::new();
+        for (name, resource) in &self.resources {
+            match resource {
+                Resource::Transient(_) => {
+                    // Transients no pass uses get no slot.
+                    if let Some(&slot) = plan.slots.get(name) {
+                        textures.insert(*name, &slot_views[slot]);
+                    }
+                }
+                Resource::Texture(view) => {
+                    textures.insert(*name, *view);
+                }
+                Resource::Buffer(buffer) => {
+                    buffers.insert(*name, *buffer);
+                }
+            }
+        }
+
+        let mut passes: Vec<Option<PassNode>> = self.passes.into_iter().map(Some).collect();
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Render Graph Encoder"),
+        });
+        for index in plan.order {
+            let pass = passes[index].take().unwrap();
+            let used = |name: &&str| pass.reads.contains(name) || pass.writes.contains(name);
+            let resources = PassResources {
+                textures: textures
+                    .iter()
+                    .filter(|(name, _)| used(name))
+                    .map(|(name, view)| (*name, *view))
+                    .collect(),
+                buffers: buffers
+                    .iter()
+                    .filter(|(name, _)| used(name))
+                    .map(|(name, buffer)| (*name, *buffer))
+                    .collect(),
+            };
+            encoder.push_debug_group(pass.name);
+            (pass.run)(&resources, &mut encoder);
+            encoder.pop_debug_group();
+        }
+        Ok(queue.submit(Some(encoder.finish())))
+    }
+}
+
+/// Dependency ordering and slot assignment, independent of any GPU objects.
+fn plan(
+    passes: &[(&'static str, &[&'static str], &[&'static str])],
+    transients: &BTreeMap<&'static str, TextureDesc>,
+    imported: &[&'static str],
+) -> Result<Plan, RenderGraphError> {
+    for (pass, reads, writes) in passes {
+        for resource in reads.iter().chain(writes.iter()) {
+            if !transients.contains_key(resource) && !imported.contains(resource) {
+                return Err(RenderGraphError::UnknownResource { pass, resource });
+            }
+        }
+        for resource in reads.iter() {
+            let written = passes
+                .iter()
+                .any(|(other, _, writes)| other != pass && writes.contains(resource));
+            if !written && transients.contains_key(resource) && !writes.contains(resource) {
+                return Err(RenderGraphError::NeverWritten { pass, resource });
+            }
+        }
+    }
+
+    // `dependencies[i]` are the passes that have to run before pass `i`. Among passes touching
+    // the same resource, the order they were added in is kept whenever one of them writes it.
+    // A read with no earlier writer waits for the writers added after it instead.
+    let reads_ahead = |index: usize, resource: &str| {
+        let (_, reads, writes) = passes[index];
+        reads.contains(&resource)
+            && !writes.contains(&resource)
+            && !passes[..index]
+                .iter()
+                .any(|(_, _, writes)| writes.contains(&resource))
+    };
+    let dependencies: Vec<Vec<usize>> = passes
+        .iter()
+        .enumerate()
+        .map(|(index, (_, reads, writes))| {
+            let uses = |resource: &&str| reads.contains(resource) || writes.contains(resource);
+            (0..passes.len())
+                .filter(|&other| {
+                    let (_, other_reads, other_writes) = passes[other];
+                    if other < index {
+                        other_writes.iter().any(uses)
+                            || other_reads
+                                .iter()
+                                .any(|read| writes.contains(read) && !reads_ahead(other, read))
+                    } else {
+                        other > index
+                            && reads
+                                .iter()
+                                .any(|read| other_writes.contains(read) && reads_ahead(index, read))
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut order = Vec::with_capacity(passes.len());
+    let mut done = vec![false; passes.len()];
+    while order.len() < passes.len() {
+        let ready = (0..passes.len())
+            .find(|&index| !done[index] && dependencies[index].iter().all(|&dep| done[dep]));
+        let Some(index) = ready else {
+            let stuck = (0..passes.len())
+                .filter(|&index| !done[index])
+                .map(|index| passes[index].0)
+                .collect();
+            return Err(RenderGraphError::Cycle(stuck));
+        };
+        done[index] = true;
+        order.push(index);
+    }
+
+    // Greedy interval allocation: a slot is free again after the last pass using it.
+    let mut lifetimes: BTreeMap<&'static str, (usize, usize)> = BTreeMap::new();
+    for (position, &index) in order.iter().enumerate() {
+        let (_, reads, writes) = passes[index];
+        for resource in reads.iter().chain(writes.iter()) {
+            if transients.contains_key(resource) {
+                let lifetime = lifetimes.entry(resource).or_insert((position, position));
+                lifetime.1 = position;
+            }
+        }
+    }
+    let mut by_first_use: Vec<_> = lifetimes.into_iter().collect();
+    by_first_use.sort_by_key(|(name, (first, _))| (*first, *name));
+
+    let mut slots = BTreeMap::new();
+    let mut slot_descs: Vec<TextureDesc> = Vec::new();
+    let mut slot_free_after: Vec<usize> = Vec::new();
+    for (name, (first, last)) in by_first_use {
+        let desc = transients[name];
+        let reusable = (0..slot_descs.len())
+            .find(|&slot| slot_descs[slot] == desc && slot_free_after[slot] < first);
+        let slot = reusable.unwrap_or_else(|| {
+            slot_descs.push(desc);
+            slot_free_after.push(0);
+            slot_descs.len() - 1
+        });
+        slot_free_after[slot] = last;
+        slots.insert(name, slot);
+    }
+
+    Ok(Plan {
+        order,
+        slots,
+        slot_descs,
+    })
+}
+
+struct PooledTexture {
+    desc: TextureDesc,
+    _texture: Texture,
+    view: TextureView,
+}
+
+/// Physical textures behind transient graph textures, kept across frames.
+#[derive(Default)]
+pub struct TexturePool {
+    frame_size: (u32, u32),
+    textures: Vec<PooledTexture>,
+}
+
+impl TexturePool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops every texture, e.g. because frame-sized textures no longer fit.
+    pub fn clear(&mut self) {
+        self.textures.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    /// Views for each slot, reusing pooled textures with the same description.
+    fn acquire(
+        &mut self,
+        device: &Device,
+        slot_descs: &[TextureDesc],
+        frame_size: (u32, u32),
+    ) -> Vec<TextureView> {
+        if self.frame_size != frame_size {
+            self.clear();
+            self.frame_size = frame_size;
+        }
+
+        let mut taken = vec![false; self.textures.len()];
+        let mut views = Vec::with_capacity(slot_descs.len());
+        for desc in slot_descs {
+            let pooled = (0..self.textures.len())
+                .find(|&index| !taken[index] && self.textures[index].desc == *desc);
+            let index = pooled.unwrap_or_else(|| {
+                let texture = device.create_texture(&TextureDescriptor {
+                    label: Some("Render Graph Texture"),
+                    size: desc.extent(frame_size),
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: desc.format,
+                    usage: desc.usage,
+                    view_formats: &[],
+                });
+                let view = texture.create_view(&TextureViewDescriptor {
+                    dimension: Some(match desc.size {
+                        TextureSize::Fixed { layers, .. } if layers > 1 => {
+                            TextureViewDimension::D2Array
+                        }
+                        _ => TextureViewDimension::D2,
+                    }),
+                    ..Default::default()
+                });
+                self.textures.push(PooledTexture {
+                    desc: *desc,
+                    _texture: texture,
+                    view,
+                });
+                taken.push(false);
+                self.textures.len() - 1
+            });
+            taken[index] = true;
+            views.push(self.textures[index].view.clone());
+        }
+        views
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLOR: TextureDesc = TextureDesc {
+        size: TextureSize::Frame,
+        format: TextureFormat::Rgba16Float,
+        usage: TextureUsages::RENDER_ATTACHMENT.union(TextureUsages::TEXTURE_BINDING),
+    };
+
+    fn transients(names: &[&'static str]) -> BTreeMap<&'static str, TextureDesc> {
+        names.iter().map(|name| (*name, COLOR)).collect()
+    }
+
+    #[test]
+    fn orders_writers_before_readers() {
+        let passes: [(&str, &[&str], &[&str]); 4] = [
+            ("post", &["hdr", "ao"], &[OUTPUT]),
+            ("world", &["shadow"], &["hdr", "depth"]),
+            ("ssao", &["depth"], &["ao"]),
+            ("shadow", &[], &["shadow"]),
+        ];
+        let plan = plan(
+            &passes,
+            &transients(&["hdr", "ao", "depth", "shadow"]),
+            &[OUTPUT],
+        )
+        .unwrap();
+        let names: Vec<_> = plan.order.iter().map(|&index| passes[index].0).collect();
+        assert_eq!(names, ["shadow", "world", "ssao", "post"]);
+    }
+
+    #[test]
+    fn keeps_insertion_order_for_shared_writes() {
+        let passes: [(&str, &[&str], &[&str]); 3] = [
+            ("sky", &[], &[OUTPUT]),
+            ("world", &[], &[OUTPUT]),
+            ("ui", &[], &[OUTPUT]),
+        ];
+        let plan = plan(&passes, &BTreeMap::new(), &[OUTPUT]).unwrap();
+        assert_eq!(plan.order, [0, 1, 2]);
+    }
+
+    #[test]
+    fn reports_cycles_and_unknown_resources() {
+        let cycle: [(&str, &[&str], &[&str]); 2] = [("a", &["y"], &["x"]), ("b", &["x"], &["y"])];
+        assert_eq!(
+            plan(&cycle, &transients(&["x", "y"]), &[]),
+            Err(RenderGraphError::Cycle(vec!["a", "b"]))
+        );
+
+        let unknown: [(&str, &[&str], &[&str]); 1] = [("a", &["missing"], &[OUTPUT])];
+        assert_eq!(
+            plan(&unknown, &BTreeMap::new(), &[OUTPUT]),
+            Err(RenderGraphError::UnknownResource {
+                pass: "a",
+                resource: "missing"
+            })
+        );
+
+        let unwritten: [(&str, &[&str], &[&str]); 1] = [("a", &["x"], &[OUTPUT])];
+        assert_eq!(
+            plan(&unwritten, &transients(&["x"]), &[OUTPUT]),
+            Err(RenderGraphError::NeverWritten {
+                pass: "a",
+                resource: "x"
+            })
+        );
+    }
+
+    #[test]
+    fn aliases_textures_with_disjoint_lifetimes() {
+        let passes: [(&str, &[&str], &[&str]); 4] = [
+            ("scene", &[], &["a"]),
+            ("blur_x", &["a"], &["b"]),
+            ("blur_y", &["b"], &["c"]),
+            ("composite", &["c"], &[OUTPUT]),
+        ];
+        let mut textures = transients(&["a", "b", "c"]);
+        let depth = TextureDesc::frame(
+            TextureFormat::Depth32Float,
+            TextureUsages::RENDER_ATTACHMENT,
+        );
+        textures.insert("depth", depth);
+        let plan = plan(&passes, &textures, &[OUTPUT]).unwrap();
+
+        // `a` is dead once `blur_x` has run, so `c` takes its place; `depth` is unused.
+        assert_eq!(plan.slots["a"], plan.slots["c"]);
+        assert_ne!(plan.slots["a"], plan.slots["b"]);
+        assert!(!plan.slots.contains_key("depth"));
+        assert_eq!(plan.slot_descs, [COLOR, COLOR]);
+    }
+
+    #[test]
+    fn downscaled_textures_keep_at_least_one_texel() {
+        let desc = TextureDesc {
+            size: TextureSize::Downscaled { levels: 3 },
+            ..COLOR
+        };
+        let extent = desc.extent((100, 6));
+        assert_eq!((extent.width, extent.height), (12, 1));
+    }
+}
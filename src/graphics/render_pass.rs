@@ -1,109 +1,256 @@
+use std::{collections::BTreeSet, io, ops::Range};
+
 use wgpu::*;
 
-use crate::graphics::structures::VertexBuffer;
+use crate::{
+    graphics::{
+        shaders::{self, ShaderError},
+        structures::VertexBuffer,
+    },
+    world::block::RenderLayer,
+};
+
+pub const SHADER: &str = "render_pass.wgsl";
 
+/// Format of the normal G-buffer written alongside the scene color.
+pub const NORMAL_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+/// Chunk mesh vertices as the world shaders read them.
+pub fn vertex_layout() -> VertexBufferLayout<'static> {
+    VertexBufferLayout {
+        array_stride: std::mem::size_of::<VertexBuffer>() as u64,
+        step_mode: VertexStepMode::Vertex,
+        attributes: &[
+            VertexAttribute {
+                format: VertexFormat::Float32x4, //position
+                offset: 0,
+                shader_location: 0,
+            },
+            VertexAttribute {
+                format: VertexFormat::Float32x4, //normal
+                offset: std::mem::offset_of!(VertexBuffer, normal) as u64,
+                shader_location: 1,
+            },
+            VertexAttribute {
+                format: VertexFormat::Float32x2, //uv
+                offset: std::mem::offset_of!(VertexBuffer, uv) as u64,
+                shader_location: 2,
+            },
+        ],
+    }
+}
+
+/// Attachments the world is drawn into.
+pub struct RenderTargets<'a> {
+    pub color: &'a TextureView,
+    pub normals: &'a TextureView,
+    pub depth: &'a TextureView,
+}
+
+/// Draws the chunk meshes, with a pipeline per render layer: opaque faces are culled from
+/// behind, cutout faces are alpha tested and seen from both sides, and translucent faces are
+/// blended without writing depth. The water surface has a pass of its own.
 pub struct RenderPass {
-    pipeline: RenderPipeline,
+    opaque: RenderPipeline,
+    cutout: RenderPipeline,
+    translucent: RenderPipeline,
+    /// Shader files the pipelines were built from, including includes.
+    pub files: BTreeSet<String>,
 }
 
 impl RenderPass {
-    pub fn new(device: &Device, bind_group_layouts: &[&BindGroupLayout]) -> Self {
+    pub fn new(
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+        color_format: TextureFormat,
+    ) -> Self {
+        Self::load(device, bind_group_layouts, color_format, &shaders::embedded)
+            .unwrap_or_else(|err| panic!("{SHADER}: {err}"))
+    }
+
+    /// Builds the pipelines from shader files read through `load`, reporting preprocessor
+    /// errors, invalid WGSL, struct layout mismatches and pipeline errors instead of panicking.
+    pub fn load(
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+        color_format: TextureFormat,
+        load: &dyn Fn(&str) -> io::Result<String>,
+    ) -> Result<Self, ShaderError> {
+        let shader = shaders::compile(SHADER, load, &[])?;
+        device.push_error_scope(ErrorFilter::Validation);
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Render Pass Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader.source.as_str().into()),
+        });
+
         let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("Render Pass Pipeline Layout"),
-            bind_group_layouts: bind_group_layouts,
+            bind_group_layouts,
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("Render Pass Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: VertexState {
-                module: &device.create_shader_module(ShaderModuleDescriptor {
-                    label: Some("Vertex Shader"),
-                    source: wgpu::ShaderSource::Wgsl(
-                        include_str!("../assets/shaders/render_pass.wgsl").into(),
-                    ),
-                }),
-                entry_point: Some("main_vertex"),
-                buffers: &[VertexBufferLayout {
-                    array_stride: std::mem::size_of::<VertexBuffer>() as u64,
-                    step_mode: VertexStepMode::Vertex,
-                    attributes: &[
-                        VertexAttribute {
-                            format: VertexFormat::Float32x4, //position
-                            offset: 0,
-                            shader_location: 0,
-                        },
-                        VertexAttribute {
-                            format: VertexFormat::Float32x4, //normal
-                            offset: std::mem::offset_of!(VertexBuffer, normal) as u64,
-                            shader_location: 1,
-                        },
-                        VertexAttribute {
-                            format: VertexFormat::Float32x2, //uv
-                            offset: std::mem::offset_of!(VertexBuffer, uv) as u64,
-                            shader_location: 2,
-                        },
-                    ],
-                }],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(FragmentState {
-                module: &device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: Some("Fragment Shader"),
-                    source: wgpu::ShaderSource::Wgsl(
-                        include_str!("../assets/shaders/render_pass.wgsl").into(),
-                    ),
-                }),
-                entry_point: Some("main_fragment"),
-                targets: &[Some(ColorTargetState {
-                    format: TextureFormat::Bgra8UnormSrgb,
-                    blend: Some(BlendState::REPLACE),
-                    write_mask: ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
+        let opaque_targets = [
+            Some(ColorTargetState {
+                format: color_format,
+                blend: Some(BlendState::REPLACE),
+                write_mask: ColorWrites::ALL,
             }),
-            primitive: PrimitiveState {
-                cull_mode: None,
-                ..Default::default()
-            },
-            depth_stencil: Some(DepthStencilState {
-                format: TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: CompareFunction::GreaterEqual,
-                stencil: StencilState::default(),
-                bias: DepthBiasState::default(),
+            Some(ColorTargetState {
+                format: NORMAL_FORMAT,
+                blend: Some(BlendState::REPLACE),
+                write_mask: ColorWrites::ALL,
             }),
-            multisample: MultisampleState::default(),
-            multiview: None,
-            cache: Default::default(),
-        });
+        ];
+        let translucent_targets = [Some(ColorTargetState {
+            format: color_format,
+            blend: Some(BlendState::ALPHA_BLENDING),
+            write_mask: ColorWrites::ALL,
+        })];
+        let pipeline = |label, entry_point, targets: &[_], cull_mode, depth_write_enabled| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &module,
+                    entry_point: Some("main_vertex"),
+                    buffers: &[vertex_layout()],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &module,
+                    entry_point: Some(entry_point),
+                    targets,
+                    compilation_options: Default::default(),
+                }),
+                primitive: PrimitiveState {
+                    cull_mode,
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled,
+                    depth_compare: CompareFunction::GreaterEqual,
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: MultisampleState::default(),
+                multiview: None,
+                cache: Default::default(),
+            })
+        };
+        let opaque = pipeline(
+            "Render Pass Opaque Pipeline",
+            "main_fragment",
+            &opaque_targets,
+            Some(Face::Back),
+            true,
+        );
+        let cutout = pipeline(
+            "Render Pass Cutout Pipeline",
+            "cutout_fragment",
+            &opaque_targets,
+            None,
+            true,
+        );
+        // Seen from both sides, so the sides of water show from inside it as well.
+        let translucent = pipeline(
+            "Render Pass Translucent Pipeline",
+            "translucent_fragment",
+            &translucent_targets,
+            None,
+            false,
+        );
 
-        Self { pipeline }
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(err) => Err(ShaderError::Pipeline(err)),
+            None => Ok(Self {
+                opaque,
+                cutout,
+                translucent,
+                files: shader.files().into_iter().map(str::to_string).collect(),
+            }),
+        }
     }
+
+    /// Draws the opaque and cutout layers, clearing the targets first.
     pub fn encode(
         &self,
         encoder: &mut CommandEncoder,
-        view: &TextureView,
-        depth_view: &TextureView,
+        targets: RenderTargets,
         bind_groups: &[&BindGroup],
         vertex_buffer: &Buffer,
+        ranges: &[Range<u32>; RenderLayer::ALL.len()],
     ) {
         let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some("Render Pass Descriptor"),
+            color_attachments: &[
+                Some(RenderPassColorAttachment {
+                    view: targets.color,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: StoreOp::Store,
+                    },
+                    depth_slice: None,
+                }),
+                Some(RenderPassColorAttachment {
+                    view: targets.normals,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                    depth_slice: None,
+                }),
+            ],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: targets.depth,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(0.0),
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        for (i, bind_group) in bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(i as u32, *bind_group, &[]);
+        }
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_pipeline(&self.opaque);
+        render_pass.draw(ranges[RenderLayer::Opaque as usize].clone(), 0..1);
+        render_pass.set_pipeline(&self.cutout);
+        render_pass.draw(ranges[RenderLayer::Cutout as usize].clone(), 0..1);
+    }
+
+    /// Blends the translucent layer over `color`, testing against but not writing `depth`.
+    /// The faces are expected in back-to-front order.
+    pub fn encode_translucent(
+        &self,
+        encoder: &mut CommandEncoder,
+        color: &TextureView,
+        depth: &TextureView,
+        bind_groups: &[&BindGroup],
+        vertex_buffer: &Buffer,
+        range: Range<u32>,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Translucent Pass Descriptor"),
             color_attachments: &[Some(RenderPassColorAttachment {
-                view,
+                view: color,
                 resolve_target: None,
                 ops: Operations {
-                    load: LoadOp::Clear(Color::BLACK),
+                    load: LoadOp::Load,
                     store: StoreOp::Store,
                 },
                 depth_slice: None,
             })],
             depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
-                view: depth_view,
+                view: depth,
                 depth_ops: Some(Operations {
-                    load: LoadOp::Clear(0.0),
+                    load: LoadOp::Load,
                     store: StoreOp::Store,
                 }),
                 stencil_ops: None,
@@ -111,14 +258,11 @@ impl RenderPass {
             timestamp_writes: None,
             occlusion_query_set: None,
         });
-        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_pipeline(&self.translucent);
         for (i, bind_group) in bind_groups.iter().enumerate() {
             render_pass.set_bind_group(i as u32, *bind_group, &[]);
         }
         render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-        render_pass.draw(
-            0..(vertex_buffer.size() / std::mem::size_of::<VertexBuffer>() as u64) as u32,
-            0..1,
-        );
+        render_pass.draw(range, 0..1);
     }
 }
@@ -0,0 +1,311 @@
+use std::{fmt, mem};
+
+use naga::{
+    TypeInner,
+    proc::Layouter,
+    valid::{Capabilities, ValidationFlags, Validator},
+};
+
+use crate::graphics::structures::{
+    AmbientOcclusion, Fog, Globals, Lights, PostEffects, VertexBuffer, View,
+};
+
+/// Byte layout of a `#[repr(C)]` struct shared with the shaders.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructLayout {
+    pub name: &'static str,
+    pub size: usize,
+    pub fields: Vec<FieldLayout>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldLayout {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[doc(hidden)]
+pub fn field_size<T, F>(_field: fn(&T) -> &F) -> usize {
+    mem::size_of::<F>()
+}
+
+/// Describes a struct's layout from its field list. Every field has to be listed, otherwise
+/// the exhaustive destructuring below fails to compile.
+macro_rules! struct_layout {
+    ($ty:ident { $($field:ident),* $(,)? }) => {{
+        let _exhaustive = |value: $ty| {
+            let $ty { $($field: _),* } = value;
+        };
+        StructLayout {
+            name: stringify!($ty),
+            size: ::std::mem::size_of::<$ty>(),
+            fields: vec![$(FieldLayout {
+                name: stringify!($field),
+                offset: ::std::mem::offset_of!($ty, $field),
+                size: field_size(|value: &$ty| &value.$field),
+            }),*],
+        }
+    }};
+}
+
+/// Every struct that is uploaded to a uniform, storage or vertex buffer.
+pub fn shared_struct_layouts() -> Vec<StructLayout> {
+    vec![
+        struct_layout!(Globals {
+            mouse_pos,
+            resolution,
+            time_passed,
+            frame_time,
+            frame,
+            time_of_day,
+        }),
+        struct_layout!(View {
+            proj_view_rev_z,
+            inv_proj_view_rev_z,
+            proj_view,
+            inv_proj_view,
+            camera_position,
+        }),
+        struct_layout!(Lights {
+            cascades,
+            cascade_splits,
+            cascade_texel_sizes,
+            sun_direction,
+            light_direction,
+            light_color,
+            sky_light,
+            zenith_color,
+            horizon_color,
+            cascade_count,
+            depth_bias,
+            normal_offset,
+            pcf_radius,
+        }),
+        struct_layout!(Fog {
+            color,
+            density,
+            height_falloff,
+            base_height,
+            start_distance,
+            end_distance,
+            sky_amount,
+            _pad,
+        }),
+        struct_layout!(PostEffects {
+            exposure,
+            bloom_intensity,
+            bloom_threshold,
+            bloom_knee,
+            tonemapper,
+            encode_srgb,
+            ambient_occlusion,
+            _pad,
+        }),
+        struct_layout!(AmbientOcclusion {
+            radius,
+            intensity,
+            bias,
+            sample_count,
+            blur_radius,
+            blur_sharpness,
+            _pad,
+        }),
+        struct_layout!(VertexBuffer {
+            position,
+            normal,
+            uv,
+            _pad,
+        }),
+    ]
+}
+
+#[derive(Debug)]
+pub enum ShaderLayoutError {
+    Parse {
+        line: Option<usize>,
+        message: String,
+    },
+    Invalid {
+        line: Option<usize>,
+        message: String,
+    },
+    Mismatch(Vec<String>),
+}
+
+impl fmt::Display for ShaderLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderLayoutError::Parse { message, .. } => {
+                write!(f, "failed to parse shader: {message}")
+            }
+            ShaderLayoutError::Invalid { message, .. } => write!(f, "invalid shader: {message}"),
+            ShaderLayoutError::Mismatch(mismatches) => {
+                write!(f, "shader structs disagree with their Rust counterparts:")?;
+                for mismatch in mismatches {
+                    write!(f, "\n  {mismatch}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShaderLayoutError {}
+
+impl ShaderLayoutError {
+    /// 1-based line of the validated source the error points at, if any.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            ShaderLayoutError::Parse { line, .. } | ShaderLayoutError::Invalid { line, .. } => {
+                *line
+            }
+            ShaderLayoutError::Mismatch(_) => None,
+        }
+    }
+}
+
+/// Validates `source` with naga, then checks every struct in it named like one of `layouts`
+/// against it: member names, offsets and sizes, and the struct size. Structs the shader
+/// doesn't declare are skipped.
+pub fn validate_wgsl(source: &str, layouts: &[StructLayout]) -> Result<(), ShaderLayoutError> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|err| ShaderLayoutError::Parse {
+        line: err.location(source).map(|at| at.line_number as usize),
+        message: err.emit_to_string(source),
+    })?;
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|err| ShaderLayoutError::Invalid {
+            line: err.location(source).map(|at| at.line_number as usize),
+            message: err.emit_to_string(source),
+        })?;
+    let mut layouter = Layouter::default();
+    layouter
+        .update(module.to_ctx())
+        .map_err(|err| ShaderLayoutError::Invalid {
+            line: None,
+            message: err.to_string(),
+        })?;
+
+    let mut mismatches = Vec::new();
+    for (_, ty) in module.types.iter() {
+        let TypeInner::Struct { members, span } = &ty.inner else {
+            continue;
+        };
+        let Some(rust) = layouts
+            .iter()
+            .find(|layout| ty.name.as_deref() == Some(layout.name))
+        else {
+            continue;
+        };
+        let name = rust.name;
+
+        // Array strides come from the struct size, so it has to match exactly.
+        if *span as usize != rust.size {
+            mismatches.push(format!("{name}: WGSL size {span}, Rust size {}", rust.size));
+        }
+
+        let wgsl_names: Vec<&str> = members
+            .iter()
+            .map(|member| member.name.as_deref().unwrap_or("?"))
+            .collect();
+        let rust_names: Vec<&str> = rust.fields.iter().map(|field| field.name).collect();
+        if wgsl_names != rust_names {
+            mismatches.push(format!(
+                "{name}: WGSL fields {wgsl_names:?} do not match Rust fields {rust_names:?}"
+            ));
+            continue;
+        }
+
+        for (member, field) in members.iter().zip(&rust.fields) {
+            let offset = member.offset as usize;
+            let size = layouter[member.ty].size as usize;
+            if offset != field.offset {
+                mismatches.push(format!(
+                    "{name}.{}: WGSL offset {offset}, Rust offset {}",
+                    field.name, field.offset
+                ));
+            }
+            if size != field.size {
+                mismatches.push(format!(
+                    "{name}.{}: WGSL size {size}, Rust size {}",
+                    field.name, field.size
+                ));
+            }
+        }
+    }
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(ShaderLayoutError::Mismatch(mismatches))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{
+        post_pass, render_pass, shaders, shadow_pass, sky_pass, ssao_pass, water_pass,
+    };
+
+    #[test]
+    fn shaders_match_structures() {
+        let defines = [("SUN_TILT", "0.5".to_string())];
+        for shader in [
+            render_pass::SHADER,
+            shadow_pass::SHADER,
+            sky_pass::SHADER,
+            post_pass::SHADER,
+            ssao_pass::SHADER,
+            water_pass::SHADER,
+        ] {
+            if let Err(err) = shaders::compile(shader, &shaders::embedded, &defines) {
+                panic!("{shader}: {err}");
+            }
+        }
+    }
+
+    #[test]
+    fn reports_reordered_and_missing_fields() {
+        let source = "
+            struct Globals { mouse_pos: vec2<f32>, time_passed: f32, frame_time: f32, frame: u32 }
+            struct View { proj_view: mat4x4<f32>, camera_position: vec4<f32> }
+        ";
+        let Err(ShaderLayoutError::Mismatch(mismatches)) =
+            validate_wgsl(source, &shared_struct_layouts())
+        else {
+            panic!("mismatches were not detected");
+        };
+        assert_eq!(mismatches.len(), 4, "{mismatches:#?}");
+        assert!(
+            mismatches
+                .iter()
+                .any(|m| m.starts_with("Globals: WGSL size 24"))
+        );
+        assert!(
+            mismatches
+                .iter()
+                .any(|m| m.starts_with("View: WGSL fields"))
+        );
+    }
+
+    #[test]
+    fn reports_wrong_offsets() {
+        let source = "
+            struct VertexBuffer {
+                position: vec3<f32>,
+                normal: vec2<f32>,
+                uv: vec2<f32>,
+                _pad: array<f32, 6>,
+            }
+        ";
+        let Err(ShaderLayoutError::Mismatch(mismatches)) =
+            validate_wgsl(source, &shared_struct_layouts())
+        else {
+            panic!("mismatches were not detected");
+        };
+        assert!(mismatches.contains(&"VertexBuffer.position: WGSL size 12, Rust size 16".into()));
+        assert!(mismatches.contains(&"VertexBuffer.uv: WGSL offset 24, Rust offset 32".into()));
+    }
+}
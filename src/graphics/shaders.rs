@@ -0,0 +1,193 @@
+use std::{
+    collections::BTreeSet,
+    ffi::OsStr,
+    fmt, fs, io,
+    path::Path,
+    sync::mpsc::{Receiver, channel},
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::graphics::{
+    preprocessor::{PreprocessError, PreprocessedShader, Preprocessor, SourceLine},
+    shader_layout::{ShaderLayoutError, shared_struct_layouts, validate_wgsl},
+    structures::MAX_CASCADES,
+};
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Preprocess(PreprocessError),
+    Layout {
+        /// Where in the original files the error points, if it points anywhere.
+        origin: Option<SourceLine>,
+        err: ShaderLayoutError,
+    },
+    /// wgpu rejected the module or pipeline built from it.
+    Pipeline(wgpu::Error),
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Preprocess(err) => write!(f, "{err}"),
+            ShaderError::Layout {
+                origin: Some(origin),
+                err,
+            } => write!(f, "{origin}: {err}"),
+            ShaderError::Layout { origin: None, err } => write!(f, "{err}"),
+            ShaderError::Pipeline(err) => write!(f, "failed to build pipeline: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// Preprocesses `file` with the given defines and validates the result, reporting errors
+/// at their line in the original files. Constants shared with the Rust side, such as
+/// `MAX_CASCADES`, are always defined.
+pub fn compile(
+    file: &str,
+    load: &dyn Fn(&str) -> io::Result<String>,
+    defines: &[(&str, String)],
+) -> Result<PreprocessedShader, ShaderError> {
+    let shared = Preprocessor::new(load).define("MAX_CASCADES", MAX_CASCADES);
+    let preprocessor = defines.iter().fold(shared, |preprocessor, (name, value)| {
+        preprocessor.define(name, value)
+    });
+    let shader = preprocessor.run(file).map_err(ShaderError::Preprocess)?;
+    validate_wgsl(&shader.source, &shared_struct_layouts()).map_err(|err| ShaderError::Layout {
+        origin: err.line().and_then(|line| shader.origin(line)).cloned(),
+        err,
+    })?;
+    Ok(shader)
+}
+
+/// Shader sources as checked out, watched for changes in development builds.
+pub const SHADER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/assets/shaders");
+
+/// Shaders compiled into the binary, by file name.
+const EMBEDDED: &[(&str, &str)] = &[
+    ("common.wgsl", include_str!("../assets/shaders/common.wgsl")),
+    (
+        "lighting.wgsl",
+        include_str!("../assets/shaders/lighting.wgsl"),
+    ),
+    (
+        "post_pass.wgsl",
+        include_str!("../assets/shaders/post_pass.wgsl"),
+    ),
+    (
+        "render_pass.wgsl",
+        include_str!("../assets/shaders/render_pass.wgsl"),
+    ),
+    (
+        "shadow_pass.wgsl",
+        include_str!("../assets/shaders/shadow_pass.wgsl"),
+    ),
+    ("sky.wgsl", include_str!("../assets/shaders/sky.wgsl")),
+    (
+        "sky_pass.wgsl",
+        include_str!("../assets/shaders/sky_pass.wgsl"),
+    ),
+    (
+        "ssao_pass.wgsl",
+        include_str!("../assets/shaders/ssao_pass.wgsl"),
+    ),
+    (
+        "water_pass.wgsl",
+        include_str!("../assets/shaders/water_pass.wgsl"),
+    ),
+];
+
+/// The embedded copy of a shader.
+pub fn embedded(name: &str) -> io::Result<String> {
+    EMBEDDED
+        .iter()
+        .find(|(embedded_name, _)| *embedded_name == name)
+        .map(|(_, source)| source.to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no embedded shader"))
+}
+
+/// The current on-disk copy of a shader, for reloading.
+pub fn read(name: &str) -> io::Result<String> {
+    fs::read_to_string(Path::new(SHADER_DIR).join(name))
+}
+
+/// Watches the shader directory and reports which `.wgsl` files changed.
+pub struct ShaderWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl ShaderWatcher {
+    pub fn new(dir: &Path) -> notify::Result<Self> {
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// File names of the shaders written since the last call, without blocking.
+    pub fn changed(&self) -> BTreeSet<String> {
+        let mut changed = BTreeSet::new();
+        for event in self.events.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(err) => {
+                    log::warn!("shader watcher: {err}");
+                    continue;
+                }
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                continue;
+            }
+            changed.extend(event.paths.iter().filter_map(|path| shader_name(path)));
+        }
+        changed
+    }
+}
+
+fn shader_name(path: &Path) -> Option<String> {
+    if path.extension() != Some(OsStr::new("wgsl")) {
+        return None;
+    }
+    Some(path.file_name()?.to_str()?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn embeds_every_shader_in_the_directory() {
+        for entry in fs::read_dir(SHADER_DIR).unwrap() {
+            let path = entry.unwrap().path();
+            if let Some(name) = shader_name(&path) {
+                assert_eq!(embedded(&name).unwrap(), read(&name).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn reports_written_shaders() {
+        let dir = std::env::temp_dir().join(format!("voxel-shaders-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let watcher = ShaderWatcher::new(&dir).unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        fs::write(dir.join("test.wgsl"), "fn main() {}").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut changed = BTreeSet::new();
+        while changed.is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+            changed = watcher.changed();
+        }
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(changed, BTreeSet::from(["test.wgsl".to_string()]));
+    }
+}
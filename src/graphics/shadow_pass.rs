@@ -0,0 +1,281 @@
+use std::{collections::BTreeSet, io, ops::Range};
+
+use wgpu::*;
+
+use crate::{
+    graphics::{
+        shaders::{self, ShaderError},
+        structures::{Lights, MAX_CASCADES, VertexBuffer},
+    },
+    world::block::RenderLayer,
+};
+
+pub const SHADER: &str = "shadow_pass.wgsl";
+
+pub const SHADOW_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// Distance between cascade matrices in the cascade buffer; the largest uniform offset
+/// alignment WebGPU allows, so it's valid on every adapter.
+const CASCADE_STRIDE: u64 = 256;
+
+/// Depth array with one layer per cascade, sampled with a comparison sampler for PCF.
+pub struct ShadowMap {
+    pub texture: Texture,
+    pub view: TextureView,
+    pub layer_views: Vec<TextureView>,
+    pub sampler: Sampler,
+    pub size: u32,
+}
+
+impl ShadowMap {
+    pub fn new(device: &Device, size: u32, cascades: u32) -> Self {
+        // GL can't view a single-layer texture as an array.
+        let layers = cascades.max(2);
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Shadow Map"),
+            size: Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor {
+            label: Some("Shadow Map View"),
+            dimension: Some(TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let layer_views = (0..layers)
+            .map(|layer| {
+                texture.create_view(&TextureViewDescriptor {
+                    label: Some("Shadow Cascade View"),
+                    dimension: Some(TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            compare: Some(CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            layer_views,
+            sampler,
+            size,
+        }
+    }
+}
+
+pub struct ShadowPass {
+    pipeline: RenderPipeline,
+    /// Alpha-tested pipeline for the cutout layer, so leaves cast leaf-shaped shadows.
+    cutout_pipeline: RenderPipeline,
+    cascade_buffer: Buffer,
+    cascade_bind_group: BindGroup,
+    /// Shader files the pipeline was built from, including includes.
+    pub files: BTreeSet<String>,
+}
+
+impl ShadowPass {
+    pub fn new(device: &Device, atlas_layout: &BindGroupLayout) -> Self {
+        Self::load(device, atlas_layout, &shaders::embedded)
+            .unwrap_or_else(|err| panic!("{SHADER}: {err}"))
+    }
+
+    /// Builds the depth-only and alpha-tested pipelines from shader files read through
+    /// `load`; the latter samples the block atlas bound with `atlas_layout`.
+    pub fn load(
+        device: &Device,
+        atlas_layout: &BindGroupLayout,
+        load: &dyn Fn(&str) -> io::Result<String>,
+    ) -> Result<Self, ShaderError> {
+        let shader = shaders::compile(SHADER, load, &[])?;
+        device.push_error_scope(ErrorFilter::Validation);
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Shadow Pass Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader.source.as_str().into()),
+        });
+
+        let cascade_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout Shadow Cascade"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: BufferSize::new(64),
+                },
+                count: None,
+            }],
+        });
+        let cascade_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Shadow Cascade Buffer"),
+            size: CASCADE_STRIDE * MAX_CASCADES as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let cascade_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Bind Group Shadow Cascade"),
+            layout: &cascade_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Buffer(BufferBinding {
+                    buffer: &cascade_buffer,
+                    offset: 0,
+                    size: BufferSize::new(64),
+                }),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Shadow Pass Pipeline Layout"),
+            bind_group_layouts: &[&cascade_layout, atlas_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_stride = std::mem::size_of::<VertexBuffer>() as u64;
+        let position = VertexAttribute {
+            format: VertexFormat::Float32x4, //position
+            offset: 0,
+            shader_location: 0,
+        };
+        let uv = VertexAttribute {
+            format: VertexFormat::Float32x2, //uv
+            offset: std::mem::offset_of!(VertexBuffer, uv) as u64,
+            shader_location: 2,
+        };
+        let create_pipeline = |label: &str,
+                               vertex_entry: &str,
+                               fragment_entry: Option<&str>,
+                               attributes: &[VertexAttribute]| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &module,
+                    entry_point: Some(vertex_entry),
+                    buffers: &[VertexBufferLayout {
+                        array_stride: vertex_stride,
+                        step_mode: VertexStepMode::Vertex,
+                        attributes,
+                    }],
+                    compilation_options: Default::default(),
+                },
+                fragment: fragment_entry.map(|entry_point| FragmentState {
+                    module: &module,
+                    entry_point: Some(entry_point),
+                    targets: &[],
+                    compilation_options: Default::default(),
+                }),
+                // Both sides cast, so thin geometry seen edge-on from the sun still does.
+                primitive: PrimitiveState::default(),
+                depth_stencil: Some(DepthStencilState {
+                    format: SHADOW_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: CompareFunction::LessEqual,
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState {
+                        constant: 2,
+                        slope_scale: 2.0,
+                        clamp: 0.0,
+                    },
+                }),
+                multisample: MultisampleState::default(),
+                multiview: None,
+                cache: Default::default(),
+            })
+        };
+        let pipeline = create_pipeline("Shadow Pass Pipeline", "main_vertex", None, &[position]);
+        let cutout_pipeline = create_pipeline(
+            "Shadow Pass Cutout Pipeline",
+            "cutout_vertex",
+            Some("cutout_fragment"),
+            &[position, uv],
+        );
+
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(err) => Err(ShaderError::Pipeline(err)),
+            None => Ok(Self {
+                pipeline,
+                cutout_pipeline,
+                cascade_buffer,
+                cascade_bind_group,
+                files: shader.files().into_iter().map(str::to_string).collect(),
+            }),
+        }
+    }
+
+    /// Uploads this frame's cascade matrices.
+    pub fn write_cascades(&self, queue: &Queue, lights: &Lights) {
+        for (i, cascade) in lights.cascades.iter().enumerate() {
+            queue.write_buffer(
+                &self.cascade_buffer,
+                i as u64 * CASCADE_STRIDE,
+                bytemuck::bytes_of(cascade),
+            );
+        }
+    }
+
+    /// Renders the chunk meshes into each of the first `cascade_count` shadow map layers,
+    /// alpha testing the cutout layer against the `atlas`. Translucent blocks don't cast
+    /// shadows.
+    pub fn encode(
+        &self,
+        encoder: &mut CommandEncoder,
+        shadow_map: &ShadowMap,
+        cascade_count: u32,
+        vertex_buffer: &Buffer,
+        vertex_ranges: &[Range<u32>; RenderLayer::ALL.len()],
+        atlas: &BindGroup,
+    ) {
+        for (i, layer) in shadow_map
+            .layer_views
+            .iter()
+            .take(cascade_count as usize)
+            .enumerate()
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Shadow Pass Descriptor"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: layer,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(1.0),
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_bind_group(
+                0,
+                &self.cascade_bind_group,
+                &[(i as u64 * CASCADE_STRIDE) as u32],
+            );
+            render_pass.set_bind_group(1, atlas, &[]);
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.draw(vertex_ranges[RenderLayer::Opaque as usize].clone(), 0..1);
+            render_pass.set_pipeline(&self.cutout_pipeline);
+            render_pass.draw(vertex_ranges[RenderLayer::Cutout as usize].clone(), 0..1);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::{collections::BTreeSet, io};
+
+use wgpu::*;
+
+use crate::graphics::{
+    lighting::SUN_TILT,
+    shaders::{self, ShaderError},
+};
+
+pub const SHADER: &str = "sky_pass.wgsl";
+
+/// Procedural sky drawn behind the terrain: a zenith-to-horizon gradient with scattering
+/// around the sun, sun and moon discs, and stars at night.
+pub struct SkyPass {
+    pipeline: RenderPipeline,
+    /// Shader files the pipeline was built from, including includes.
+    pub files: BTreeSet<String>,
+}
+
+impl SkyPass {
+    /// `bind_group_layouts` are the globals and view layouts of the render pass.
+    pub fn new(
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+        color_format: TextureFormat,
+    ) -> Self {
+        Self::load(device, bind_group_layouts, color_format, &shaders::embedded)
+            .unwrap_or_else(|err| panic!("{SHADER}: {err}"))
+    }
+
+    pub fn load(
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+        color_format: TextureFormat,
+        load: &dyn Fn(&str) -> io::Result<String>,
+    ) -> Result<Self, ShaderError> {
+        let shader = shaders::compile(SHADER, load, &[("SUN_TILT", SUN_TILT.to_string())])?;
+        device.push_error_scope(ErrorFilter::Validation);
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Sky Pass Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader.source.as_str().into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Sky Pass Pipeline Layout"),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Sky Pass Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &module,
+                entry_point: Some("main_vertex"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: Some("main_fragment"),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            // Drawn at the far plane, so it only covers pixels the terrain left at the
+            // cleared depth.
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: Default::default(),
+        });
+
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(err) => Err(ShaderError::Pipeline(err)),
+            None => Ok(Self {
+                pipeline,
+                files: shader.files().into_iter().map(str::to_string).collect(),
+            }),
+        }
+    }
+
+    pub fn encode(
+        &self,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        depth_view: &TextureView,
+        bind_groups: &[&BindGroup],
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Sky Pass Descriptor"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        for (i, bind_group) in bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(i as u32, *bind_group, &[]);
+        }
+        render_pass.draw(0..3, 0..1);
+    }
+}
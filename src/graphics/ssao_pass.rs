@@ -0,0 +1,278 @@
+use std::{collections::BTreeSet, io};
+
+use wgpu::*;
+
+use crate::{
+    graphics::{
+        shaders::{self, ShaderError},
+        structures::AmbientOcclusion,
+    },
+    settings::settings::SsaoQuality,
+};
+
+pub const SHADER: &str = "ssao_pass.wgsl";
+
+/// Format of the occlusion textures: the fraction of ambient light that gets through.
+pub const AO_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+/// Parameters for a quality level, `None` when SSAO is off.
+pub fn ambient_occlusion(quality: SsaoQuality) -> Option<AmbientOcclusion> {
+    let (sample_count, blur_radius) = match quality {
+        SsaoQuality::Off => return None,
+        SsaoQuality::Low => (8, 2),
+        SsaoQuality::Medium => (16, 3),
+        SsaoQuality::High => (32, 4),
+    };
+    Some(AmbientOcclusion {
+        radius: 1.0,
+        intensity: 1.5,
+        bias: 0.005,
+        sample_count,
+        blur_radius,
+        blur_sharpness: 40.0,
+        ..Default::default()
+    })
+}
+
+/// Screen-space ambient occlusion from the depth buffer and the normal G-buffer, followed by
+/// a depth-aware blur in each direction.
+pub struct SsaoPass {
+    occlusion: RenderPipeline,
+    blur_x: RenderPipeline,
+    blur_y: RenderPipeline,
+    params_buffer: Buffer,
+    occlusion_layout: BindGroupLayout,
+    blur_layout: BindGroupLayout,
+    /// Shader files the pipelines were built from, including includes.
+    pub files: BTreeSet<String>,
+}
+
+/// Views the pass reads and writes.
+pub struct SsaoTargets<'a> {
+    pub depth: &'a TextureView,
+    pub normals: &'a TextureView,
+    /// Receives the result.
+    pub ao: &'a TextureView,
+    /// Holds the horizontally blurred occlusion in between.
+    pub scratch: &'a TextureView,
+}
+
+fn uniform_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn texture_entry(binding: u32, sample_type: TextureSampleType) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: BindingType::Texture {
+            sample_type,
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+impl SsaoPass {
+    pub fn new(device: &Device) -> Self {
+        Self::load(device, &shaders::embedded).unwrap_or_else(|err| panic!("{SHADER}: {err}"))
+    }
+
+    pub fn load(
+        device: &Device,
+        load: &dyn Fn(&str) -> io::Result<String>,
+    ) -> Result<Self, ShaderError> {
+        let shader = shaders::compile(SHADER, load, &[])?;
+        device.push_error_scope(ErrorFilter::Validation);
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("SSAO Pass Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader.source.as_str().into()),
+        });
+
+        let unfilterable = TextureSampleType::Float { filterable: false };
+        let occlusion_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout SSAO"),
+            entries: &[
+                uniform_entry(0),
+                uniform_entry(1),
+                texture_entry(2, unfilterable),
+                texture_entry(3, unfilterable),
+            ],
+        });
+        let blur_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout SSAO Blur"),
+            entries: &[
+                uniform_entry(1),
+                texture_entry(2, unfilterable),
+                texture_entry(4, unfilterable),
+            ],
+        });
+        let params_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("SSAO Buffer"),
+            size: std::mem::size_of::<AmbientOcclusion>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline = |label, layout: &BindGroupLayout, entry_point| {
+            let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &module,
+                    entry_point: Some("main_vertex"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &module,
+                    entry_point: Some(entry_point),
+                    targets: &[Some(ColorTargetState {
+                        format: AO_FORMAT,
+                        blend: Some(BlendState::REPLACE),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                multiview: None,
+                cache: Default::default(),
+            })
+        };
+        let occlusion = pipeline("SSAO Pipeline", &occlusion_layout, "occlusion_fragment");
+        let blur_x = pipeline("SSAO Blur X Pipeline", &blur_layout, "blur_x_fragment");
+        let blur_y = pipeline("SSAO Blur Y Pipeline", &blur_layout, "blur_y_fragment");
+
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(err) => Err(ShaderError::Pipeline(err)),
+            None => Ok(Self {
+                occlusion,
+                blur_x,
+                blur_y,
+                params_buffer,
+                occlusion_layout,
+                blur_layout,
+                files: shader.files().into_iter().map(str::to_string).collect(),
+            }),
+        }
+    }
+
+    pub fn write_params(&self, queue: &Queue, params: &AmbientOcclusion) {
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(params));
+    }
+
+    /// Computes the occlusion into `targets.ao`, blurring it through `targets.scratch`.
+    pub fn encode(
+        &self,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+        view_buffer: &Buffer,
+        targets: SsaoTargets,
+    ) {
+        // Graph textures can be reallocated between frames, so their bind groups are made
+        // as they are drawn.
+        let occlusion = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Bind Group SSAO"),
+            layout: &self.occlusion_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: view_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: self.params_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(targets.depth),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(targets.normals),
+                },
+            ],
+        });
+        let blur = |source| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Bind Group SSAO Blur"),
+                layout: &self.blur_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: self.params_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::TextureView(targets.depth),
+                    },
+                    BindGroupEntry {
+                        binding: 4,
+                        resource: BindingResource::TextureView(source),
+                    },
+                ],
+            })
+        };
+
+        draw(encoder, &self.occlusion, &occlusion, targets.ao);
+        draw(encoder, &self.blur_x, &blur(targets.ao), targets.scratch);
+        draw(encoder, &self.blur_y, &blur(targets.scratch), targets.ao);
+    }
+}
+
+fn draw(
+    encoder: &mut CommandEncoder,
+    pipeline: &RenderPipeline,
+    bind_group: &BindGroup,
+    target: &TextureView,
+) {
+    let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some("SSAO Pass Descriptor"),
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: Operations {
+                load: LoadOp::Clear(Color::WHITE),
+                store: StoreOp::Store,
+            },
+            depth_slice: None,
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn higher_quality_takes_more_samples() {
+        assert!(ambient_occlusion(SsaoQuality::Off).is_none());
+        let samples = [SsaoQuality::Low, SsaoQuality::Medium, SsaoQuality::High]
+            .map(|quality| ambient_occlusion(quality).unwrap().sample_count);
+        assert!(samples.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
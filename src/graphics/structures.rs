@@ -1,8 +1,7 @@
-use std::{collections::HashSet, time::Instant};
+use std::time::Instant;
 
 use bytemuck::{Pod, Zeroable};
-use glam::{Mat4, Vec2, Vec3A};
-use winit::keyboard::KeyCode;
+use glam::{Mat4, Vec2, Vec3A, Vec4};
 
 #[repr(C, align(16))]
 #[derive(Clone, Copy, Pod, Zeroable, Default, Debug)]
@@ -12,7 +11,8 @@ pub struct Globals {
     pub time_passed: f32,
     pub frame_time: f32,
     pub frame: u32,
-    pub _pad: [f32; 1],
+    /// World clock, see `WorldTime`.
+    pub time_of_day: f32,
 }
 
 #[repr(C, align(16))]
@@ -25,12 +25,98 @@ pub struct View {
     pub camera_position: Vec3A,
 }
 
+/// Shadow cascades the lighting uniform has room for; shaders get it as `MAX_CASCADES`.
+pub const MAX_CASCADES: usize = 4;
+
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Pod, Zeroable, Default, Debug)]
+pub struct Lights {
+    /// Light-space projection of each shadow cascade.
+    pub cascades: [Mat4; MAX_CASCADES],
+    /// Distance along the view direction where each cascade ends.
+    pub cascade_splits: Vec4,
+    /// World-space size of a shadow map texel in each cascade.
+    pub cascade_texel_sizes: Vec4,
+    /// Unit vector toward the sun; the moon is opposite.
+    pub sun_direction: Vec3A,
+    /// Unit vector toward the sun by day and the moon by night, which cast the shadows.
+    pub light_direction: Vec3A,
+    pub light_color: Vec3A,
+    /// Light from the whole sky, for ambient shading.
+    pub sky_light: Vec3A,
+    pub zenith_color: Vec3A,
+    pub horizon_color: Vec3A,
+    pub cascade_count: u32,
+    /// Depth offset against shadow acne, in light-space depth units.
+    pub depth_bias: f32,
+    /// How far receivers are pushed along their normal before the lookup, in texels.
+    pub normal_offset: f32,
+    /// PCF kernel radius in texels; the filter samples a square of `2 * radius + 1` texels.
+    pub pcf_radius: u32,
+}
+
+/// Exponential height fog, which also fades terrain out toward the end of the render distance.
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Pod, Zeroable, Default, Debug)]
+pub struct Fog {
+    /// Linear RGB; the sky horizon color above water.
+    pub color: Vec3A,
+    /// Extinction per block at `base_height`.
+    pub density: f32,
+    /// How quickly the density falls off with height, per block. 0 for uniform fog.
+    pub height_falloff: f32,
+    pub base_height: f32,
+    /// View distances over which terrain fades fully into the fog color.
+    pub start_distance: f32,
+    pub end_distance: f32,
+    /// How much the sky itself is hidden by the fog; 1 underwater.
+    pub sky_amount: f32,
+    pub _pad: [f32; 2],
+}
+
+/// Parameters of the bloom and tonemapping passes that turn the HDR scene into the frame.
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Pod, Zeroable, Default, Debug)]
+pub struct PostEffects {
+    /// Linear multiplier applied to the scene before bloom and tonemapping.
+    pub exposure: f32,
+    pub bloom_intensity: f32,
+    /// Exposed brightness above which pixels bloom.
+    pub bloom_threshold: f32,
+    /// Width of the soft transition into blooming, relative to the threshold.
+    pub bloom_knee: f32,
+    /// `Tonemapper` as its declaration index.
+    pub tonemapper: u32,
+    /// Set when the output format isn't sRGB, so the shader has to encode it.
+    pub encode_srgb: u32,
+    /// Set when the SSAO pass ran and its result should darken the scene.
+    pub ambient_occlusion: u32,
+    pub _pad: u32,
+}
+
+/// Parameters of the screen-space ambient occlusion pass.
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Pod, Zeroable, Default, Debug)]
+pub struct AmbientOcclusion {
+    /// World-space radius of the sampled hemisphere, in blocks.
+    pub radius: f32,
+    /// Exponent applied to the unoccluded fraction; higher darkens creases more.
+    pub intensity: f32,
+    /// Depth difference per block of distance from the camera below which a sample doesn't
+    /// count as occluded.
+    pub bias: f32,
+    pub sample_count: u32,
+    /// Taps on each side of the center in each blur direction.
+    pub blur_radius: u32,
+    /// How strongly the blur avoids mixing across depth discontinuities.
+    pub blur_sharpness: f32,
+    pub _pad: [u32; 2],
+}
+
 #[derive(Debug)]
 pub struct Metadata {
     pub start_instant: Instant,
     pub prev_frame_start_insant: Instant,
-    pub keyboard_state: HashSet<KeyCode>,
-    pub delta_mouse: Vec2,
 }
 
 #[repr(C, align(64))]
@@ -47,8 +133,12 @@ impl Metadata {
         Metadata {
             start_instant: Instant::now(),
             prev_frame_start_insant: Instant::now(),
-            keyboard_state: HashSet::new(),
-            delta_mouse: Vec2::ZERO,
         }
     }
 }
+
+impl Default for Metadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
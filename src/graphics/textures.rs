@@ -0,0 +1,57 @@
+use wgpu::*;
+
+use crate::meshing::atlas::TextureAtlas;
+
+pub struct Textures {
+    pub atlas: Texture,
+    pub atlas_view: TextureView,
+    pub atlas_sampler: Sampler,
+}
+
+impl Textures {
+    pub fn new(device: &Device, queue: &Queue, atlas: &TextureAtlas) -> Self {
+        let size = Extent3d {
+            width: atlas.width,
+            height: atlas.height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Atlas Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            &atlas.pixels,
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(atlas.width * 4),
+                rows_per_image: Some(atlas.height),
+            },
+            size,
+        );
+
+        Self {
+            atlas_view: texture.create_view(&TextureViewDescriptor::default()),
+            atlas: texture,
+            atlas_sampler: device.create_sampler(&SamplerDescriptor {
+                label: Some("Atlas Sampler"),
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Nearest,
+                min_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+        }
+    }
+}
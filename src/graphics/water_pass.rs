@@ -0,0 +1,280 @@
+use std::{collections::BTreeSet, io, ops::Range};
+
+use wgpu::*;
+
+use crate::graphics::{
+    post_pass::HDR_FORMAT,
+    render_pass,
+    shaders::{self, ShaderError},
+};
+
+pub const SHADER: &str = "water_pass.wgsl";
+
+/// Views the surface is drawn with.
+pub struct WaterTargets<'a> {
+    pub color: &'a TextureView,
+    pub depth: &'a TextureView,
+    /// Copy of `color` taken by `encode_scene_copy`, with distance from the camera in alpha.
+    pub scene: &'a TextureView,
+}
+
+/// Draws the top surface of water: animated waves that refract the opaque scene behind them,
+/// absorbing light with depth, and reflect it by marching a copy of the scene, falling back to
+/// the sky. The scene is copied first, as the surface can't sample what it draws into.
+pub struct WaterPass {
+    copy: RenderPipeline,
+    surface: RenderPipeline,
+    copy_layout: BindGroupLayout,
+    scene_layout: BindGroupLayout,
+    sampler: Sampler,
+    /// Shader files the pipelines were built from, including includes.
+    pub files: BTreeSet<String>,
+}
+
+fn texture_entry(binding: u32, filterable: bool) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable },
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+impl WaterPass {
+    /// `bind_group_layouts` are the render pass's globals and view layouts.
+    pub fn new(device: &Device, bind_group_layouts: &[&BindGroupLayout]) -> Self {
+        Self::load(device, bind_group_layouts, &shaders::embedded)
+            .unwrap_or_else(|err| panic!("{SHADER}: {err}"))
+    }
+
+    pub fn load(
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+        load: &dyn Fn(&str) -> io::Result<String>,
+    ) -> Result<Self, ShaderError> {
+        let shader = shaders::compile(SHADER, load, &[])?;
+        device.push_error_scope(ErrorFilter::Validation);
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Water Pass Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader.source.as_str().into()),
+        });
+
+        // Depth textures are bound as float textures: loading from depth textures isn't
+        // supported everywhere.
+        let copy_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout Scene Copy"),
+            entries: &[texture_entry(1, false), texture_entry(2, false)],
+        });
+        let scene_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout Water Scene"),
+            entries: &[
+                texture_entry(0, true),
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Water Scene Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let copy_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Scene Copy Pipeline Layout"),
+            bind_group_layouts: &[&copy_layout, bind_group_layouts[1]],
+            push_constant_ranges: &[],
+        });
+        let copy = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Scene Copy Pipeline"),
+            layout: Some(&copy_pipeline_layout),
+            vertex: VertexState {
+                module: &module,
+                entry_point: Some("copy_vertex"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: Some("copy_fragment"),
+                targets: &[Some(ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: Default::default(),
+        });
+
+        let mut layouts = bind_group_layouts[..2].to_vec();
+        layouts.push(&scene_layout);
+        let surface_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Water Surface Pipeline Layout"),
+            bind_group_layouts: &layouts,
+            push_constant_ranges: &[],
+        });
+        let surface = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Water Surface Pipeline"),
+            layout: Some(&surface_pipeline_layout),
+            vertex: VertexState {
+                module: &module,
+                entry_point: Some("main_vertex"),
+                buffers: &[render_pass::vertex_layout()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: Some("main_fragment"),
+                targets: &[Some(ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            // Seen from below as well, when the camera is underwater.
+            primitive: PrimitiveState::default(),
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: Default::default(),
+        });
+
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(err) => Err(ShaderError::Pipeline(err)),
+            None => Ok(Self {
+                copy,
+                surface,
+                copy_layout,
+                scene_layout,
+                sampler,
+                files: shader.files().into_iter().map(str::to_string).collect(),
+            }),
+        }
+    }
+
+    /// Copies the scene for the surface to sample, turning depth into distance from the
+    /// camera. `view_bind_group` is the render pass's view group.
+    pub fn encode_scene_copy(
+        &self,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+        color: &TextureView,
+        depth: &TextureView,
+        view_bind_group: &BindGroup,
+        scene: &TextureView,
+    ) {
+        // Graph textures can be reallocated between frames, so their bind groups are made
+        // as they are drawn.
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Bind Group Scene Copy"),
+            layout: &self.copy_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(color),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(depth),
+                },
+            ],
+        });
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Scene Copy Descriptor"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: scene,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.copy);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.set_bind_group(1, view_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Draws the `vertices` of the water layer. `bind_groups` are the render pass's globals
+    /// and view groups.
+    pub fn encode(
+        &self,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+        targets: WaterTargets,
+        bind_groups: &[&BindGroup],
+        vertex_buffer: &Buffer,
+        vertices: Range<u32>,
+    ) {
+        let scene = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Bind Group Water Scene"),
+            layout: &self.scene_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(targets.scene),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Water Pass Descriptor"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: targets.color,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: targets.depth,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.surface);
+        render_pass.set_bind_group(0, bind_groups[0], &[]);
+        render_pass.set_bind_group(1, bind_groups[1], &[]);
+        render_pass.set_bind_group(2, &scene, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(vertices, 0..1);
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Descend,
+    Sprint,
+    ToggleFly,
+    LookUp,
+    LookDown,
+    LookLeft,
+    LookRight,
+    Break,
+    Place,
+    /// Switches the held block to the next placeable one.
+    NextBlock,
+    PreviousBlock,
+    CaptureCursor,
+    ReleaseCursor,
+    Screenshot,
+    /// Walks through every action, binding each to the next input, and saves the result.
+    Rebind,
+}
+
+impl Action {
+    pub const ALL: [Action; 20] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Descend,
+        Action::Sprint,
+        Action::ToggleFly,
+        Action::LookUp,
+        Action::LookDown,
+        Action::LookLeft,
+        Action::LookRight,
+        Action::Break,
+        Action::Place,
+        Action::NextBlock,
+        Action::PreviousBlock,
+        Action::CaptureCursor,
+        Action::ReleaseCursor,
+        Action::Screenshot,
+        Action::Rebind,
+    ];
+}
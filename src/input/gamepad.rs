@@ -0,0 +1,120 @@
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use crate::input::{
+    binding::{GamepadAxis, GamepadButton},
+    state::InputState,
+};
+
+/// Connected gamepads, read through gilrs and fed into `InputState` as positional buttons and
+/// axes.
+pub struct Gamepads {
+    gilrs: Gilrs,
+}
+
+impl Gamepads {
+    /// `None`, after logging why, where gamepads can't be read.
+    pub fn new() -> Option<Self> {
+        Gilrs::new()
+            .inspect_err(|err| log::warn!("gamepads unavailable: {err}"))
+            .ok()
+            .map(|gilrs| Gamepads { gilrs })
+    }
+
+    /// Passes on every gamepad event since the last poll.
+    pub fn poll(&mut self, input: &mut InputState) {
+        while let Some(event) = self.gilrs.next_event() {
+            match event.event {
+                EventType::ButtonPressed(button, _) => {
+                    if let Some(button) = gamepad_button(button) {
+                        input.handle_gamepad_button(button, true);
+                    }
+                }
+                EventType::ButtonReleased(button, _) => {
+                    if let Some(button) = gamepad_button(button) {
+                        input.handle_gamepad_button(button, false);
+                    }
+                }
+                EventType::ButtonChanged(button, value, _) => {
+                    if let Some(axis) = trigger_axis(button) {
+                        input.handle_gamepad_axis(axis, value);
+                    }
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    if let Some(axis) = gamepad_axis(axis) {
+                        input.handle_gamepad_axis(axis, value);
+                    }
+                }
+                // Nothing is held on a gamepad that's gone.
+                EventType::Disconnected => {
+                    for axis in GamepadAxis::ALL {
+                        input.handle_gamepad_axis(axis, 0.0);
+                    }
+                    for button in GamepadButton::ALL {
+                        input.handle_gamepad_button(button, false);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Digital buttons; the analog triggers are axes, see `trigger_axis`.
+fn gamepad_button(button: Button) -> Option<GamepadButton> {
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::West => GamepadButton::West,
+        Button::North => GamepadButton::North,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+fn trigger_axis(button: Button) -> Option<GamepadAxis> {
+    match button {
+        Button::LeftTrigger2 => Some(GamepadAxis::LeftTrigger),
+        Button::RightTrigger2 => Some(GamepadAxis::RightTrigger),
+        _ => None,
+    }
+}
+
+/// Sticks, with up positive like gilrs reports them.
+fn gamepad_axis(axis: Axis) -> Option<GamepadAxis> {
+    match axis {
+        Axis::LeftStickX => Some(GamepadAxis::LeftStickX),
+        Axis::LeftStickY => Some(GamepadAxis::LeftStickY),
+        Axis::RightStickX => Some(GamepadAxis::RightStickX),
+        Axis::RightStickY => Some(GamepadAxis::RightStickY),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{action::Action, input_map::InputMap};
+
+    #[test]
+    fn gilrs_inputs_drive_the_default_actions() {
+        let mut input = InputState::new(InputMap::default());
+        let button = gamepad_button(Button::South).unwrap();
+        input.handle_gamepad_button(button, true);
+        input.handle_gamepad_axis(gamepad_axis(Axis::LeftStickY).unwrap(), 0.8);
+        input.handle_gamepad_axis(trigger_axis(Button::RightTrigger2).unwrap(), 1.0);
+        input.update();
+        assert!(input.pressed(Action::Jump));
+        assert!(input.pressed(Action::Break));
+        assert_eq!(input.value(Action::MoveForward), 0.8);
+        assert_eq!(gamepad_button(Button::RightTrigger2), None);
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+use crate::input::{
+    action::Action,
+    binding::{AxisDirection, Binding, GamepadAxis, GamepadButton},
+};
+
+#[derive(Debug)]
+pub enum InputMapError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for InputMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputMapError::Io(err) => write!(f, "input map io error: {err}"),
+            InputMapError::Parse(err) => write!(f, "invalid input map: {err}"),
+            InputMapError::Serialize(err) => write!(f, "failed to serialize input map: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for InputMapError {}
+
+/// Bindings for every action; an action fires when any of its bindings does.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputMap {
+    pub bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl InputMap {
+    pub fn empty() -> Self {
+        InputMap {
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the map from `path`, falling back to the defaults if the file doesn't exist.
+    /// Actions missing from the file keep their default bindings.
+    pub fn load_or_default(path: &Path) -> Result<Self, InputMapError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(InputMapError::Io(err)),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, InputMapError> {
+        let loaded: InputMap = toml::from_str(text).map_err(InputMapError::Parse)?;
+        let mut map = Self::default();
+        map.bindings.extend(loaded.bindings);
+        Ok(map)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), InputMapError> {
+        let text = toml::to_string_pretty(self).map_err(InputMapError::Serialize)?;
+        fs::write(path, text).map_err(InputMapError::Io)
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Replaces all bindings of `action` with `binding`.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        self.bindings.insert(action, vec![binding]);
+    }
+
+    pub fn unbind(&mut self, action: Action, binding: Binding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|bound| *bound != binding);
+        }
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use AxisDirection::{Negative, Positive};
+
+        let mut map = Self::empty();
+        let defaults = [
+            (Action::MoveForward, Binding::Key(KeyCode::KeyW)),
+            (
+                Action::MoveForward,
+                Binding::axis(GamepadAxis::LeftStickY, Positive),
+            ),
+            (Action::MoveBackward, Binding::Key(KeyCode::KeyS)),
+            (
+                Action::MoveBackward,
+                Binding::axis(GamepadAxis::LeftStickY, Negative),
+            ),
+            (Action::MoveLeft, Binding::Key(KeyCode::KeyA)),
+            (
+                Action::MoveLeft,
+                Binding::axis(GamepadAxis::LeftStickX, Negative),
+            ),
+            (Action::MoveRight, Binding::Key(KeyCode::KeyD)),
+            (
+                Action::MoveRight,
+                Binding::axis(GamepadAxis::LeftStickX, Positive),
+            ),
+            (Action::Jump, Binding::Key(KeyCode::Space)),
+            (Action::Jump, Binding::GamepadButton(GamepadButton::South)),
+            (Action::Descend, Binding::Key(KeyCode::ControlLeft)),
+            (Action::Descend, Binding::GamepadButton(GamepadButton::East)),
+            (Action::Sprint, Binding::Key(KeyCode::ShiftLeft)),
+            (
+                Action::Sprint,
+                Binding::GamepadButton(GamepadButton::LeftStick),
+            ),
+            (Action::ToggleFly, Binding::Key(KeyCode::KeyF)),
+            (
+                Action::ToggleFly,
+                Binding::GamepadButton(GamepadButton::North),
+            ),
+            (
+                Action::LookUp,
+                Binding::axis(GamepadAxis::RightStickY, Positive),
+            ),
+            (
+                Action::LookDown,
+                Binding::axis(GamepadAxis::RightStickY, Negative),
+            ),
+            (
+                Action::LookLeft,
+                Binding::axis(GamepadAxis::RightStickX, Negative),
+            ),
+            (
+                Action::LookRight,
+                Binding::axis(GamepadAxis::RightStickX, Positive),
+            ),
+            (Action::Break, Binding::Mouse(MouseButton::Left)),
+            (
+                Action::Break,
+                Binding::axis(GamepadAxis::RightTrigger, Positive),
+            ),
+            (Action::Place, Binding::Mouse(MouseButton::Right)),
+            (
+                Action::Place,
+                Binding::axis(GamepadAxis::LeftTrigger, Positive),
+            ),
+            (Action::NextBlock, Binding::Key(KeyCode::KeyE)),
+            (
+                Action::NextBlock,
+                Binding::GamepadButton(GamepadButton::RightBumper),
+            ),
+            (Action::PreviousBlock, Binding::Key(KeyCode::KeyQ)),
+            (
+                Action::PreviousBlock,
+                Binding::GamepadButton(GamepadButton::LeftBumper),
+            ),
+            (Action::CaptureCursor, Binding::Mouse(MouseButton::Left)),
+            (Action::ReleaseCursor, Binding::Key(KeyCode::Escape)),
+            (Action::Screenshot, Binding::Key(KeyCode::F2)),
+            (Action::Rebind, Binding::Key(KeyCode::F4)),
+        ];
+        for (action, binding) in defaults {
+            map.bind(action, binding);
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_escape_releases_the_cursor() {
+        let map = InputMap::default();
+        assert_eq!(
+            map.bindings(Action::ReleaseCursor),
+            &[Binding::Key(KeyCode::Escape)]
+        );
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let mut map = InputMap::default();
+        map.rebind(Action::Jump, Binding::Key(KeyCode::KeyJ));
+        let text = toml::to_string_pretty(&map).unwrap();
+        assert_eq!(InputMap::parse(&text).unwrap(), map);
+    }
+
+    #[test]
+    fn partial_file_keeps_other_defaults() {
+        let map = InputMap::parse(
+            r#"
+            [bindings]
+            Jump = [{ key = "KeyJ" }, { gamepad_axis = { axis = "RightTrigger", direction = "positive" } }]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            map.bindings(Action::Jump),
+            &[
+                Binding::Key(KeyCode::KeyJ),
+                Binding::axis(GamepadAxis::RightTrigger, AxisDirection::Positive)
+            ]
+        );
+        assert_eq!(
+            map.bindings(Action::MoveForward),
+            InputMap::default().bindings(Action::MoveForward)
+        );
+    }
+}
@@ -0,0 +1,6 @@
+pub mod action;
+pub mod binding;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+pub mod input_map;
+pub mod state;
@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use glam::Vec2;
+use winit::{
+    event::{ElementState, MouseButton},
+    keyboard::KeyCode,
+};
+
+use crate::input::{
+    action::Action,
+    binding::{AxisDirection, Binding, GamepadAxis, GamepadButton},
+    input_map::InputMap,
+};
+
+/// Analog values above this count as the action being held.
+const PRESS_THRESHOLD: f32 = 0.5;
+/// Stick values below this are treated as resting.
+const AXIS_DEADZONE: f32 = 0.15;
+
+/// Raw device state fed from window and gamepad events, resolved into actions once per frame.
+#[derive(Debug)]
+pub struct InputState {
+    pub map: InputMap,
+
+    held: HashSet<Binding>,
+    /// Bindings pressed since the last `update`, so taps shorter than a frame still register.
+    tapped: HashSet<Binding>,
+    axes: HashMap<GamepadAxis, f32>,
+    pending_mouse_delta: Vec2,
+    mouse_delta: Vec2,
+
+    current: HashMap<Action, f32>,
+    previous: HashMap<Action, f32>,
+
+    /// Actions waiting for a new binding, the first one taking the next input.
+    rebinding: VecDeque<Action>,
+    /// Inputs taken for a rebind, ignored until let go so key repeat or a held stick doesn't
+    /// rebind the next action too.
+    swallowed: HashSet<Binding>,
+}
+
+impl InputState {
+    pub fn new(map: InputMap) -> Self {
+        InputState {
+            map,
+            held: HashSet::new(),
+            tapped: HashSet::new(),
+            axes: HashMap::new(),
+            pending_mouse_delta: Vec2::ZERO,
+            mouse_delta: Vec2::ZERO,
+            current: HashMap::new(),
+            previous: HashMap::new(),
+            rebinding: VecDeque::new(),
+            swallowed: HashSet::new(),
+        }
+    }
+
+    pub fn handle_key(&mut self, key_code: KeyCode, state: ElementState) {
+        self.handle_binding(Binding::Key(key_code), state.is_pressed());
+    }
+
+    pub fn handle_mouse_button(&mut self, button: MouseButton, state: ElementState) {
+        self.handle_binding(Binding::Mouse(button), state.is_pressed());
+    }
+
+    pub fn handle_gamepad_button(&mut self, button: GamepadButton, pressed: bool) {
+        self.handle_binding(Binding::GamepadButton(button), pressed);
+    }
+
+    pub fn handle_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        let value = if value.abs() < AXIS_DEADZONE {
+            0.0
+        } else {
+            value.clamp(-1.0, 1.0)
+        };
+        for (direction, held) in [
+            (AxisDirection::Positive, value >= PRESS_THRESHOLD),
+            (AxisDirection::Negative, value <= -PRESS_THRESHOLD),
+        ] {
+            let binding = Binding::axis(axis, direction);
+            if !held {
+                self.swallowed.remove(&binding);
+            } else if !self.swallowed.contains(&binding) && self.capture_rebind(binding) {
+                self.swallowed.insert(binding);
+            }
+        }
+        self.axes.insert(axis, value);
+    }
+
+    pub fn handle_mouse_motion(&mut self, delta: Vec2) {
+        self.pending_mouse_delta += delta;
+    }
+
+    fn handle_binding(&mut self, binding: Binding, pressed: bool) {
+        if pressed {
+            if self.swallowed.contains(&binding) {
+                return;
+            }
+            if self.capture_rebind(binding) {
+                self.swallowed.insert(binding);
+                return;
+            }
+            self.held.insert(binding);
+            self.tapped.insert(binding);
+        } else {
+            self.swallowed.remove(&binding);
+            self.held.remove(&binding);
+        }
+    }
+
+    /// Binds the next pressed key, button or axis to `action`, replacing its bindings.
+    pub fn begin_rebind(&mut self, action: Action) {
+        self.rebinding = VecDeque::from([action]);
+    }
+
+    /// Rebinds every action in turn, each to the next input. The rebind action's own bindings
+    /// skip an action and keep what it has, and are never rebound themselves.
+    pub fn begin_rebind_all(&mut self) {
+        self.rebinding = Action::ALL
+            .into_iter()
+            .filter(|action| *action != Action::Rebind)
+            .collect();
+    }
+
+    pub fn is_rebinding(&self) -> bool {
+        !self.rebinding.is_empty()
+    }
+
+    /// The action the next input will be bound to.
+    pub fn rebinding(&self) -> Option<Action> {
+        self.rebinding.front().copied()
+    }
+
+    fn capture_rebind(&mut self, binding: Binding) -> bool {
+        let Some(action) = self.rebinding.pop_front() else {
+            return false;
+        };
+        if action == Action::Rebind || !self.map.bindings(Action::Rebind).contains(&binding) {
+            self.map.rebind(action, binding);
+        }
+        true
+    }
+
+    fn binding_value(&self, binding: &Binding) -> f32 {
+        match binding {
+            Binding::GamepadAxis { axis, direction } => {
+                let value = self.axes.get(axis).copied().unwrap_or(0.0);
+                match direction {
+                    AxisDirection::Positive => value.max(0.0),
+                    AxisDirection::Negative => (-value).max(0.0),
+                }
+            }
+            _ if self.held.contains(binding) || self.tapped.contains(binding) => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Resolves actions for the new frame. Call once per frame before querying.
+    pub fn update(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+        for action in Action::ALL {
+            let value = self
+                .map
+                .bindings(action)
+                .iter()
+                .map(|binding| self.binding_value(binding))
+                .fold(0.0, f32::max);
+            if value > 0.0 {
+                self.current.insert(action, value);
+            }
+        }
+        self.tapped.clear();
+        self.mouse_delta = std::mem::take(&mut self.pending_mouse_delta);
+    }
+
+    /// Strength of the action this frame, in 0.0..=1.0.
+    pub fn value(&self, action: Action) -> f32 {
+        self.current.get(&action).copied().unwrap_or(0.0)
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.value(action) >= PRESS_THRESHOLD
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed(action) && !Self::is_pressed_in(&self.previous, action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        !self.pressed(action) && Self::is_pressed_in(&self.previous, action)
+    }
+
+    fn is_pressed_in(values: &HashMap<Action, f32>, action: Action) -> bool {
+        values
+            .get(&action)
+            .is_some_and(|value| *value >= PRESS_THRESHOLD)
+    }
+
+    /// `positive` minus `negative`, e.g. forward/backward into one signed axis.
+    pub fn axis(&self, negative: Action, positive: Action) -> f32 {
+        self.value(positive) - self.value(negative)
+    }
+
+    /// Mouse movement accumulated over the frame resolved by the last `update`.
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_press_edges() {
+        let mut input = InputState::new(InputMap::default());
+        input.handle_key(KeyCode::Space, ElementState::Pressed);
+        input.update();
+        assert!(input.just_pressed(Action::Jump));
+        input.update();
+        assert!(input.pressed(Action::Jump) && !input.just_pressed(Action::Jump));
+
+        input.handle_key(KeyCode::Space, ElementState::Released);
+        input.update();
+        assert!(input.just_released(Action::Jump));
+    }
+
+    #[test]
+    fn tap_within_a_frame_registers() {
+        let mut input = InputState::new(InputMap::default());
+        input.handle_key(KeyCode::KeyF, ElementState::Pressed);
+        input.handle_key(KeyCode::KeyF, ElementState::Released);
+        input.update();
+        assert!(input.just_pressed(Action::ToggleFly));
+        input.update();
+        assert!(input.just_released(Action::ToggleFly));
+    }
+
+    #[test]
+    fn gamepad_axes_map_to_actions() {
+        let mut input = InputState::new(InputMap::default());
+        input.handle_gamepad_axis(GamepadAxis::LeftStickY, -0.75);
+        input.handle_gamepad_axis(GamepadAxis::LeftStickX, 0.1);
+        input.update();
+        assert_eq!(input.value(Action::MoveBackward), 0.75);
+        assert_eq!(input.axis(Action::MoveLeft, Action::MoveRight), 0.0);
+        assert_eq!(input.axis(Action::MoveBackward, Action::MoveForward), -0.75);
+    }
+
+    #[test]
+    fn rebinds_every_action_in_turn() {
+        let mut input = InputState::new(InputMap::default());
+        let tap = |input: &mut InputState, key| {
+            input.handle_key(key, ElementState::Pressed);
+            // Key repeat while held doesn't move on to the next action.
+            input.handle_key(key, ElementState::Pressed);
+            input.handle_key(key, ElementState::Released);
+        };
+        input.begin_rebind_all();
+        assert_eq!(input.rebinding(), Some(Action::MoveForward));
+        tap(&mut input, KeyCode::ArrowUp);
+        // The rebind key skips an action, leaving its bindings alone.
+        tap(&mut input, KeyCode::F4);
+        input.handle_gamepad_axis(GamepadAxis::LeftStickX, -0.9);
+        input.handle_gamepad_axis(GamepadAxis::LeftStickX, -1.0);
+        assert_eq!(input.rebinding(), Some(Action::MoveRight));
+
+        assert_eq!(
+            input.map.bindings(Action::MoveForward),
+            &[Binding::Key(KeyCode::ArrowUp)]
+        );
+        assert_eq!(
+            input.map.bindings(Action::MoveBackward),
+            InputMap::default().bindings(Action::MoveBackward)
+        );
+        assert_eq!(
+            input.map.bindings(Action::MoveLeft),
+            &[Binding::axis(
+                GamepadAxis::LeftStickX,
+                AxisDirection::Negative
+            )]
+        );
+
+        while input.is_rebinding() {
+            tap(&mut input, KeyCode::F4);
+        }
+        input.update();
+        assert!(!input.pressed(Action::Rebind));
+        assert_eq!(
+            input.map.bindings(Action::Rebind),
+            &[Binding::Key(KeyCode::F4)]
+        );
+    }
+
+    #[test]
+    fn rebinds_to_next_input() {
+        let mut input = InputState::new(InputMap::default());
+        input.begin_rebind(Action::Jump);
+        input.handle_key(KeyCode::KeyJ, ElementState::Pressed);
+        input.update();
+        assert!(!input.pressed(Action::Jump));
+        assert_eq!(
+            input.map.bindings(Action::Jump),
+            &[Binding::Key(KeyCode::KeyJ)]
+        );
+
+        input.handle_key(KeyCode::KeyJ, ElementState::Released);
+        input.handle_key(KeyCode::KeyJ, ElementState::Pressed);
+        input.update();
+        assert!(input.just_pressed(Action::Jump));
+    }
+}
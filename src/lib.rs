@@ -0,0 +1,9 @@
+pub mod app;
+pub mod formats;
+pub mod graphics;
+pub mod input;
+pub mod meshing;
+pub mod physics;
+pub mod settings;
+pub mod simulation;
+pub mod world;
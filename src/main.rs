@@ -1,19 +1,81 @@
-mod app;
-mod graphics;
+use std::path::Path;
 
-use crate::{app::App, graphics::graphics::*};
+use clap::Parser;
+use rust_voxel_blocks::{
+    app::App,
+    formats::mesh_export::ExportMesh,
+    graphics::graphics::*,
+    settings::{cli::Cli, settings::Settings},
+    world::storage::world_save::WorldSave,
+};
 use winit::event_loop::{ControlFlow, EventLoop};
 
 fn run_app(event_loop: EventLoop<Graphics>, mut app: App) {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("error")).init();
-
     let _ = event_loop.run_app(&mut app);
 }
 
+fn export_mesh(settings: &Settings, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let world = WorldSave::open(&settings.world.path)?.load_world()?;
+    let mesh = ExportMesh::from_world(&world);
+    mesh.save(path)?;
+    log::info!(
+        "exported {} vertices to {}",
+        mesh.vertices.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn run_headless(
+    settings: &Settings,
+    path: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let window = &settings.window;
+    let mut gfx = pollster::block_on(create_headless_graphics(
+        settings,
+        window.width,
+        window.height,
+        true,
+    ))?;
+    log::info!("rendering headless on {:?}", gfx.adapter.get_info());
+    match path {
+        Some(path) => gfx.capture()?.save(path)?,
+        None => gfx.save_screenshot(),
+    }
+    Ok(())
+}
+
 fn main() {
+    let cli = Cli::parse();
+    let settings = cli.load_settings().unwrap_or_else(|err| {
+        eprintln!("{}: {err}", cli.config.display());
+        std::process::exit(2);
+    });
+
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(&settings.debug.log_filter),
+    )
+    .init();
+
+    if let Some(path) = &cli.export_mesh {
+        if let Err(err) = export_mesh(&settings, path) {
+            eprintln!("{}: {err}", path.display());
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if cli.headless {
+        if let Err(err) = run_headless(&settings, cli.screenshot.as_deref()) {
+            eprintln!("headless rendering failed: {err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let event_loop = EventLoop::<Graphics>::with_user_event().build().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
 
-    let app = App::new(&event_loop);
+    let app = App::new(&event_loop, settings);
     run_app(event_loop, app);
 }
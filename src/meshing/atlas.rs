@@ -0,0 +1,101 @@
+use std::collections::{BTreeSet, HashMap};
+
+use glam::Vec2;
+
+use crate::world::block::{BLOCKS, BlockId, RenderLayer};
+
+pub const TILE_SIZE: u32 = 16;
+
+/// CPU-side texture atlas with one generated tile per block, in RGBA8 sRGB.
+#[derive(Clone, Debug)]
+pub struct TextureAtlas {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    tiles: HashMap<BlockId, u32>,
+    columns: u32,
+}
+
+impl TextureAtlas {
+    /// Atlas with a tile for each of `blocks` (air is skipped); states share their block's tile.
+    pub fn new(blocks: impl IntoIterator<Item = BlockId>) -> Self {
+        let blocks: BTreeSet<u16> = blocks
+            .into_iter()
+            .filter(|block| !block.is_air())
+            .map(|block| block.base().0)
+            .collect();
+        let count = blocks.len().max(1) as u32;
+        let columns = (count as f32).sqrt().ceil() as u32;
+        let rows = count.div_ceil(columns);
+
+        let mut atlas = TextureAtlas {
+            width: columns * TILE_SIZE,
+            height: rows * TILE_SIZE,
+            pixels: vec![0; (columns * rows * TILE_SIZE * TILE_SIZE * 4) as usize],
+            tiles: HashMap::new(),
+            columns,
+        };
+        for (tile, block) in blocks.into_iter().enumerate() {
+            atlas.paint_tile(tile as u32, BlockId(block));
+            atlas.tiles.insert(BlockId(block), tile as u32);
+        }
+        atlas
+    }
+
+    /// Atlas covering every registered block.
+    pub fn registered() -> Self {
+        Self::new((0..BLOCKS.len() as u16).map(BlockId))
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = BlockId> + '_ {
+        self.tiles.keys().copied()
+    }
+
+    pub fn contains(&self, block: BlockId) -> bool {
+        self.tiles.contains_key(&block)
+    }
+
+    /// UV rectangle (min, max) of the block's tile, inset by half a texel against bleeding.
+    pub fn uv_rect(&self, block: BlockId) -> Option<(Vec2, Vec2)> {
+        let tile = *self.tiles.get(&block)?;
+        let size = Vec2::new(self.width as f32, self.height as f32);
+        let origin = Vec2::new(
+            ((tile % self.columns) * TILE_SIZE) as f32,
+            ((tile / self.columns) * TILE_SIZE) as f32,
+        );
+        Some((
+            (origin + 0.5) / size,
+            (origin + TILE_SIZE as f32 - 0.5) / size,
+        ))
+    }
+
+    /// Fills a tile with the block color, a little deterministic noise and a darker rim.
+    /// Cutout blocks get holes punched into the inside.
+    fn paint_tile(&mut self, tile: u32, block: BlockId) {
+        let color = block.color();
+        let cutout = block.layer() == RenderLayer::Cutout;
+        let origin_x = (tile % self.columns) * TILE_SIZE;
+        let origin_y = (tile / self.columns) * TILE_SIZE;
+        for y in 0..TILE_SIZE {
+            for x in 0..TILE_SIZE {
+                let hash = (x.wrapping_mul(73_856_093)
+                    ^ y.wrapping_mul(19_349_663)
+                    ^ (block.0 as u32).wrapping_mul(83_492_791))
+                    % 17;
+                let edge = x == 0 || y == 0 || x == TILE_SIZE - 1 || y == TILE_SIZE - 1;
+                let shade = if edge {
+                    0.82
+                } else {
+                    0.92 + hash as f32 * 0.01
+                };
+
+                let index = (((origin_y + y) * self.width + origin_x + x) * 4) as usize;
+                for (channel, value) in color[..3].iter().enumerate() {
+                    self.pixels[index + channel] = (*value as f32 * shade).round().min(255.0) as u8;
+                }
+                let hole = cutout && !edge && hash < 5;
+                self.pixels[index + 3] = if hole { 0 } else { color[3] };
+            }
+        }
+    }
+}
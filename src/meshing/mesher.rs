@@ -0,0 +1,385 @@
+use std::collections::HashSet;
+
+use glam::{IVec3, Vec2, Vec3, Vec3A};
+
+use crate::{
+    graphics::structures::VertexBuffer,
+    meshing::{
+        atlas::TextureAtlas,
+        models::{BakedQuad, baked},
+    },
+    world::{
+        block::{BlockId, RenderLayer, Shape},
+        chunk::{CHUNK_SIZE, Chunk},
+        world::World,
+    },
+};
+
+/// One face of a unit cube: the quad spans `base + u * s + v * t` for `s, t` in `0..=1`, with
+/// `u x v` pointing along `normal` so the corners wind counter-clockwise seen from outside.
+#[derive(Clone, Copy, Debug)]
+pub struct Face {
+    pub normal: IVec3,
+    pub base: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+}
+
+pub const FACES: [Face; 6] = [
+    Face {
+        normal: IVec3::X,
+        base: Vec3::X,
+        u: Vec3::Y,
+        v: Vec3::Z,
+    },
+    Face {
+        normal: IVec3::NEG_X,
+        base: Vec3::ZERO,
+        u: Vec3::Z,
+        v: Vec3::Y,
+    },
+    Face {
+        normal: IVec3::Y,
+        base: Vec3::Y,
+        u: Vec3::Z,
+        v: Vec3::X,
+    },
+    Face {
+        normal: IVec3::NEG_Y,
+        base: Vec3::ZERO,
+        u: Vec3::X,
+        v: Vec3::Z,
+    },
+    Face {
+        normal: IVec3::Z,
+        base: Vec3::Z,
+        u: Vec3::X,
+        v: Vec3::Y,
+    },
+    Face {
+        normal: IVec3::NEG_Z,
+        base: Vec3::ZERO,
+        u: Vec3::Y,
+        v: Vec3::X,
+    },
+];
+
+/// Triangle corners of a quad, as indices into its four corners.
+pub const QUAD_TRIANGLES: [usize; 6] = [0, 1, 2, 0, 2, 3];
+
+#[derive(Debug, Default)]
+pub struct ChunkMesh {
+    /// Triangle lists by render layer, indexed by `RenderLayer as usize`.
+    pub layers: [Vec<VertexBuffer>; RenderLayer::ALL.len()],
+    /// Blocks the atlas had no tile for; their faces were skipped.
+    pub missing: HashSet<BlockId>,
+}
+
+impl ChunkMesh {
+    pub fn layer(&self, layer: RenderLayer) -> &[VertexBuffer] {
+        &self.layers[layer as usize]
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.layers.iter().map(Vec::len).sum()
+    }
+
+    /// Every layer's triangles, in render order.
+    pub fn into_vertices(self) -> impl Iterator<Item = VertexBuffer> {
+        self.layers.into_iter().flatten()
+    }
+}
+
+/// Builds the triangle lists for a chunk in world space, emitting only faces that can be
+/// seen: those not against an opaque block or another block of their own kind. Leaves are the
+/// exception, as their inner faces show through the holes. Fluids are cut down to the height
+/// of their level. Blocks of other shapes or turned by their state are drawn from their model,
+/// see `models::baked`, with water around them when they are waterlogged.
+pub fn mesh_chunk(world: &World, chunk_pos: IVec3, atlas: &TextureAtlas) -> ChunkMesh {
+    let mut mesh = ChunkMesh::default();
+    let Some(chunk) = world.chunk(chunk_pos) else {
+        return mesh;
+    };
+    let origin = chunk_pos * CHUNK_SIZE;
+
+    for y in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let local = IVec3::new(x, y, z);
+                let block = chunk.get(local);
+                if block.is_air() {
+                    continue;
+                }
+                let neighbors = |direction| neighbor(world, chunk, origin, local + direction);
+                let position = (origin + local).as_vec3();
+                if block.is_waterlogged() {
+                    mesh_cube(&mut mesh, atlas, position, BlockId::WATER, neighbors);
+                }
+                match baked(block) {
+                    Some(quads) => mesh_model(&mut mesh, atlas, position, block, quads, neighbors),
+                    None => mesh_cube(&mut mesh, atlas, position, block, neighbors),
+                }
+            }
+        }
+    }
+    mesh
+}
+
+/// Whether `block` fills its cell with `kind`, counting water around waterlogged blocks. Slabs
+/// of a kind only fill their cell when they are double.
+fn fills(block: BlockId, kind: BlockId) -> bool {
+    (block.base() == kind && block.shape() == Shape::Cube)
+        || (kind == BlockId::WATER && block.is_waterlogged())
+}
+
+fn mesh_cube(
+    mesh: &mut ChunkMesh,
+    atlas: &TextureAtlas,
+    position: Vec3,
+    block: BlockId,
+    neighbor: impl Fn(IVec3) -> BlockId,
+) {
+    let kind = block.base();
+    let Some(uv_rect) = atlas.uv_rect(kind) else {
+        mesh.missing.insert(kind);
+        return;
+    };
+
+    let layer = block.layer();
+    let height = block.fluid().map_or(1.0, |_| block.fluid_height());
+    for face in &FACES {
+        let next = neighbor(face.normal);
+        if next.is_opaque() || (fills(next, kind) && layer != RenderLayer::Cutout) {
+            continue;
+        }
+        let layer = if block.is_water() && face.normal == IVec3::Y {
+            RenderLayer::Water
+        } else {
+            layer
+        };
+        let vertices = &mut mesh.layers[layer as usize];
+        let start = vertices.len();
+        push_face(vertices, position, face, uv_rect);
+        if height < 1.0 {
+            for vertex in &mut vertices[start..] {
+                vertex.position.y = position.y + (vertex.position.y - position.y) * height;
+            }
+        }
+    }
+}
+
+fn mesh_model(
+    mesh: &mut ChunkMesh,
+    atlas: &TextureAtlas,
+    position: Vec3,
+    block: BlockId,
+    quads: &[BakedQuad],
+    neighbor: impl Fn(IVec3) -> BlockId,
+) {
+    let layer = block.layer() as usize;
+    for quad in quads {
+        if quad.cull.is_some_and(|side| neighbor(side).is_opaque()) {
+            continue;
+        }
+        let Some((uv_min, uv_max)) = atlas.uv_rect(quad.texture) else {
+            mesh.missing.insert(quad.texture);
+            continue;
+        };
+        let vertices = &mut mesh.layers[layer];
+        for corner in QUAD_TRIANGLES {
+            vertices.push(VertexBuffer {
+                position: Vec3A::from(position + quad.corners[corner]),
+                normal: quad.normal.into(),
+                uv: uv_min + quad.uvs[corner] * (uv_max - uv_min),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+fn neighbor(world: &World, chunk: &Chunk, origin: IVec3, local: IVec3) -> BlockId {
+    if local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(CHUNK_SIZE)).all() {
+        chunk.get(local)
+    } else {
+        world.get_block(origin + local)
+    }
+}
+
+pub fn push_face(vertices: &mut Vec<VertexBuffer>, position: Vec3, face: &Face, uv: (Vec2, Vec2)) {
+    let (uv_min, uv_max) = uv;
+    let corners = [
+        (Vec3::ZERO, Vec2::new(uv_min.x, uv_max.y)),
+        (face.u, Vec2::new(uv_max.x, uv_max.y)),
+        (face.u + face.v, Vec2::new(uv_max.x, uv_min.y)),
+        (face.v, Vec2::new(uv_min.x, uv_min.y)),
+    ];
+    for corner in QUAD_TRIANGLES {
+        let (offset, uv) = corners[corner];
+        vertices.push(VertexBuffer {
+            position: Vec3A::from(position + face.base + offset),
+            normal: face.normal.as_vec3().into(),
+            uv,
+            ..Default::default()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::Half;
+
+    fn triangle_normal(triangle: &[VertexBuffer]) -> Vec3A {
+        (triangle[1].position - triangle[0].position)
+            .cross(triangle[2].position - triangle[0].position)
+            .normalize()
+    }
+
+    #[test]
+    fn culls_faces_between_blocks() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(0, 0, 0), BlockId::STONE);
+        world.set_block(IVec3::new(1, 0, 0), BlockId::DIRT);
+        let mesh = mesh_chunk(&world, IVec3::ZERO, &TextureAtlas::registered());
+        assert_eq!(mesh.vertex_count(), 10 * 6);
+    }
+
+    #[test]
+    fn blocks_show_through_water() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(0, 0, 0), BlockId::STONE);
+        world.set_block(IVec3::new(1, 0, 0), BlockId::WATER);
+        world.set_block(IVec3::new(2, 0, 0), BlockId::WATER);
+        let mesh = mesh_chunk(&world, IVec3::ZERO, &TextureAtlas::registered());
+        // All six stone faces, and the water's outer faces except the one against the stone,
+        // with its top surface on a layer of its own.
+        assert_eq!(mesh.layer(RenderLayer::Opaque).len(), 6 * 6);
+        assert_eq!(mesh.layer(RenderLayer::Water).len(), 2 * 6);
+        assert_eq!(mesh.layer(RenderLayer::Translucent).len(), 7 * 6);
+    }
+
+    #[test]
+    fn flowing_water_is_lowered_to_its_level() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(0, 0, 0), BlockId::WATER);
+        world.set_block(
+            IVec3::new(1, 0, 0),
+            BlockId::WATER.with_fluid_state(4, false),
+        );
+        let mesh = mesh_chunk(&world, IVec3::ZERO, &TextureAtlas::registered());
+        // No face between the source and the flow: they are the same fluid.
+        assert_eq!(mesh.layer(RenderLayer::Translucent).len(), 8 * 6);
+        let tops: Vec<f32> = mesh
+            .layer(RenderLayer::Water)
+            .iter()
+            .map(|vertex| vertex.position.y)
+            .collect();
+        assert_eq!(tops.len(), 2 * 6);
+        assert!(tops[..6].iter().all(|y| *y == 1.0));
+        assert!(tops[6..].iter().all(|y| *y == 0.5));
+    }
+
+    #[test]
+    fn slabs_are_hidden_only_on_their_sides() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(0, 0, 0), BlockId::STONE_SLAB);
+        world.set_block(IVec3::new(1, 0, 0), BlockId::STONE);
+        world.set_block(IVec3::new(0, 1, 0), BlockId::STONE);
+        let mesh = mesh_chunk(&world, IVec3::ZERO, &TextureAtlas::registered());
+        // The slab loses its face against the stone beside it but keeps its top, halfway up,
+        // and doesn't hide any stone face.
+        assert_eq!(mesh.layer(RenderLayer::Opaque).len(), (5 + 6 + 6) * 6);
+
+        // A double slab is a cube, but its kind beside it isn't.
+        let mut world = World::new();
+        world.set_block(
+            IVec3::new(0, 0, 0),
+            BlockId::STONE_SLAB.with_half(Half::Double),
+        );
+        world.set_block(IVec3::new(1, 0, 0), BlockId::STONE_SLAB);
+        let mesh = mesh_chunk(&world, IVec3::ZERO, &TextureAtlas::registered());
+        assert_eq!(mesh.layer(RenderLayer::Opaque).len(), (6 + 5) * 6);
+    }
+
+    #[test]
+    fn waterlogged_blocks_stand_in_water() {
+        let mut world = World::new();
+        let stairs = BlockId::STONE_STAIRS.with_waterlogged(true);
+        world.set_block(IVec3::new(0, 0, 0), stairs);
+        world.set_block(IVec3::new(1, 0, 0), BlockId::WATER);
+        let mesh = mesh_chunk(&world, IVec3::ZERO, &TextureAtlas::registered());
+        // Both boxes of the stairs, and the water of both cells as one body.
+        assert_eq!(mesh.layer(RenderLayer::Opaque).len(), 12 * 6);
+        assert_eq!(mesh.layer(RenderLayer::Translucent).len(), 8 * 6);
+        assert_eq!(mesh.layer(RenderLayer::Water).len(), 2 * 6);
+    }
+
+    #[test]
+    fn models_are_hidden_only_where_they_touch_their_neighbors() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(0, 0, 0), BlockId::TABLE);
+        world.set_block(IVec3::new(0, 1, 0), BlockId::STONE);
+        world.set_block(IVec3::new(1, 0, 0), BlockId::STONE);
+        let mesh = mesh_chunk(&world, IVec3::ZERO, &TextureAtlas::registered());
+        // The stone on top and beside hide the top and edge of the tabletop, but not the legs
+        // set in from the side, and the table hides no stone face.
+        let table = 4 + 4 * 6;
+        assert_eq!(mesh.layer(RenderLayer::Opaque).len(), (table + 6 + 6) * 6);
+    }
+
+    #[test]
+    fn model_faces_take_tiles_of_other_blocks() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(0, 0, 0), BlockId::TORCH);
+        let atlas = TextureAtlas::new([BlockId::TORCH]);
+        let mesh = mesh_chunk(&world, IVec3::ZERO, &atlas);
+        // The flame is drawn; the stick needs the log's tile.
+        assert_eq!(mesh.vertex_count(), 5 * 6);
+        assert_eq!(mesh.missing, HashSet::from([BlockId::LOG]));
+    }
+
+    #[test]
+    fn leaves_keep_their_inner_faces() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(0, 0, 0), BlockId::LEAVES);
+        world.set_block(IVec3::new(1, 0, 0), BlockId::LEAVES);
+        world.set_block(IVec3::new(0, 1, 0), BlockId::STONE);
+        let mesh = mesh_chunk(&world, IVec3::ZERO, &TextureAtlas::registered());
+        // Stone shows every face above the holey leaves; only the leaf face under it is hidden.
+        assert_eq!(mesh.layer(RenderLayer::Opaque).len(), 6 * 6);
+        assert_eq!(mesh.layer(RenderLayer::Cutout).len(), 11 * 6);
+    }
+
+    #[test]
+    fn culls_across_chunk_borders() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(CHUNK_SIZE - 1, 0, 0), BlockId::STONE);
+        world.set_block(IVec3::new(CHUNK_SIZE, 0, 0), BlockId::STONE);
+        let atlas = TextureAtlas::registered();
+        assert_eq!(
+            mesh_chunk(&world, IVec3::ZERO, &atlas).vertex_count(),
+            5 * 6
+        );
+        assert_eq!(mesh_chunk(&world, IVec3::X, &atlas).vertex_count(), 5 * 6);
+    }
+
+    #[test]
+    fn faces_wind_counter_clockwise_outwards() {
+        let mut world = World::new();
+        world.set_block(IVec3::ZERO, BlockId::STONE);
+        let mesh = mesh_chunk(&world, IVec3::ZERO, &TextureAtlas::registered());
+        for triangle in mesh.layer(RenderLayer::Opaque).chunks(3) {
+            assert_eq!(triangle_normal(triangle), triangle[0].normal);
+        }
+    }
+
+    #[test]
+    fn reports_blocks_missing_from_atlas() {
+        let mut world = World::new();
+        let color = BlockId::from_rgb([1, 2, 3]);
+        world.set_block(IVec3::ZERO, color);
+        let mesh = mesh_chunk(&world, IVec3::ZERO, &TextureAtlas::registered());
+        assert_eq!(mesh.vertex_count(), 0);
+        assert!(mesh.missing.contains(&color));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod atlas;
+pub mod mesher;
+pub mod models;
+pub mod world_mesh;
@@ -0,0 +1,625 @@
+use std::{collections::HashMap, fmt, sync::LazyLock};
+
+use glam::{IVec3, Mat3, Vec2, Vec3};
+use serde::Deserialize;
+
+use crate::{
+    meshing::mesher::FACES,
+    world::block::{Axis, BLOCKS, BlockId, Half, Shape},
+};
+
+#[derive(Debug)]
+pub enum ModelError {
+    Parse(serde_json::Error),
+    UnknownModel(String),
+    /// Faces name the block whose tile they show.
+    UnknownTexture(String),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::Parse(err) => write!(f, "invalid block model: {err}"),
+            ModelError::UnknownModel(name) => write!(f, "no block model named {name:?}"),
+            ModelError::UnknownTexture(name) => {
+                write!(f, "no block named {name:?} to texture with")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+/// A block model, in the JSON form of the files in `assets/models`: boxes given by two
+/// opposite corners in sixteenths of a block. Models face north and sit in the bottom half;
+/// the block's state turns them, see `orientation`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BlockModel {
+    pub elements: Vec<Element>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Element {
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+    #[serde(default)]
+    pub rotation: Option<ElementRotation>,
+    /// The faces to draw. Without them all six are, with the block's own tile stretched over
+    /// the block so each shows the part it covers, and hidden by an opaque neighbor on any
+    /// side of the block they lie on.
+    #[serde(default)]
+    pub faces: Option<HashMap<Side, ElementFace>>,
+}
+
+/// Turns a box around an axis through `origin`.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct ElementRotation {
+    /// In sixteenths of a block; the middle of the block by default.
+    #[serde(default = "block_middle")]
+    pub origin: [f32; 3],
+    #[serde(default)]
+    pub axis: RotationAxis,
+    /// Degrees, counter-clockwise seen from the positive end of the axis.
+    pub angle: f32,
+}
+
+fn block_middle() -> [f32; 3] {
+    [8.0; 3]
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotationAxis {
+    X,
+    #[default]
+    Y,
+    Z,
+}
+
+/// A side of a block, as a model names it; north is -Z.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Down,
+    Up,
+    North,
+    South,
+    West,
+    East,
+}
+
+impl Side {
+    pub fn normal(self) -> IVec3 {
+        match self {
+            Side::Down => IVec3::NEG_Y,
+            Side::Up => IVec3::Y,
+            Side::North => IVec3::NEG_Z,
+            Side::South => IVec3::Z,
+            Side::West => IVec3::NEG_X,
+            Side::East => IVec3::X,
+        }
+    }
+
+    /// The side an axis-aligned unit normal points to.
+    pub fn of(normal: IVec3) -> Self {
+        match normal.to_array() {
+            [0, -1, 0] => Side::Down,
+            [0, 1, 0] => Side::Up,
+            [0, 0, -1] => Side::North,
+            [0, 0, 1] => Side::South,
+            [-1, 0, 0] => Side::West,
+            _ => Side::East,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ElementFace {
+    /// Name of the block whose tile the face shows; the block's own by default.
+    #[serde(default)]
+    pub texture: Option<String>,
+    /// Part of the tile shown, `[u0, v0, u1, v1]` in sixteenths from its top left; the part
+    /// the face covers by default.
+    #[serde(default)]
+    pub uv: Option<[f32; 4]>,
+    /// Side whose opaque neighbor hides the face. Only honored where the face, turned with
+    /// the model, lies on that side of the block; a face inside the block can always be seen.
+    #[serde(default)]
+    pub cullface: Option<Side>,
+}
+
+/// One face of a baked model in block space, `0..1` on each axis, ready for the mesher.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BakedQuad {
+    /// Counter-clockwise seen from outside.
+    pub corners: [Vec3; 4],
+    /// Block whose tile the face shows.
+    pub texture: BlockId,
+    /// Where each corner falls within the tile, from `(0, 0)` at its top left.
+    pub uvs: [Vec2; 4],
+    pub normal: Vec3,
+    /// Side of the block where an opaque neighbor hides the face.
+    pub cull: Option<IVec3>,
+}
+
+impl BlockModel {
+    pub fn from_json(json: &str) -> Result<Self, ModelError> {
+        serde_json::from_str(json).map_err(ModelError::Parse)
+    }
+
+    /// The model's faces for `block`, turned to its state.
+    pub fn bake(&self, block: BlockId) -> Result<Vec<BakedQuad>, ModelError> {
+        let orientation = orientation(block);
+        let mut quads = Vec::new();
+        for element in &self.elements {
+            bake_element(element, block, orientation, &mut quads)?;
+        }
+        Ok(quads)
+    }
+
+    /// The model's boxes for `block`, turned to its state, as their lowest and highest corner
+    /// in block space. A box turned by other than quarter turns is replaced by its bounds.
+    pub fn boxes(&self, block: BlockId) -> Vec<[Vec3; 2]> {
+        let orientation = orientation(block);
+        self.elements
+            .iter()
+            .map(|element| {
+                let place = element_placement(element, orientation);
+                let from = Vec3::from(element.from) / 16.0;
+                let to = Vec3::from(element.to) / 16.0;
+                let corners = (0..8).map(|i| {
+                    let pick = Vec3::new((i & 1) as f32, (i >> 1 & 1) as f32, (i >> 2) as f32);
+                    place(from + (to - from) * pick)
+                });
+                corners.fold(
+                    [Vec3::INFINITY, Vec3::NEG_INFINITY],
+                    |[min, max], corner| [min.min(corner), max.max(corner)],
+                )
+            })
+            .collect()
+    }
+}
+
+const MODEL_FILES: [(&str, &str); 6] = [
+    ("cube", include_str!("../assets/models/cube.json")),
+    ("slab", include_str!("../assets/models/slab.json")),
+    ("stairs", include_str!("../assets/models/stairs.json")),
+    ("cross", include_str!("../assets/models/cross.json")),
+    ("torch", include_str!("../assets/models/torch.json")),
+    ("table", include_str!("../assets/models/table.json")),
+];
+
+static MODELS: LazyLock<HashMap<&str, BlockModel>> = LazyLock::new(|| {
+    MODEL_FILES
+        .iter()
+        .map(|(name, json)| {
+            let model = BlockModel::from_json(json)
+                .unwrap_or_else(|error| panic!("built-in {name} model: {error}"));
+            (*name, model)
+        })
+        .collect()
+});
+
+/// A built-in model by name.
+pub fn model(name: &str) -> Option<&'static BlockModel> {
+    MODELS.get(name)
+}
+
+/// Name of the built-in model a shape is drawn with.
+pub fn model_name(shape: Shape) -> &'static str {
+    match shape {
+        Shape::Cube => "cube",
+        Shape::Slab => "slab",
+        Shape::Stairs => "stairs",
+        Shape::Cross => "cross",
+        Shape::Model(name) => name,
+    }
+}
+
+const STATES: usize = (BlockId::STATE_MASK >> BlockId::STATE_SHIFT) as usize + 1;
+
+/// Every state of every registered block, baked once, by block and then state.
+static BAKED: LazyLock<Vec<Option<Vec<BakedQuad>>>> = LazyLock::new(|| {
+    (0..BLOCKS.len() * STATES)
+        .map(|index| {
+            let block =
+                BlockId((((index % STATES) << BlockId::STATE_SHIFT) | (index / STATES)) as u16);
+            bake(block).unwrap_or_else(|error| panic!("{}: {error}", block.properties().name))
+        })
+        .collect()
+});
+
+/// Bakes the block's model turned to its state, or returns `None` for blocks the mesher draws
+/// as plain cubes.
+pub fn bake(block: BlockId) -> Result<Option<Vec<BakedQuad>>, ModelError> {
+    let shape = block.shape();
+    if shape == Shape::Cube && orientation(block) == Mat3::IDENTITY {
+        return Ok(None);
+    }
+    let name = model_name(shape);
+    let model = model(name).ok_or_else(|| ModelError::UnknownModel(name.to_string()))?;
+    model.bake(block).map(Some)
+}
+
+/// Every state of every registered block's collision boxes, by block and then state.
+static COLLIDERS: LazyLock<Vec<Vec<[Vec3; 2]>>> = LazyLock::new(|| {
+    (0..BLOCKS.len() * STATES)
+        .map(|index| {
+            let block =
+                BlockId((((index % STATES) << BlockId::STATE_SHIFT) | (index / STATES)) as u16);
+            if !block.is_solid() {
+                return Vec::new();
+            }
+            let name = model_name(block.shape());
+            let model = model(name).unwrap_or_else(|| panic!("unknown model {name:?}"));
+            model.boxes(block)
+        })
+        .collect()
+});
+
+/// The boxes a block collides with, in block space: its model's boxes turned to its state,
+/// or none for blocks that aren't solid.
+pub fn colliders(block: BlockId) -> &'static [[Vec3; 2]] {
+    if block.is_color_block() {
+        return &[[Vec3::ZERO, Vec3::ONE]];
+    }
+    let index = block.base().0 as usize * STATES + block.state() as usize;
+    COLLIDERS.get(index).map_or(&[], Vec::as_slice)
+}
+
+/// Like `bake`, from the faces baked for every registered block up front.
+pub fn baked(block: BlockId) -> Option<&'static [BakedQuad]> {
+    if block.is_color_block() {
+        return None;
+    }
+    let index = block.base().0 as usize * STATES + block.state() as usize;
+    BAKED.get(index)?.as_deref()
+}
+
+/// The tiles a block is drawn with: its own, any its model's faces borrow, and water's when
+/// it stands in water.
+pub fn textures(block: BlockId) -> impl Iterator<Item = BlockId> {
+    let borrowed = baked(block).into_iter().flatten().map(|quad| quad.texture);
+    let water = block.is_waterlogged().then_some(BlockId::WATER);
+    std::iter::once(block.base()).chain(borrowed).chain(water)
+}
+
+/// How a block's state turns its model: upside down for the top half, then around the
+/// vertical to its facing, or on its side along its axis. Quarter turns only, so the
+/// matrix is exact.
+fn orientation(block: BlockId) -> Mat3 {
+    let mut orientation = Mat3::IDENTITY;
+    if block.half() == Some(Half::Top) {
+        // Half a turn around Z rather than a mirror, which keeps the winding and the back
+        // of stairs where it was.
+        orientation = Mat3::from_cols(Vec3::NEG_X, Vec3::NEG_Y, Vec3::Z);
+    }
+    if let Some(facing) = block.facing() {
+        let clockwise = Mat3::from_cols(Vec3::Z, Vec3::Y, Vec3::NEG_X);
+        for _ in 0..facing.quarter_turns() {
+            orientation = clockwise * orientation;
+        }
+    }
+    match block.axis() {
+        Some(Axis::X) => Mat3::from_cols(Vec3::NEG_Y, Vec3::X, Vec3::Z) * orientation,
+        Some(Axis::Z) => Mat3::from_cols(Vec3::X, Vec3::Z, Vec3::NEG_Y) * orientation,
+        _ => orientation,
+    }
+}
+
+/// The element's own rotation, and the point in block space it turns around.
+fn element_rotation(element: &Element) -> (Mat3, Vec3) {
+    element
+        .rotation
+        .map_or((Mat3::IDENTITY, Vec3::splat(0.5)), |rotation| {
+            let axis = match rotation.axis {
+                RotationAxis::X => Vec3::X,
+                RotationAxis::Y => Vec3::Y,
+                RotationAxis::Z => Vec3::Z,
+            };
+            let turn = Mat3::from_axis_angle(axis, rotation.angle.to_radians());
+            (turn, Vec3::from(rotation.origin) / 16.0)
+        })
+}
+
+/// Where a point of the element ends up in block space: turned by the element's rotation,
+/// then by the block's `orientation` around the middle of the block.
+fn element_placement(element: &Element, orientation: Mat3) -> impl Fn(Vec3) -> Vec3 {
+    let center = Vec3::splat(0.5);
+    let (rotation, origin) = element_rotation(element);
+    move |corner| orientation * (rotation * (corner - origin) + origin - center) + center
+}
+
+fn bake_element(
+    element: &Element,
+    block: BlockId,
+    orientation: Mat3,
+    quads: &mut Vec<BakedQuad>,
+) -> Result<(), ModelError> {
+    let center = Vec3::splat(0.5);
+    let from = Vec3::from(element.from) / 16.0;
+    let to = Vec3::from(element.to) / 16.0;
+    let size = to - from;
+    let rotation = element_rotation(element).0;
+    let place = element_placement(element, orientation);
+
+    for face in &FACES {
+        if size.dot(face.u) == 0.0 || size.dot(face.v) == 0.0 {
+            continue;
+        }
+        let side = Side::of(face.normal);
+        let listed = match &element.faces {
+            Some(faces) => match faces.get(&side) {
+                Some(listed) => Some(listed),
+                None => continue,
+            },
+            None => None,
+        };
+        // Unless its faces say otherwise, a box flat along an axis is a single plane, seen
+        // from both sides.
+        let flat = size.dot(face.normal.as_vec3().abs()) == 0.0;
+        if listed.is_none() && flat && face.normal.element_sum() < 0 {
+            continue;
+        }
+
+        let texture = match listed.and_then(|listed| listed.texture.as_ref()) {
+            Some(name) => BlockId::find_by_name(name)
+                .ok_or_else(|| ModelError::UnknownTexture(name.clone()))?,
+            None => block.base(),
+        };
+        let [u0, v0, u1, v1] = match listed.and_then(|listed| listed.uv) {
+            Some(uv) => uv.map(|value| value / 16.0),
+            None => [
+                from.dot(face.u),
+                1.0 - to.dot(face.v),
+                to.dot(face.u),
+                1.0 - from.dot(face.v),
+            ],
+        };
+        let cull_side = match listed {
+            Some(listed) => listed.cullface,
+            None => Some(side),
+        };
+
+        let offsets = [Vec3::ZERO, face.u, face.u + face.v, face.v];
+        let corners = offsets.map(|offset| place(from + (face.base + offset) * size));
+        let uvs = offsets.map(|offset| {
+            let (s, t) = (offset.dot(face.u), offset.dot(face.v));
+            Vec2::new(u0 + (u1 - u0) * s, v1 + (v0 - v1) * t)
+        });
+        let cull = cull_side
+            .map(|side| (orientation * side.normal().as_vec3()).round().as_ivec3())
+            .filter(|side| {
+                let side = side.as_vec3();
+                corners
+                    .iter()
+                    .all(|corner| ((*corner - center).dot(side) - 0.5).abs() < 1e-5)
+            });
+        quads.push(BakedQuad {
+            corners,
+            texture,
+            uvs,
+            normal: orientation * rotation * face.normal.as_vec3(),
+            cull,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::Facing;
+
+    fn culled_sides(block: BlockId) -> Vec<IVec3> {
+        baked(block)
+            .unwrap()
+            .iter()
+            .filter_map(|quad| quad.cull)
+            .collect()
+    }
+
+    #[test]
+    fn every_block_state_bakes() {
+        for index in 0..BLOCKS.len() as u16 {
+            for state in 0..STATES as u8 {
+                let block = BlockId(index).with_state(state);
+                assert_eq!(baked(block), bake(block).unwrap().as_deref(), "{block:?}");
+            }
+        }
+        assert_eq!(baked(BlockId::STONE), None);
+        assert_eq!(baked(BlockId::from_rgb([10, 20, 30])), None);
+    }
+
+    #[test]
+    fn slabs_sit_in_their_half() {
+        let bottom = baked(BlockId::STONE_SLAB).unwrap();
+        assert_eq!(bottom.len(), 6);
+        assert!(
+            bottom
+                .iter()
+                .all(|quad| quad.corners.iter().all(|c| c.y <= 0.5))
+        );
+        // The top face is halfway up, not on the block's side.
+        assert_eq!(culled_sides(BlockId::STONE_SLAB).len(), 5);
+        assert!(!culled_sides(BlockId::STONE_SLAB).contains(&IVec3::Y));
+
+        let top = baked(BlockId::STONE_SLAB.with_half(Half::Top)).unwrap();
+        assert!(
+            top.iter()
+                .all(|quad| quad.corners.iter().all(|c| c.y >= 0.5))
+        );
+        assert!(!culled_sides(BlockId::STONE_SLAB.with_half(Half::Top)).contains(&IVec3::NEG_Y));
+        assert_eq!(baked(BlockId::STONE_SLAB.with_half(Half::Double)), None);
+    }
+
+    /// Lowest and highest corner of the faces pointing along `normal`, on the block's side or
+    /// off it.
+    fn extent(quads: &[BakedQuad], normal: IVec3, on_side: bool) -> (f32, f32) {
+        quads
+            .iter()
+            .filter(|quad| quad.normal == normal.as_vec3() && quad.cull.is_some() == on_side)
+            .flat_map(|quad| quad.corners)
+            .fold((1.0, 0.0), |(low, high), corner| {
+                (corner.y.min(low), corner.y.max(high))
+            })
+    }
+
+    #[test]
+    fn stairs_step_up_towards_their_facing() {
+        for facing in Facing::ALL {
+            for half in [Half::Bottom, Half::Top] {
+                let stairs = BlockId::STONE_STAIRS.with_facing(facing).with_half(half);
+                let quads = baked(stairs).unwrap();
+                // The back, the way the stairs face, is closed all the way up, the front only
+                // as far as the step below.
+                assert_eq!(extent(quads, facing.normal(), true), (0.0, 1.0));
+                let front = extent(quads, -facing.normal(), true);
+                let expected = if half == Half::Top {
+                    (0.5, 1.0)
+                } else {
+                    (0.0, 0.5)
+                };
+                assert_eq!(front, expected, "{facing:?} {half:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn stairs_collide_with_a_slab_and_a_step() {
+        let stairs = BlockId::STONE_STAIRS.with_facing(Facing::East);
+        let boxes: Vec<_> = colliders(stairs)
+            .iter()
+            .map(|corners| corners.map(|corner| (corner * 2.0).round() / 2.0))
+            .collect();
+        assert_eq!(
+            boxes,
+            [
+                [Vec3::ZERO, Vec3::new(1.0, 0.5, 1.0)],
+                [Vec3::new(0.5, 0.5, 0.0), Vec3::ONE],
+            ]
+        );
+        assert!(colliders(BlockId::TORCH).is_empty());
+    }
+
+    #[test]
+    fn logs_lie_along_their_axis() {
+        assert_eq!(baked(BlockId::LOG), None);
+        let log = baked(BlockId::LOG.with_axis(Axis::X)).unwrap();
+        assert_eq!(log.len(), 6);
+        assert_eq!(culled_sides(BlockId::LOG.with_axis(Axis::X)).len(), 6);
+        // The tile turns with the log: down the tile is along X on its top.
+        let top = log.iter().find(|quad| quad.normal == Vec3::Y).unwrap();
+        for (a, b) in [(0, 1), (1, 2), (2, 3), (3, 0)] {
+            let along_x = top.corners[a].x != top.corners[b].x;
+            assert_eq!(top.uvs[a].y != top.uvs[b].y, along_x);
+        }
+    }
+
+    #[test]
+    fn crosses_are_two_diagonal_planes() {
+        let quads = baked(BlockId::WHEAT).unwrap();
+        assert_eq!(quads.len(), 2);
+        for quad in quads {
+            assert_eq!(quad.cull, None);
+            assert!((quad.normal.x.abs() - quad.normal.z.abs()).abs() < 1e-6);
+            for corner in quad.corners {
+                assert!(corner.cmpge(Vec3::ZERO).all() && corner.cmple(Vec3::ONE).all());
+            }
+        }
+    }
+
+    #[test]
+    fn faces_take_their_texture_uvs_and_cull_hints() {
+        let model = BlockModel::from_json(
+            r#"{ "elements": [{
+                "from": [0, 0, 0], "to": [16, 4, 16],
+                "faces": {
+                    "up": { "texture": "log", "uv": [0, 0, 8, 16], "cullface": "up" },
+                    "down": { "cullface": "down" },
+                    "east": { "texture": "glass", "cullface": "east" }
+                }
+            }] }"#,
+        )
+        .unwrap();
+        let quads = model.bake(BlockId::STONE).unwrap();
+        assert_eq!(quads.len(), 3);
+
+        let up = quads.iter().find(|quad| quad.normal == Vec3::Y).unwrap();
+        assert_eq!(up.texture, BlockId::LOG);
+        assert_eq!(up.uvs[0], Vec2::new(0.0, 1.0));
+        assert_eq!(up.uvs[2], Vec2::new(0.5, 0.0));
+        // A quarter of the way up is inside the block, whatever the hint says.
+        assert_eq!(up.cull, None);
+
+        let down = quads
+            .iter()
+            .find(|quad| quad.normal == Vec3::NEG_Y)
+            .unwrap();
+        assert_eq!(
+            (down.texture, down.cull),
+            (BlockId::STONE, Some(IVec3::NEG_Y))
+        );
+        let east = quads.iter().find(|quad| quad.normal == Vec3::X).unwrap();
+        assert_eq!((east.texture, east.cull), (BlockId::GLASS, Some(IVec3::X)));
+        // Without a rect of its own it shows the strip of the tile it covers.
+        assert!(east.uvs.iter().all(|uv| uv.x <= 0.25));
+    }
+
+    #[test]
+    fn listed_faces_without_a_hint_always_show() {
+        let model = BlockModel::from_json(
+            r#"{ "elements": [{ "from": [0, 0, 0], "to": [16, 16, 16], "faces": { "north": {} } }] }"#,
+        )
+        .unwrap();
+        let quads = model.bake(BlockId::STONE).unwrap();
+        assert_eq!(quads.len(), 1);
+        assert_eq!((quads[0].normal, quads[0].cull), (Vec3::NEG_Z, None));
+    }
+
+    #[test]
+    fn hints_turn_with_the_block() {
+        let model = BlockModel::from_json(
+            r#"{ "elements": [{ "from": [0, 0, 0], "to": [16, 16, 2], "faces": { "north": { "cullface": "north" } } }] }"#,
+        )
+        .unwrap();
+        let east = BlockId::STONE_STAIRS.with_facing(Facing::East);
+        assert_eq!(model.bake(east).unwrap()[0].cull, Some(IVec3::X));
+    }
+
+    #[test]
+    fn boxes_turn_around_their_origin() {
+        let model = BlockModel::from_json(
+            r#"{ "elements": [{
+                "from": [0, 0, 0], "to": [16, 2, 16],
+                "rotation": { "origin": [0, 0, 0], "axis": "z", "angle": 90 }
+            }] }"#,
+        )
+        .unwrap();
+        // Stood up around its bottom west edge, so it leans out of the west side, and only its
+        // ends still lie on sides of the block.
+        for quad in model.bake(BlockId::STONE).unwrap() {
+            let end = quad.normal.z.abs() > 0.5;
+            assert_eq!(quad.cull.is_some(), end, "{quad:?}");
+            for corner in quad.corners {
+                assert!((-0.125 - 1e-5..1e-5).contains(&corner.x), "{corner}");
+            }
+        }
+    }
+
+    #[test]
+    fn reports_bad_models() {
+        assert!(matches!(
+            BlockModel::from_json(r#"{ "elements": [{ "from": [0, 0] }] }"#),
+            Err(ModelError::Parse(_))
+        ));
+        let model = BlockModel::from_json(
+            r#"{ "elements": [{ "from": [0, 0, 0], "to": [1, 1, 1], "faces": { "up": { "texture": "cheese" } } }] }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            model.bake(BlockId::STONE),
+            Err(ModelError::UnknownTexture(name)) if name == "cheese"
+        ));
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashSet},
+    ops::Range,
+};
+
+use glam::{IVec3, Vec3, Vec3A};
+
+use crate::{
+    graphics::structures::VertexBuffer,
+    meshing::{
+        atlas::TextureAtlas,
+        mesher::{QUAD_TRIANGLES, mesh_chunk},
+    },
+    world::{block::RenderLayer, chunk::CHUNK_SIZE, world::World},
+};
+
+type ChunkLayers = [Vec<VertexBuffer>; RenderLayer::ALL.len()];
+
+/// Meshes of every chunk in a world, kept up to date from its remesh set.
+///
+/// Translucent faces only blend correctly drawn back to front, so they are kept sorted by
+/// distance from the camera: within each chunk by face, and chunks among each other.
+#[derive(Debug)]
+pub struct WorldMesh {
+    pub atlas: TextureAtlas,
+    chunks: BTreeMap<[i32; 3], ChunkLayers>,
+    /// Camera position the translucent faces were last sorted for.
+    sorted_from: Vec3,
+    /// Block that position was in; the order is only refreshed once the camera leaves it.
+    sorted_block: Option<IVec3>,
+}
+
+impl WorldMesh {
+    pub fn new(world: &mut World) -> Self {
+        let mut mesh = WorldMesh {
+            atlas: TextureAtlas::registered(),
+            chunks: BTreeMap::new(),
+            sorted_from: Vec3::ZERO,
+            sorted_block: None,
+        };
+        world.take_remesh_chunks();
+        let all: HashSet<IVec3> = world.chunks().map(|(pos, _)| *pos).collect();
+        mesh.remesh(world, &all);
+        mesh
+    }
+
+    /// Rebuilds the chunks queued in the world. Returns true if the atlas had to grow, which
+    /// invalidates every UV and so remeshes everything.
+    pub fn update(&mut self, world: &mut World) -> bool {
+        let queued = world.take_remesh_chunks();
+        if queued.is_empty() {
+            return false;
+        }
+        self.remesh(world, &queued)
+    }
+
+    fn remesh(&mut self, world: &World, chunk_positions: &HashSet<IVec3>) -> bool {
+        let mut missing = HashSet::new();
+        for chunk_pos in chunk_positions {
+            let mesh = mesh_chunk(world, *chunk_pos, &self.atlas);
+            missing.extend(mesh.missing.iter().copied());
+            if mesh.vertex_count() == 0 {
+                self.chunks.remove(&chunk_pos.to_array());
+            } else {
+                let mut layers = mesh.layers;
+                sort_back_to_front(
+                    &mut layers[RenderLayer::Translucent as usize],
+                    self.sorted_from,
+                );
+                self.chunks.insert(chunk_pos.to_array(), layers);
+            }
+        }
+
+        if missing.is_empty() {
+            return false;
+        }
+        self.atlas = TextureAtlas::new(self.atlas.blocks().chain(missing));
+        let all: HashSet<IVec3> = world.chunks().map(|(pos, _)| *pos).collect();
+        self.remesh(world, &all);
+        true
+    }
+
+    /// Re-sorts the translucent faces for a camera at `camera`, if it moved into another
+    /// block since the last sort. Returns true if the order may have changed.
+    pub fn sort_translucent(&mut self, camera: Vec3) -> bool {
+        let block = camera.floor().as_ivec3();
+        if self.sorted_block == Some(block) {
+            return false;
+        }
+        self.sorted_block = Some(block);
+        self.sorted_from = camera;
+        for layers in self.chunks.values_mut() {
+            sort_back_to_front(&mut layers[RenderLayer::Translucent as usize], camera);
+        }
+        true
+    }
+
+    pub fn chunk(&self, chunk_pos: IVec3, layer: RenderLayer) -> Option<&[VertexBuffer]> {
+        self.chunks
+            .get(&chunk_pos.to_array())
+            .map(|layers| layers[layer as usize].as_slice())
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.chunks.values().flatten().map(Vec::len).sum()
+    }
+
+    /// Ranges of each layer in `vertices`, indexed by `RenderLayer as usize`.
+    pub fn layer_ranges(&self) -> [Range<u32>; RenderLayer::ALL.len()] {
+        let mut start = 0;
+        RenderLayer::ALL.map(|layer| {
+            let count: usize = self
+                .chunks
+                .values()
+                .map(|layers| layers[layer as usize].len())
+                .sum();
+            let range = start..start + count as u32;
+            start = range.end;
+            range
+        })
+    }
+
+    /// All chunk meshes concatenated layer by layer: chunks in a stable order, except for
+    /// the translucent ones, which come farthest first.
+    pub fn vertices(&self) -> Vec<VertexBuffer> {
+        let stable = RenderLayer::ALL
+            .iter()
+            .filter(|layer| **layer != RenderLayer::Translucent);
+        let mut vertices: Vec<VertexBuffer> = stable
+            .flat_map(|layer| self.chunks.values().map(|layers| &layers[*layer as usize]))
+            .flatten()
+            .copied()
+            .collect();
+        vertices.extend(self.translucent_vertices());
+        vertices
+    }
+
+    /// The translucent layer on its own, as it is laid out at the end of `vertices`.
+    pub fn translucent_vertices(&self) -> Vec<VertexBuffer> {
+        let mut chunks: Vec<(f32, &Vec<VertexBuffer>)> = self
+            .chunks
+            .iter()
+            .map(|(pos, layers)| {
+                let center =
+                    (IVec3::from_array(*pos) * CHUNK_SIZE).as_vec3() + CHUNK_SIZE as f32 * 0.5;
+                let distance = center.distance_squared(self.sorted_from);
+                (distance, &layers[RenderLayer::Translucent as usize])
+            })
+            .filter(|(_, vertices)| !vertices.is_empty())
+            .collect();
+        chunks.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+        chunks
+            .into_iter()
+            .flat_map(|(_, vertices)| vertices)
+            .copied()
+            .collect()
+    }
+}
+
+/// Orders the quads of a triangle list by the distance of their centers from `camera`,
+/// farthest first.
+fn sort_back_to_front(vertices: &mut Vec<VertexBuffer>, camera: Vec3) {
+    let quad = QUAD_TRIANGLES.len();
+    let camera = Vec3A::from(camera);
+    let mut quads: Vec<(f32, &[VertexBuffer])> = vertices
+        .chunks(quad)
+        .map(|corners| {
+            let center =
+                corners.iter().map(|vertex| vertex.position).sum::<Vec3A>() / corners.len() as f32;
+            (center.distance_squared(camera), corners)
+        })
+        .collect();
+    quads.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+    *vertices = quads
+        .into_iter()
+        .flat_map(|(_, corners)| corners)
+        .copied()
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::BlockId;
+
+    fn quad_distances(vertices: &[VertexBuffer], camera: Vec3) -> Vec<f32> {
+        vertices
+            .chunks(QUAD_TRIANGLES.len())
+            .map(|corners| {
+                let center = corners.iter().map(|vertex| vertex.position).sum::<Vec3A>() / 6.0;
+                center.distance(camera.into())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn translucent_faces_are_drawn_last_and_back_to_front() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(0, 0, 0), BlockId::STONE);
+        for x in 2..6 {
+            world.set_block(IVec3::new(x, 0, 0), BlockId::GLASS);
+        }
+        let mut mesh = WorldMesh::new(&mut world);
+        assert!(mesh.sort_translucent(Vec3::new(-4.0, 0.5, 0.5)));
+
+        let ranges = mesh.layer_ranges();
+        assert_eq!(ranges[RenderLayer::Opaque as usize], 0..36);
+        // The row of glass only shows its outside.
+        assert_eq!(ranges[RenderLayer::Translucent as usize].len(), 18 * 6);
+        let translucent =
+            &mesh.vertices()[ranges[RenderLayer::Translucent as usize].start as usize..];
+        let distances = quad_distances(translucent, Vec3::new(-4.0, 0.5, 0.5));
+        assert!(distances.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn resorts_only_after_crossing_a_block_boundary() {
+        let mut world = World::new();
+        world.set_block(IVec3::ZERO, BlockId::WATER);
+        let mut mesh = WorldMesh::new(&mut world);
+        assert!(mesh.sort_translucent(Vec3::new(3.2, 0.5, 0.5)));
+        assert!(!mesh.sort_translucent(Vec3::new(3.9, 0.1, 0.7)));
+        assert!(mesh.sort_translucent(Vec3::new(-3.2, 0.5, 0.5)));
+
+        let distances = quad_distances(&mesh.translucent_vertices(), Vec3::new(-3.2, 0.5, 0.5));
+        assert!(distances.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+}
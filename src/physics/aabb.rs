@@ -0,0 +1,112 @@
+use glam::{IVec3, Vec3};
+
+use crate::{
+    meshing::models,
+    world::{block::BlockId, world::World},
+};
+
+const EPSILON: f32 = 1e-4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    /// Box standing on `feet`, centered on it horizontally.
+    pub fn from_feet(feet: Vec3, width: f32, height: f32) -> Self {
+        let half = width * 0.5;
+        Aabb {
+            min: feet - Vec3::new(half, 0.0, half),
+            max: feet + Vec3::new(half, height, half),
+        }
+    }
+
+    /// Boxes `block` collides with when it is at `pos`, see `models::colliders`.
+    pub fn block(pos: IVec3, block: BlockId) -> impl Iterator<Item = Aabb> {
+        let origin = pos.as_vec3();
+        models::colliders(block)
+            .iter()
+            .map(move |[min, max]| Aabb::new(origin + *min, origin + *max))
+    }
+
+    pub fn translated(&self, offset: Vec3) -> Self {
+        Aabb {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+
+    /// Grows the box towards `delta`, covering everything it sweeps through.
+    pub fn swept(&self, delta: Vec3) -> Self {
+        Aabb {
+            min: self.min + delta.min(Vec3::ZERO),
+            max: self.max + delta.max(Vec3::ZERO),
+        }
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmplt(other.max).all() && self.max.cmpgt(other.min).all()
+    }
+
+    /// Inclusive range of voxel coordinates the box overlaps.
+    pub fn block_range(&self) -> (IVec3, IVec3) {
+        (
+            (self.min + EPSILON).floor().as_ivec3(),
+            (self.max - EPSILON).floor().as_ivec3(),
+        )
+    }
+
+    /// Clips `delta` along `axis` so that this box stops at the face of `other`.
+    fn clip_axis(&self, other: &Aabb, axis: usize, delta: f32) -> f32 {
+        for other_axis in 0..3 {
+            if other_axis != axis
+                && (self.max[other_axis] <= other.min[other_axis] + EPSILON
+                    || self.min[other_axis] >= other.max[other_axis] - EPSILON)
+            {
+                return delta;
+            }
+        }
+
+        if delta > 0.0 && self.max[axis] <= other.min[axis] + EPSILON {
+            delta.min(other.min[axis] - self.max[axis])
+        } else if delta < 0.0 && self.min[axis] >= other.max[axis] - EPSILON {
+            delta.max(other.max[axis] - self.min[axis])
+        } else {
+            delta
+        }
+    }
+
+    /// Moves the box through `world` one axis at a time (Y, then X, then Z) and returns the
+    /// motion that was actually applied.
+    pub fn move_through(&mut self, world: &World, motion: Vec3) -> Vec3 {
+        let (min, max) = self.swept(motion).block_range();
+        let mut colliders = Vec::new();
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    let pos = IVec3::new(x, y, z);
+                    colliders.extend(Aabb::block(pos, world.get_block(pos)));
+                }
+            }
+        }
+
+        let mut applied = Vec3::ZERO;
+        for axis in [1, 0, 2] {
+            let mut delta = motion[axis];
+            for collider in &colliders {
+                delta = self.clip_axis(collider, axis, delta);
+            }
+            let mut offset = Vec3::ZERO;
+            offset[axis] = delta;
+            *self = self.translated(offset);
+            applied[axis] = delta;
+        }
+        applied
+    }
+}
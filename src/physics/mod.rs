@@ -0,0 +1,3 @@
+pub mod aabb;
+pub mod player;
+pub mod raycast;
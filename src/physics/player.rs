@@ -0,0 +1,325 @@
+use glam::Vec3;
+
+use crate::{physics::aabb::Aabb, world::world::World};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MovementMode {
+    Walking,
+    Flying,
+}
+
+/// Desired movement for one update, in world space.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlayerInput {
+    /// Horizontal wish direction; normalized by the controller.
+    pub direction: Vec3,
+    /// -1.0..=1.0, only used while flying.
+    pub vertical: f32,
+    pub jump: bool,
+    pub sprint: bool,
+}
+
+#[derive(Debug)]
+pub struct Player {
+    /// Center of the bottom face of the body.
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub on_ground: bool,
+    pub mode: MovementMode,
+
+    pub width: f32,
+    pub height: f32,
+    pub eye_height: f32,
+    pub step_height: f32,
+    pub walk_speed: f32,
+    pub fly_speed: f32,
+    pub sprint_multiplier: f32,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    pub terminal_velocity: f32,
+}
+
+impl Player {
+    pub fn new(position: Vec3) -> Self {
+        Player {
+            position,
+            velocity: Vec3::ZERO,
+            on_ground: false,
+            mode: MovementMode::Walking,
+            width: 0.6,
+            height: 1.8,
+            eye_height: 1.62,
+            step_height: 1.0,
+            walk_speed: 4.3,
+            fly_speed: 10.0,
+            sprint_multiplier: 1.6,
+            jump_speed: 8.5,
+            gravity: 28.0,
+            terminal_velocity: 60.0,
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_feet(self.position, self.width, self.height)
+    }
+
+    pub fn eye_position(&self) -> Vec3 {
+        self.position + Vec3::Y * self.eye_height
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            MovementMode::Walking => MovementMode::Flying,
+            MovementMode::Flying => MovementMode::Walking,
+        };
+        self.velocity = Vec3::ZERO;
+        self.on_ground = false;
+    }
+
+    /// Advances the body by one simulation step of `delta_time` seconds.
+    pub fn tick(&mut self, world: &World, input: &PlayerInput, delta_time: f32) {
+        let direction = Vec3::new(input.direction.x, 0.0, input.direction.z).normalize_or_zero();
+        let sprint = if input.sprint {
+            self.sprint_multiplier
+        } else {
+            1.0
+        };
+
+        match self.mode {
+            MovementMode::Walking => {
+                let horizontal = direction * self.walk_speed * sprint;
+                self.velocity.x = horizontal.x;
+                self.velocity.z = horizontal.z;
+                if input.jump && self.on_ground {
+                    self.velocity.y = self.jump_speed;
+                }
+                self.velocity.y =
+                    (self.velocity.y - self.gravity * delta_time).max(-self.terminal_velocity);
+            }
+            MovementMode::Flying => {
+                self.velocity = (direction + Vec3::Y * input.vertical.clamp(-1.0, 1.0))
+                    * self.fly_speed
+                    * sprint;
+            }
+        }
+
+        let motion = self.velocity * delta_time;
+        let mut aabb = self.aabb();
+        let mut applied = aabb.move_through(world, motion);
+
+        let blocked_horizontally = applied.x != motion.x || applied.z != motion.z;
+        if self.mode == MovementMode::Walking && self.on_ground && blocked_horizontally {
+            let mut stepped = self.aabb();
+            let up = stepped.move_through(world, Vec3::Y * self.step_height).y;
+            let across = stepped.move_through(world, Vec3::new(motion.x, 0.0, motion.z));
+            let down = stepped
+                .move_through(world, Vec3::Y * (motion.y.min(0.0) - up))
+                .y;
+
+            let stepped_distance = across.x * across.x + across.z * across.z;
+            if stepped_distance > applied.x * applied.x + applied.z * applied.z {
+                aabb = stepped;
+                applied = Vec3::new(across.x, up + down, across.z);
+            }
+        }
+
+        self.on_ground = motion.y < 0.0 && applied.y > motion.y;
+        if applied.x != motion.x {
+            self.velocity.x = 0.0;
+        }
+        if applied.y != motion.y {
+            self.velocity.y = 0.0;
+        }
+        if applied.z != motion.z {
+            self.velocity.z = 0.0;
+        }
+
+        self.position = Vec3::new(
+            (aabb.min.x + aabb.max.x) * 0.5,
+            aabb.min.y,
+            (aabb.min.z + aabb.max.z) * 0.5,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec3;
+
+    use super::*;
+    use crate::world::block::{BlockId, Half};
+
+    const TICK: f32 = 1.0 / 60.0;
+
+    fn floor() -> World {
+        let mut world = World::new();
+        for x in -8..8 {
+            for z in -8..8 {
+                world.set_block(IVec3::new(x, -1, z), BlockId::STONE);
+            }
+        }
+        world
+    }
+
+    fn run(player: &mut Player, world: &World, input: PlayerInput, ticks: u32) {
+        for _ in 0..ticks {
+            player.tick(world, &input, TICK);
+        }
+    }
+
+    #[test]
+    fn falls_and_lands_on_floor() {
+        let world = floor();
+        let mut player = Player::new(Vec3::new(0.5, 5.0, 0.5));
+        run(&mut player, &world, PlayerInput::default(), 120);
+
+        assert!(player.on_ground);
+        assert!(player.position.y.abs() < 1e-3, "{}", player.position.y);
+        assert_eq!(player.velocity.y, 0.0);
+    }
+
+    #[test]
+    fn jump_leaves_ground_and_returns() {
+        let world = floor();
+        let mut player = Player::new(Vec3::new(0.5, 0.0, 0.5));
+        run(&mut player, &world, PlayerInput::default(), 2);
+        assert!(player.on_ground);
+
+        let jump = PlayerInput {
+            jump: true,
+            ..Default::default()
+        };
+        run(&mut player, &world, jump, 1);
+        assert!(!player.on_ground);
+
+        let mut peak: f32 = 0.0;
+        for _ in 0..120 {
+            player.tick(&world, &PlayerInput::default(), TICK);
+            peak = peak.max(player.position.y);
+        }
+        assert!(peak > 1.0 && peak < 1.5, "{peak}");
+        assert!(player.on_ground);
+    }
+
+    #[test]
+    fn wall_blocks_movement() {
+        let mut world = floor();
+        for y in 0..3 {
+            for z in -8..8 {
+                world.set_block(IVec3::new(3, y, z), BlockId::STONE);
+            }
+        }
+        let mut player = Player::new(Vec3::new(0.5, 0.0, 0.5));
+        let walk = PlayerInput {
+            direction: Vec3::X,
+            ..Default::default()
+        };
+        run(&mut player, &world, walk, 120);
+
+        assert!((player.position.x - (3.0 - player.width * 0.5)).abs() < 1e-3);
+        assert!(player.position.y.abs() < 1e-3);
+    }
+
+    #[test]
+    fn steps_up_single_block() {
+        let mut world = floor();
+        for x in 3..8 {
+            for z in -8..8 {
+                world.set_block(IVec3::new(x, 0, z), BlockId::STONE);
+            }
+        }
+        let mut player = Player::new(Vec3::new(0.5, 0.0, 0.5));
+        let walk = PlayerInput {
+            direction: Vec3::X,
+            ..Default::default()
+        };
+        run(&mut player, &world, walk, 60);
+
+        assert!(player.position.x > 3.5, "{}", player.position.x);
+        assert!(
+            (player.position.y - 1.0).abs() < 1e-3,
+            "{}",
+            player.position.y
+        );
+    }
+
+    #[test]
+    fn walks_onto_a_bottom_slab() {
+        let mut world = floor();
+        for x in 3..8 {
+            for z in -8..8 {
+                world.set_block(IVec3::new(x, 0, z), BlockId::STONE_SLAB);
+            }
+        }
+        let mut player = Player::new(Vec3::new(0.5, 0.0, 0.5));
+        let walk = PlayerInput {
+            direction: Vec3::X,
+            ..Default::default()
+        };
+        run(&mut player, &world, walk, 60);
+
+        assert!(player.position.x > 3.5, "{}", player.position.x);
+        assert!(
+            (player.position.y - 0.5).abs() < 1e-3,
+            "{}",
+            player.position.y
+        );
+    }
+
+    #[test]
+    fn ceiling_stops_jump() {
+        let mut world = floor();
+        world.set_block(IVec3::new(0, 2, 0), BlockId::STONE);
+        let mut player = Player::new(Vec3::new(0.5, 0.0, 0.5));
+        run(&mut player, &world, PlayerInput::default(), 2);
+
+        let jump = PlayerInput {
+            jump: true,
+            ..Default::default()
+        };
+        let mut peak: f32 = 0.0;
+        for _ in 0..60 {
+            player.tick(&world, &jump, TICK);
+            peak = peak.max(player.position.y);
+        }
+        assert!(peak <= 2.0 - player.height + 1e-3, "{peak}");
+    }
+
+    #[test]
+    fn top_slabs_leave_their_bottom_half_open() {
+        let mut world = floor();
+        world.set_block(
+            IVec3::new(0, 2, 0),
+            BlockId::STONE_SLAB.with_half(Half::Top),
+        );
+        let mut player = Player::new(Vec3::new(0.5, 0.0, 0.5));
+        run(&mut player, &world, PlayerInput::default(), 2);
+
+        let jump = PlayerInput {
+            jump: true,
+            ..Default::default()
+        };
+        let mut peak: f32 = 0.0;
+        for _ in 0..60 {
+            player.tick(&world, &jump, TICK);
+            peak = peak.max(player.position.y);
+        }
+        assert!((peak - (2.5 - player.height)).abs() < 1e-3, "{peak}");
+    }
+
+    #[test]
+    fn flying_ignores_gravity() {
+        let world = World::new();
+        let mut player = Player::new(Vec3::new(0.0, 10.0, 0.0));
+        player.toggle_mode();
+        run(&mut player, &world, PlayerInput::default(), 60);
+        assert_eq!(player.position.y, 10.0);
+
+        let ascend = PlayerInput {
+            vertical: 1.0,
+            ..Default::default()
+        };
+        run(&mut player, &world, ascend, 60);
+        assert!((player.position.y - (10.0 + player.fly_speed)).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,78 @@
+use glam::{IVec3, Vec3};
+
+use crate::world::world::World;
+
+/// A block a ray ran into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub pos: IVec3,
+    /// Side of the block the ray came in through; zero if it started inside.
+    pub normal: IVec3,
+    /// Where the ray met the block.
+    pub point: Vec3,
+}
+
+/// Walks the cells along the ray one boundary at a time, up to `max_distance`, and returns the
+/// first that holds anything but air or fluid.
+pub fn raycast(world: &World, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<RayHit> {
+    let direction = direction.normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return None;
+    }
+    let mut pos = origin.floor().as_ivec3();
+    let step = direction.signum().as_ivec3();
+    // Distance along the ray to the next boundary on each axis, and between boundaries.
+    let delta = direction.recip().abs();
+    let next_boundary = (pos.as_vec3() + step.max(IVec3::ZERO).as_vec3() - origin) / direction;
+    let mut next = Vec3::select(direction.cmpeq(Vec3::ZERO), Vec3::INFINITY, next_boundary);
+    let mut normal = IVec3::ZERO;
+    let mut distance = 0.0;
+
+    while distance <= max_distance {
+        let block = world.get_block(pos);
+        if !block.is_air() && block.fluid().is_none() {
+            return Some(RayHit {
+                pos,
+                normal,
+                point: origin + direction * distance,
+            });
+        }
+        let axis = next.min_position();
+        distance = next[axis];
+        next[axis] += delta[axis];
+        pos[axis] += step[axis];
+        normal = IVec3::ZERO;
+        normal[axis] = -step[axis];
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::BlockId;
+
+    #[test]
+    fn hits_the_side_facing_the_ray() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(3, 1, 0), BlockId::STONE);
+        world.set_block(IVec3::new(1, 1, 0), BlockId::WATER);
+        let hit = raycast(&world, Vec3::new(0.5, 1.75, 0.5), Vec3::X, 5.0).unwrap();
+        assert_eq!(hit.pos, IVec3::new(3, 1, 0));
+        assert_eq!(hit.normal, IVec3::NEG_X);
+        assert!(hit.point.abs_diff_eq(Vec3::new(3.0, 1.75, 0.5), 1e-5));
+
+        let down = raycast(&world, Vec3::new(3.5, 4.0, 0.2), Vec3::NEG_Y, 5.0).unwrap();
+        assert_eq!((down.pos, down.normal), (IVec3::new(3, 1, 0), IVec3::Y));
+    }
+
+    #[test]
+    fn stops_at_its_reach() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(-6, 0, -6), BlockId::STONE);
+        let direction = Vec3::new(-1.0, 0.0, -1.0);
+        assert!(raycast(&world, Vec3::new(0.5, 0.5, 0.5), direction, 6.0).is_none());
+        let hit = raycast(&world, Vec3::new(0.5, 0.5, 0.5), direction, 9.0).unwrap();
+        assert_eq!(hit.pos, IVec3::new(-6, 0, -6));
+    }
+}
@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::settings::settings::{
+    AdapterPreference, SETTINGS_PATH, Settings, SettingsError, SsaoQuality, Tonemapper,
+};
+
+/// Command-line overrides; anything left unset keeps the value from the settings file.
+#[derive(Debug, Default, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Settings file to load
+    #[arg(long, default_value = SETTINGS_PATH)]
+    pub config: PathBuf,
+
+    /// Write the resulting settings back to the settings file
+    #[arg(long)]
+    pub save_settings: bool,
+
+    #[arg(long)]
+    pub width: Option<u32>,
+    #[arg(long)]
+    pub height: Option<u32>,
+    #[arg(long)]
+    pub fullscreen: Option<bool>,
+    #[arg(long)]
+    pub vsync: Option<bool>,
+
+    /// Vertical field of view in degrees
+    #[arg(long)]
+    pub fov: Option<f32>,
+
+    /// Distance in chunks at which terrain fades fully into the fog
+    #[arg(long)]
+    pub render_distance: Option<u32>,
+
+    /// Seed of a new world's biomes and random block ticks; terrain is flat regardless
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// World directory
+    #[arg(long)]
+    pub world: Option<PathBuf>,
+
+    #[arg(long, value_enum)]
+    pub adapter: Option<AdapterPreference>,
+
+    #[arg(long, value_enum)]
+    pub tonemapper: Option<Tonemapper>,
+
+    #[arg(long, value_enum)]
+    pub ssao: Option<SsaoQuality>,
+
+    /// Exposure compensation in stops
+    #[arg(long, allow_negative_numbers = true)]
+    pub exposure: Option<f32>,
+
+    /// Export the saved world as a .glb or .obj mesh and exit without opening a window
+    #[arg(long, value_name = "PATH")]
+    pub export_mesh: Option<PathBuf>,
+
+    /// Render one frame offscreen on a software adapter, save it and exit
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Where `--headless` saves its frame; defaults to the screenshot directory
+    #[arg(long, value_name = "PATH", requires = "headless")]
+    pub screenshot: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn apply(&self, settings: &mut Settings) {
+        let window = &mut settings.window;
+        let graphics = &mut settings.graphics;
+        let world = &mut settings.world;
+
+        window.width = self.width.unwrap_or(window.width);
+        window.height = self.height.unwrap_or(window.height);
+        window.fullscreen = self.fullscreen.unwrap_or(window.fullscreen);
+        graphics.vsync = self.vsync.unwrap_or(graphics.vsync);
+        graphics.fov = self.fov.unwrap_or(graphics.fov);
+        graphics.render_distance = self.render_distance.unwrap_or(graphics.render_distance);
+        graphics.adapter = self.adapter.unwrap_or(graphics.adapter);
+        graphics.tonemapper = self.tonemapper.unwrap_or(graphics.tonemapper);
+        graphics.ssao = self.ssao.unwrap_or(graphics.ssao);
+        graphics.exposure = self.exposure.unwrap_or(graphics.exposure);
+        world.seed = self.seed.unwrap_or(world.seed);
+        if let Some(path) = &self.world {
+            world.path = path.clone();
+        }
+    }
+
+    /// Loads the settings file, applies the overrides and validates the result. The settings
+    /// file is written when it doesn't exist yet or `--save-settings` was passed.
+    pub fn load_settings(&self) -> Result<Settings, SettingsError> {
+        let mut settings = Settings::load_or_default(&self.config)?;
+        self.apply(&mut settings);
+        settings.validate()?;
+
+        if self.save_settings {
+            settings.save(&self.config)?;
+        } else if !self.config.exists() {
+            Settings::default().save(&self.config)?;
+        }
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_override_file_values() {
+        let cli = Cli::parse_from([
+            "voxel",
+            "--fov",
+            "75",
+            "--vsync",
+            "false",
+            "--adapter",
+            "low-power",
+            "--exposure",
+            "-1.5",
+        ]);
+        let mut settings = Settings::default();
+        cli.apply(&mut settings);
+        assert_eq!(settings.graphics.fov, 75.0);
+        assert!(!settings.graphics.vsync);
+        assert_eq!(settings.graphics.adapter, AdapterPreference::LowPower);
+        assert_eq!(settings.graphics.exposure, -1.5);
+        assert_eq!(settings.window.width, Settings::default().window.width);
+    }
+
+    #[test]
+    fn flags_fix_bad_file_values() {
+        let dir =
+            std::env::temp_dir().join(format!("rust-voxel-blocks-cli-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("settings.toml");
+        std::fs::write(&config, "[graphics]\nfov = 200.0\n").unwrap();
+        let config_arg = config.to_str().unwrap();
+
+        let bad = Cli::parse_from(["voxel", "--config", config_arg]);
+        assert!(matches!(
+            bad.load_settings(),
+            Err(SettingsError::Invalid(_))
+        ));
+        let fixed = Cli::parse_from(["voxel", "--config", config_arg, "--fov", "90"]);
+        assert_eq!(fixed.load_settings().unwrap().graphics.fov, 90.0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
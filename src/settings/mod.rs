@@ -0,0 +1,3 @@
+pub mod cli;
+#[allow(clippy::module_inception)]
+pub mod settings;
@@ -0,0 +1,366 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graphics::structures::MAX_CASCADES,
+    simulation::{simulation::DEFAULT_TICK_RATE, world_time::DEFAULT_DAY_LENGTH},
+};
+
+pub const SETTINGS_PATH: &str = "settings.toml";
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(err) => write!(f, "settings io error: {err}"),
+            SettingsError::Parse(err) => write!(f, "invalid settings file: {err}"),
+            SettingsError::Serialize(err) => write!(f, "failed to serialize settings: {err}"),
+            SettingsError::Invalid(reason) => write!(f, "invalid setting: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum AdapterPreference {
+    None,
+    LowPower,
+    HighPerformance,
+}
+
+impl From<AdapterPreference> for wgpu::PowerPreference {
+    fn from(preference: AdapterPreference) -> Self {
+        match preference {
+            AdapterPreference::None => wgpu::PowerPreference::None,
+            AdapterPreference::LowPower => wgpu::PowerPreference::LowPower,
+            AdapterPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+        }
+    }
+}
+
+/// Curve mapping HDR scene colors to the display range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Tonemapper {
+    /// Filmic fit of the ACES reference rendering transform.
+    Aces,
+    /// Desaturates bright colors toward white instead of skewing their hue.
+    Agx,
+    Reinhard,
+}
+
+/// Screen-space ambient occlusion: more samples and a wider blur at higher levels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SsaoQuality {
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    pub vsync: bool,
+    /// Vertical field of view in degrees.
+    pub fov: f32,
+    /// Distance in chunks at which terrain has faded fully into the fog. Every loaded chunk
+    /// is still meshed and drawn.
+    pub render_distance: u32,
+    pub adapter: AdapterPreference,
+    pub screenshot_dir: PathBuf,
+    /// Number of sun shadow cascades, at most `MAX_CASCADES`.
+    pub shadow_cascades: u32,
+    /// Edge length of each cascade's shadow map in texels.
+    pub shadow_map_size: u32,
+    /// How far from the camera shadows are drawn, in blocks.
+    pub shadow_distance: f32,
+    /// Fog extinction per block at ground level; terrain still fades out at the render
+    /// distance when 0.
+    pub fog_density: f32,
+    /// Exposure compensation in stops; each one doubles the scene brightness.
+    pub exposure: f32,
+    pub tonemapper: Tonemapper,
+    /// How much bloom is added to the scene; 0 disables it.
+    pub bloom_intensity: f32,
+    /// Scene brightness above which pixels bloom.
+    pub bloom_threshold: f32,
+    pub ssao: SsaoQuality,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlsSettings {
+    /// Radians of rotation per unit of mouse motion.
+    pub mouse_sensitivity: f32,
+    pub input_map: PathBuf,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldSettings {
+    /// Seed of a new world's biomes, which tint the fog, and of its random block ticks.
+    /// Terrain is flat whatever the seed; saved worlds keep the seed they were created with.
+    pub seed: u64,
+    pub path: PathBuf,
+    /// Simulation ticks per second.
+    pub tick_rate: f32,
+    /// Length of a full day and night in seconds.
+    pub day_length: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebugSettings {
+    /// `env_logger` filter, overridden by `RUST_LOG`.
+    pub log_filter: String,
+    /// Reload shaders from the source tree when they change. Only in debug builds.
+    pub hot_reload_shaders: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub window: WindowSettings,
+    pub graphics: GraphicsSettings,
+    pub controls: ControlsSettings,
+    pub world: WorldSettings,
+    pub debug: DebugSettings,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            title: "Voxel Blocks".to_string(),
+            width: 1280,
+            height: 720,
+            fullscreen: false,
+        }
+    }
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        GraphicsSettings {
+            vsync: true,
+            fov: 90.0,
+            render_distance: 8,
+            adapter: AdapterPreference::HighPerformance,
+            screenshot_dir: PathBuf::from("screenshots"),
+            shadow_cascades: 4,
+            shadow_map_size: 2048,
+            shadow_distance: 96.0,
+            fog_density: 0.003,
+            exposure: 0.0,
+            tonemapper: Tonemapper::Aces,
+            bloom_intensity: 0.15,
+            bloom_threshold: 1.0,
+            ssao: SsaoQuality::Medium,
+        }
+    }
+}
+
+impl Default for ControlsSettings {
+    fn default() -> Self {
+        ControlsSettings {
+            mouse_sensitivity: 0.002,
+            input_map: PathBuf::from("input.toml"),
+        }
+    }
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        WorldSettings {
+            seed: 0,
+            path: PathBuf::from("worlds/default"),
+            tick_rate: DEFAULT_TICK_RATE,
+            day_length: DEFAULT_DAY_LENGTH,
+        }
+    }
+}
+
+impl Default for DebugSettings {
+    fn default() -> Self {
+        DebugSettings {
+            log_filter: "error".to_string(),
+            hot_reload_shaders: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads `path`; a missing file yields the defaults. Not validated, so command-line
+    /// overrides can still fix a bad value before `validate` runs.
+    pub fn load_or_default(path: &Path) -> Result<Self, SettingsError> {
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(SettingsError::Parse),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(SettingsError::Io(err)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SettingsError> {
+        self.validate()?;
+        let text = toml::to_string_pretty(self).map_err(SettingsError::Serialize)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(SettingsError::Io)?;
+        }
+        fs::write(path, text).map_err(SettingsError::Io)
+    }
+
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let invalid = |reason: String| Err(SettingsError::Invalid(reason));
+
+        if self.window.width == 0 || self.window.height == 0 {
+            return invalid(format!(
+                "window size {}x{} must be non-zero",
+                self.window.width, self.window.height
+            ));
+        }
+        if !(30.0..=150.0).contains(&self.graphics.fov) {
+            return invalid(format!(
+                "fov {} must be between 30 and 150 degrees",
+                self.graphics.fov
+            ));
+        }
+        if !(2..=64).contains(&self.graphics.render_distance) {
+            return invalid(format!(
+                "render distance {} must be between 2 and 64 chunks",
+                self.graphics.render_distance
+            ));
+        }
+        if !(1..=MAX_CASCADES as u32).contains(&self.graphics.shadow_cascades) {
+            return invalid(format!(
+                "shadow cascades {} must be between 1 and {MAX_CASCADES}",
+                self.graphics.shadow_cascades
+            ));
+        }
+        let shadow_map_size = self.graphics.shadow_map_size;
+        if !(shadow_map_size.is_power_of_two() && (256..=8192).contains(&shadow_map_size)) {
+            return invalid(format!(
+                "shadow map size {shadow_map_size} must be a power of two between 256 and 8192"
+            ));
+        }
+        if !(8.0..=1024.0).contains(&self.graphics.shadow_distance) {
+            return invalid(format!(
+                "shadow distance {} must be between 8 and 1024 blocks",
+                self.graphics.shadow_distance
+            ));
+        }
+        if !(0.0..=0.1).contains(&self.graphics.fog_density) {
+            return invalid(format!(
+                "fog density {} must be between 0 and 0.1",
+                self.graphics.fog_density
+            ));
+        }
+        if !(-8.0..=8.0).contains(&self.graphics.exposure) {
+            return invalid(format!(
+                "exposure {} must be between -8 and 8 stops",
+                self.graphics.exposure
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.graphics.bloom_intensity) {
+            return invalid(format!(
+                "bloom intensity {} must be between 0 and 1",
+                self.graphics.bloom_intensity
+            ));
+        }
+        if !(0.0..=16.0).contains(&self.graphics.bloom_threshold) {
+            return invalid(format!(
+                "bloom threshold {} must be between 0 and 16",
+                self.graphics.bloom_threshold
+            ));
+        }
+        if !(self.controls.mouse_sensitivity > 0.0 && self.controls.mouse_sensitivity < 1.0) {
+            return invalid(format!(
+                "mouse sensitivity {} must be between 0 and 1",
+                self.controls.mouse_sensitivity
+            ));
+        }
+        if !(1.0..=240.0).contains(&self.world.tick_rate) {
+            return invalid(format!(
+                "tick rate {} must be between 1 and 240",
+                self.world.tick_rate
+            ));
+        }
+        if !(1.0..).contains(&self.world.day_length) {
+            return invalid(format!(
+                "day length {} must be at least one second",
+                self.world.day_length
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        if self.graphics.vsync {
+            wgpu::PresentMode::AutoVsync
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_file_fills_in_defaults() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [graphics]
+            fov = 70.0
+            adapter = "low-power"
+            tonemapper = "agx"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(settings.graphics.fov, 70.0);
+        assert_eq!(settings.graphics.tonemapper, Tonemapper::Agx);
+        assert_eq!(settings.graphics.adapter, AdapterPreference::LowPower);
+        assert_eq!(settings.window, WindowSettings::default());
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let mut settings = Settings::default();
+        settings.graphics.fov = 200.0;
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsError::Invalid(_))
+        ));
+
+        let mut settings = Settings::default();
+        settings.graphics.shadow_map_size = 3000;
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsError::Invalid(_))
+        ));
+    }
+}
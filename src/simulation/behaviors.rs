@@ -0,0 +1,268 @@
+use std::collections::{HashSet, VecDeque};
+
+use glam::IVec3;
+
+use crate::{
+    simulation::{
+        block_updates::{BlockBehavior, UpdateContext},
+        fluids::FluidFlow,
+    },
+    world::{
+        block::BlockId,
+        world::{NEIGHBOR_OFFSETS, World},
+    },
+};
+
+/// Seconds between a falling block losing its support and dropping a block.
+pub const FALL_DELAY: f32 = 0.05;
+/// Leaves further than this from a log, counting through leaves, decay.
+pub const LEAF_DECAY_DISTANCE: u32 = 4;
+/// Crops grow through ages 0 to this, one per random tick.
+pub const MAX_CROP_AGE: u8 = 7;
+
+/// Behavior of a kind of block; blocks not listed here only sit there.
+pub fn behavior(block: BlockId) -> Option<&'static dyn BlockBehavior> {
+    match block.base() {
+        BlockId::AIR | BlockId::WATER | BlockId::LAVA => Some(&FluidFlow),
+        BlockId::SAND | BlockId::GRAVEL => Some(&Falling),
+        BlockId::GRASS => Some(&GrassSpread),
+        BlockId::LEAVES => Some(&LeafDecay),
+        BlockId::WHEAT => Some(&Crop),
+        _ => None,
+    }
+}
+
+/// Falls one block per tick while there is air or fluid below, displacing the fluid.
+pub struct Falling;
+
+fn can_fall_into(block: BlockId) -> bool {
+    block.is_air() || block.fluid().is_some()
+}
+
+impl BlockBehavior for Falling {
+    fn neighbor_changed(&self, updates: &mut UpdateContext, pos: IVec3, _: IVec3, _: BlockId) {
+        if can_fall_into(updates.get_block(pos + IVec3::NEG_Y)) {
+            updates.schedule(pos, FALL_DELAY);
+        }
+    }
+
+    fn scheduled_tick(&self, updates: &mut UpdateContext, pos: IVec3) {
+        let below = pos + IVec3::NEG_Y;
+        let chunk_loaded = updates.world.chunk(World::chunk_pos(below)).is_some();
+        if chunk_loaded && can_fall_into(updates.get_block(below)) {
+            let block = updates.get_block(pos);
+            updates.set_block(pos, BlockId::AIR);
+            updates.set_block(below, block);
+        }
+    }
+}
+
+/// Grass dies to dirt under an opaque block, and spreads onto dirt nearby with nothing on top.
+pub struct GrassSpread;
+
+fn is_covered(updates: &UpdateContext, pos: IVec3) -> bool {
+    let above = updates.get_block(pos + IVec3::Y);
+    above.is_opaque() || above.fluid().is_some()
+}
+
+impl BlockBehavior for GrassSpread {
+    fn random_tick(&self, updates: &mut UpdateContext, pos: IVec3) {
+        if is_covered(updates, pos) {
+            updates.set_block(pos, BlockId::DIRT);
+            return;
+        }
+        // Anywhere in the 3x5x3 box, reaching further down than up.
+        let target = pos
+            + IVec3::new(
+                updates.rng.below(3) as i32 - 1,
+                updates.rng.below(5) as i32 - 3,
+                updates.rng.below(3) as i32 - 1,
+            );
+        if updates.get_block(target) == BlockId::DIRT && !is_covered(updates, target) {
+            updates.set_block(target, BlockId::GRASS);
+        }
+    }
+}
+
+/// Leaves too far from a log rot away.
+pub struct LeafDecay;
+
+/// Whether a log can be reached from `pos` through leaves within `LEAF_DECAY_DISTANCE`.
+fn near_log(updates: &UpdateContext, pos: IVec3) -> bool {
+    let mut visited = HashSet::from([pos]);
+    let mut queue = VecDeque::from([(pos, 0)]);
+    while let Some((current, distance)) = queue.pop_front() {
+        for offset in NEIGHBOR_OFFSETS {
+            let next = current + offset;
+            let block = updates.get_block(next);
+            if block.base() == BlockId::LOG {
+                return true;
+            }
+            if block.base() == BlockId::LEAVES
+                && distance + 1 < LEAF_DECAY_DISTANCE
+                && visited.insert(next)
+            {
+                queue.push_back((next, distance + 1));
+            }
+        }
+    }
+    false
+}
+
+impl BlockBehavior for LeafDecay {
+    fn random_tick(&self, updates: &mut UpdateContext, pos: IVec3) {
+        if !near_log(updates, pos) {
+            updates.set_block(pos, BlockId::AIR);
+        }
+    }
+}
+
+/// Grows an age per random tick on dirt or grass, and breaks off anything else.
+pub struct Crop;
+
+impl BlockBehavior for Crop {
+    fn neighbor_changed(&self, updates: &mut UpdateContext, pos: IVec3, _: IVec3, _: BlockId) {
+        let soil = updates.get_block(pos + IVec3::NEG_Y);
+        if soil != BlockId::DIRT && soil != BlockId::GRASS {
+            updates.set_block(pos, BlockId::AIR);
+        }
+    }
+
+    fn random_tick(&self, updates: &mut UpdateContext, pos: IVec3) {
+        let crop = updates.get_block(pos);
+        if crop.state() < MAX_CROP_AGE {
+            updates.set_block(pos, crop.with_state(crop.state() + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        simulation::block_updates::BlockUpdates,
+        world::{block::Axis, chunk::CHUNK_SIZE},
+    };
+
+    const TICK_SECS: f32 = 0.05;
+    /// Long ticks for the random tick tests, each block getting one every 67 or so.
+    const SLOW_TICK_SECS: f32 = 1.0;
+
+    fn place(updates: &BlockUpdates, world: &mut World, pos: IVec3, block: BlockId) {
+        let previous = world.get_block(pos);
+        world.set_block(pos, block);
+        updates.block_changed(world, pos, previous, 0);
+    }
+
+    fn run(updates: &BlockUpdates, world: &mut World, ticks: u64) {
+        for now in 1..=ticks {
+            updates.tick(world, now);
+        }
+    }
+
+    #[test]
+    fn sand_falls_until_it_lands() {
+        let updates = BlockUpdates::new(0, TICK_SECS);
+        let mut world = World::flat(1);
+        place(&updates, &mut world, IVec3::new(2, 6, 2), BlockId::SAND);
+        place(&updates, &mut world, IVec3::new(2, 7, 2), BlockId::GRAVEL);
+        run(&updates, &mut world, 40);
+
+        assert_eq!(world.get_block(IVec3::new(2, 0, 2)), BlockId::SAND);
+        assert_eq!(world.get_block(IVec3::new(2, 1, 2)), BlockId::GRAVEL);
+        assert!(world.get_block(IVec3::new(2, 2, 2)).is_air());
+        assert_eq!(world.ticking_chunks().count(), 0);
+    }
+
+    #[test]
+    fn grass_spreads_to_uncovered_dirt() {
+        let updates = BlockUpdates::new(0, SLOW_TICK_SECS);
+        let mut world = World::new();
+        for x in 0..8 {
+            for z in 0..8 {
+                world.set_block(IVec3::new(x, 0, z), BlockId::DIRT);
+            }
+        }
+        world.set_block(IVec3::new(0, 0, 0), BlockId::GRASS);
+        world.set_block(IVec3::new(1, 1, 0), BlockId::STONE);
+        run(&updates, &mut world, 20_000);
+
+        let grass = |x, z| world.get_block(IVec3::new(x, 0, z)) == BlockId::GRASS;
+        assert!(grass(7, 7));
+        assert!(!grass(1, 0), "grew under stone");
+    }
+
+    #[test]
+    fn random_ticks_follow_the_seed() {
+        let grow = |seed| {
+            let updates = BlockUpdates::new(seed, SLOW_TICK_SECS);
+            let mut world = World::new();
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    world.set_block(IVec3::new(x, 0, z), BlockId::DIRT);
+                }
+            }
+            world.set_block(IVec3::new(16, 0, 16), BlockId::GRASS);
+            run(&updates, &mut world, 300);
+            (0..CHUNK_SIZE)
+                .flat_map(|x| (0..CHUNK_SIZE).map(move |z| IVec3::new(x, 0, z)))
+                .map(|pos| world.get_block(pos))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(grow(1), grow(1));
+        assert_ne!(grow(1), grow(2));
+    }
+
+    #[test]
+    fn leaves_away_from_logs_decay() {
+        let updates = BlockUpdates::new(0, SLOW_TICK_SECS);
+        let mut world = World::new();
+        world.set_block(IVec3::new(0, 0, 0), BlockId::LOG);
+        for x in 1..8 {
+            world.set_block(IVec3::new(x, 0, 0), BlockId::LEAVES);
+        }
+        run(&updates, &mut world, 2_000);
+
+        for x in 1..=LEAF_DECAY_DISTANCE as i32 {
+            assert_eq!(world.get_block(IVec3::new(x, 0, 0)), BlockId::LEAVES);
+        }
+        for x in LEAF_DECAY_DISTANCE as i32 + 1..8 {
+            assert!(world.get_block(IVec3::new(x, 0, 0)).is_air(), "{x}");
+        }
+    }
+
+    #[test]
+    fn leaves_are_kept_by_logs_lying_down() {
+        let updates = BlockUpdates::new(0, SLOW_TICK_SECS);
+        let mut world = World::new();
+        world.set_block(IVec3::new(0, 0, 0), BlockId::LOG.with_axis(Axis::X));
+        world.set_block(IVec3::new(0, 0, 1), BlockId::LOG.with_axis(Axis::Z));
+        world.set_block(IVec3::new(1, 0, 0), BlockId::LEAVES);
+        world.set_block(IVec3::new(0, 0, 2), BlockId::LEAVES);
+        run(&updates, &mut world, 2_000);
+
+        assert_eq!(world.get_block(IVec3::new(1, 0, 0)), BlockId::LEAVES);
+        assert_eq!(world.get_block(IVec3::new(0, 0, 2)), BlockId::LEAVES);
+    }
+
+    #[test]
+    fn crops_grow_and_break_without_soil() {
+        let updates = BlockUpdates::new(0, SLOW_TICK_SECS);
+        let mut world = World::flat(1);
+        place(&updates, &mut world, IVec3::new(3, 0, 3), BlockId::WHEAT);
+        run(&updates, &mut world, 2_000);
+        assert_eq!(world.get_block(IVec3::new(3, 0, 3)).state(), MAX_CROP_AGE);
+
+        place(&updates, &mut world, IVec3::new(3, -1, 3), BlockId::AIR);
+        assert!(world.get_block(IVec3::new(3, 0, 3)).is_air());
+        place(
+            &updates,
+            &mut world,
+            IVec3::new(5, 0, 5),
+            BlockId::WHEAT.with_state(0),
+        );
+        place(&updates, &mut world, IVec3::new(6, 1, 6), BlockId::WHEAT);
+        assert_eq!(world.get_block(IVec3::new(5, 0, 5)), BlockId::WHEAT);
+        assert!(world.get_block(IVec3::new(6, 1, 6)).is_air());
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::VecDeque;
+
+use glam::IVec3;
+
+use crate::{
+    simulation::behaviors,
+    world::{
+        block::BlockId,
+        chunk::{CHUNK_SIZE, CHUNK_VOLUME, Chunk},
+        world::{NEIGHBOR_OFFSETS, World},
+    },
+};
+
+/// Scheduled ticks run in one tick at most; the rest wait for the next.
+pub const MAX_SCHEDULED_TICKS: usize = 4096;
+/// Random ticks each block gets per second, on average.
+pub const RANDOM_TICK_RATE: f32 = 0.015;
+
+/// What a kind of block does on its own, see `behaviors::behavior` for who does what. Every
+/// method does nothing by default.
+pub trait BlockBehavior: Sync {
+    /// The block at `changed` became something else; that is `pos` itself or one of its
+    /// neighbors.
+    fn neighbor_changed(
+        &self,
+        _updates: &mut UpdateContext,
+        _pos: IVec3,
+        _changed: IVec3,
+        _previous: BlockId,
+    ) {
+    }
+
+    /// A tick the block scheduled came due.
+    fn scheduled_tick(&self, _updates: &mut UpdateContext, _pos: IVec3) {}
+
+    /// The block was picked for a random tick.
+    fn random_tick(&self, _updates: &mut UpdateContext, _pos: IVec3) {}
+}
+
+/// Splitmix64. Random ticks have to come out the same for a seed on every platform.
+#[derive(Debug)]
+pub struct TickRng(u64);
+
+impl TickRng {
+    pub fn new(seed: u64, tick: u64) -> Self {
+        // Starts from a hash of both: the stream for one tick would otherwise be the stream
+        // for the next, shifted by a draw.
+        let mut rng = TickRng(seed ^ tick.wrapping_mul(0xd1b5_4a32_d192_ed03));
+        TickRng(rng.next_u64())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut hash = self.0;
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^ (hash >> 31)
+    }
+
+    /// In `0..bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+/// The world as behaviors see it during one tick. Blocks set through it let their
+/// neighbors react.
+pub struct UpdateContext<'a> {
+    pub world: &'a mut World,
+    pub now: u64,
+    pub rng: TickRng,
+    tick_secs: f32,
+    /// Changed blocks and what they were, whose neighbors haven't been told yet.
+    changes: VecDeque<(IVec3, BlockId)>,
+}
+
+impl UpdateContext<'_> {
+    pub fn get_block(&self, pos: IVec3) -> BlockId {
+        self.world.get_block(pos)
+    }
+
+    pub fn set_block(&mut self, pos: IVec3, block: BlockId) {
+        let previous = self.world.get_block(pos);
+        if previous != block {
+            self.world.set_block(pos, block);
+            self.changes.push_back((pos, previous));
+        }
+    }
+
+    /// Schedules the block at `pos` to tick `delay` seconds from now, one tick at least.
+    pub fn schedule(&mut self, pos: IVec3, delay: f32) {
+        let ticks = (delay / self.tick_secs).round().max(1.0) as u64;
+        self.world.schedule_tick(pos, self.now + ticks);
+    }
+
+    /// Tells the neighbors of every changed block, including blocks changed in turn.
+    fn propagate(&mut self) {
+        while let Some((changed, previous)) = self.changes.pop_front() {
+            let targets = NEIGHBOR_OFFSETS.map(|offset| changed + offset);
+            for pos in std::iter::once(changed).chain(targets) {
+                if let Some(behavior) = behaviors::behavior(self.world.get_block(pos)) {
+                    behavior.neighbor_changed(self, pos, changed, previous);
+                }
+            }
+        }
+    }
+}
+
+/// Runs block behaviors each tick: the scheduled ticks that came due, queued per chunk in the
+/// world, then a few random ticks in every chunk, picked the same way for the same seed.
+#[derive(Debug)]
+pub struct BlockUpdates {
+    pub seed: u64,
+    tick_secs: f32,
+}
+
+impl BlockUpdates {
+    pub fn new(seed: u64, tick_secs: f32) -> Self {
+        BlockUpdates { seed, tick_secs }
+    }
+
+    /// Random ticks per chunk per tick.
+    pub fn random_ticks_per_chunk(&self) -> usize {
+        (CHUNK_VOLUME as f32 * RANDOM_TICK_RATE * self.tick_secs).round() as usize
+    }
+
+    fn context<'a>(&self, world: &'a mut World, now: u64) -> UpdateContext<'a> {
+        UpdateContext {
+            world,
+            now,
+            rng: TickRng::new(self.seed, now),
+            tick_secs: self.tick_secs,
+            changes: VecDeque::new(),
+        }
+    }
+
+    /// Lets the block at `pos`, which held `previous` until now, and its neighbors react to
+    /// the change; for changes made outside of block updates.
+    pub fn block_changed(&self, world: &mut World, pos: IVec3, previous: BlockId, now: u64) {
+        let mut updates = self.context(world, now);
+        updates.changes.push_back((pos, previous));
+        updates.propagate();
+    }
+
+    /// Runs tick `now` and returns how many scheduled ticks ran. Changed blocks are queued
+    /// for remeshing by the world.
+    pub fn tick(&self, world: &mut World, now: u64) -> usize {
+        let mut updates = self.context(world, now);
+
+        let due = updates.world.take_due_ticks(now, MAX_SCHEDULED_TICKS);
+        for &pos in &due {
+            if let Some(behavior) = behaviors::behavior(updates.get_block(pos)) {
+                behavior.scheduled_tick(&mut updates, pos);
+                updates.propagate();
+            }
+        }
+
+        let mut chunks: Vec<IVec3> = updates.world.chunks().map(|(pos, _)| *pos).collect();
+        chunks.sort_by_key(|pos| pos.to_array());
+        for chunk_pos in chunks {
+            for _ in 0..self.random_ticks_per_chunk() {
+                let index = updates.rng.below(CHUNK_VOLUME as u64) as usize;
+                let pos = chunk_pos * CHUNK_SIZE + Chunk::local_pos(index);
+                if let Some(behavior) = behaviors::behavior(updates.get_block(pos)) {
+                    behavior.random_tick(&mut updates, pos);
+                    updates.propagate();
+                }
+            }
+        }
+        due.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_ticks_depend_only_on_the_seed() {
+        let picks = |seed| {
+            let mut rng = TickRng::new(seed, 12);
+            (0..64)
+                .map(|_| rng.below(CHUNK_VOLUME as u64))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(picks(3), picks(3));
+        assert_ne!(picks(3), picks(4));
+    }
+
+    #[test]
+    fn scheduled_ticks_wait_in_their_chunk() {
+        let mut world = World::new();
+        world.set_block(IVec3::new(40, 0, 0), BlockId::STONE);
+        world.schedule_tick(IVec3::new(40, 0, 0), 10);
+        world.schedule_tick(IVec3::new(500, 0, 0), 10);
+        assert_eq!(world.ticking_chunks().collect::<Vec<_>>(), [IVec3::X]);
+
+        let updates = BlockUpdates::new(0, 0.05);
+        assert_eq!(updates.tick(&mut world, 9), 0);
+        assert_eq!(updates.tick(&mut world, 10), 1);
+        assert_eq!(world.ticking_chunks().count(), 0);
+    }
+}
@@ -0,0 +1,329 @@
+use glam::IVec3;
+
+use crate::{
+    simulation::block_updates::{BlockBehavior, UpdateContext},
+    world::{
+        block::{BlockId, Fluid},
+        world::{NEIGHBOR_OFFSETS, World},
+    },
+};
+
+const HORIZONTAL_OFFSETS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+/// Cellular simulation of water and lava, the behavior of fluids and of air.
+///
+/// Fluid flows down and, where it can't, sideways, one level thinner per block until it runs
+/// out at `BlockId::MAX_FLUID_LEVEL`. Each cell works out its own state from its neighbors, so
+/// cut off flows drain away by themselves. Water between two sources on solid ground becomes
+/// a source, and lava touching water turns to stone. Waterlogged blocks act as water sources
+/// that never move.
+///
+/// Cells are only looked at when something next to them changed, a fluid's delay later. Fluid
+/// in a world that is loaded or generated stays put until it is disturbed.
+pub struct FluidFlow;
+
+impl BlockBehavior for FluidFlow {
+    fn neighbor_changed(
+        &self,
+        updates: &mut UpdateContext,
+        pos: IVec3,
+        changed: IVec3,
+        previous: BlockId,
+    ) {
+        let fluid = held_fluid(updates.get_block(pos))
+            .or(held_fluid(updates.get_block(changed)))
+            .or(held_fluid(previous))
+            .or_else(|| {
+                NEIGHBOR_OFFSETS
+                    .iter()
+                    .find_map(|offset| held_fluid(updates.get_block(pos + *offset)))
+            });
+        // Cells with no fluid in or around them have nothing to do.
+        if let Some(fluid) = fluid {
+            updates.schedule(pos, fluid.flow_delay());
+        }
+    }
+
+    fn scheduled_tick(&self, updates: &mut UpdateContext, pos: IVec3) {
+        let next = next_state(updates.world, pos);
+        updates.set_block(pos, next);
+    }
+}
+
+/// The fluid in `block`, counting the water around waterlogged blocks.
+fn held_fluid(block: BlockId) -> Option<Fluid> {
+    block
+        .fluid()
+        .or(block.is_waterlogged().then_some(Fluid::Water))
+}
+
+/// Whether `block` is or holds a fluid source.
+fn holds_source(block: BlockId) -> bool {
+    block.is_fluid_source() || block.is_waterlogged()
+}
+
+fn is_loaded(world: &World, pos: IVec3) -> bool {
+    world.chunk(World::chunk_pos(pos)).is_some()
+}
+
+/// What the block at `pos` becomes given its neighbors. Only air and fluids change; chunks
+/// that aren't loaded are left alone, and act as walls to fluid next to them.
+fn next_state(world: &World, pos: IVec3) -> BlockId {
+    let block = world.get_block(pos);
+    if !(block.is_air() || block.fluid().is_some()) || !is_loaded(world, pos) {
+        return block;
+    }
+    let next = if block.is_fluid_source() {
+        block
+    } else {
+        inflow(world, pos, block.fluid()).unwrap_or(BlockId::AIR)
+    };
+
+    let touches_water = || {
+        NEIGHBOR_OFFSETS
+            .iter()
+            .any(|offset| held_fluid(world.get_block(pos + *offset)) == Some(Fluid::Water))
+    };
+    if next.fluid() == Some(Fluid::Lava) && touches_water() {
+        return BlockId::STONE;
+    }
+    next
+}
+
+/// Fluid flowing into `pos` from above or the sides, limited to `current` if the cell
+/// already holds a fluid.
+fn inflow(world: &World, pos: IVec3, current: Option<Fluid>) -> Option<BlockId> {
+    let accepts = |fluid| current.is_none_or(|current| current == fluid);
+
+    if let Some(fluid) = held_fluid(world.get_block(pos + IVec3::Y))
+        && accepts(fluid)
+    {
+        return Some(fluid.block().with_fluid_state(0, true));
+    }
+
+    let mut thickest: Option<(u8, Fluid)> = None;
+    let mut sources = 0;
+    for offset in HORIZONTAL_OFFSETS {
+        let from = pos + offset;
+        let neighbor = world.get_block(from);
+        let Some(fluid) = held_fluid(neighbor) else {
+            continue;
+        };
+        if !accepts(fluid) || can_flow_into(world, from + IVec3::NEG_Y, fluid) {
+            continue;
+        }
+        if holds_source(neighbor) && fluid.forms_sources() {
+            sources += 1;
+        }
+        let level = neighbor.fluid_level() + fluid.level_step();
+        if level <= BlockId::MAX_FLUID_LEVEL && thickest.is_none_or(|(best, _)| level < best) {
+            thickest = Some((level, fluid));
+        }
+    }
+
+    let (level, fluid) = thickest?;
+    let below = world.get_block(pos + IVec3::NEG_Y);
+    let supported = below.is_solid() || (held_fluid(below) == Some(fluid) && holds_source(below));
+    if fluid.forms_sources() && sources >= 2 && supported {
+        return Some(fluid.block());
+    }
+    Some(fluid.block().with_fluid_state(level, false))
+}
+
+/// Whether `fluid` would rather move into `pos` than spread beside it.
+fn can_flow_into(world: &World, pos: IVec3, fluid: Fluid) -> bool {
+    let block = world.get_block(pos);
+    is_loaded(world, pos)
+        && (block.is_air() || (block.fluid() == Some(fluid) && !block.is_fluid_source()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::block_updates::BlockUpdates;
+
+    const TICK_SECS: f32 = 0.05;
+
+    /// A stone floor at y = 0 around the origin, with fluid placed on it at y = 1.
+    fn floor() -> World {
+        let mut world = World::new();
+        for x in -12..=12 {
+            for z in -12..=12 {
+                world.set_block(IVec3::new(x, 0, z), BlockId::STONE);
+            }
+        }
+        world
+    }
+
+    fn place(updates: &BlockUpdates, world: &mut World, pos: IVec3, block: BlockId, now: u64) {
+        let previous = world.get_block(pos);
+        world.set_block(pos, block);
+        updates.block_changed(world, pos, previous, now);
+    }
+
+    /// Ticks until nothing is scheduled, returning the last tick run. The floor has no
+    /// random ticks to speak of.
+    fn settle(updates: &BlockUpdates, world: &mut World, mut now: u64) -> u64 {
+        while world.ticking_chunks().next().is_some() {
+            now += 1;
+            updates.tick(world, now);
+            assert!(now < 10_000, "fluids never settled");
+        }
+        now
+    }
+
+    fn fluid_blocks(world: &World) -> Vec<(IVec3, BlockId)> {
+        let mut blocks = Vec::new();
+        for x in -12..=12 {
+            for y in 1..8 {
+                for z in -12..=12 {
+                    let pos = IVec3::new(x, y, z);
+                    if world.get_block(pos).fluid().is_some() {
+                        blocks.push((pos, world.get_block(pos)));
+                    }
+                }
+            }
+        }
+        blocks
+    }
+
+    fn setup() -> (BlockUpdates, World) {
+        (BlockUpdates::new(0, TICK_SECS), floor())
+    }
+
+    #[test]
+    fn water_spreads_one_level_per_block() {
+        let (updates, mut world) = setup();
+        place(&updates, &mut world, IVec3::new(0, 1, 0), BlockId::WATER, 0);
+        settle(&updates, &mut world, 0);
+
+        for x in 1..=7 {
+            let block = world.get_block(IVec3::new(x, 1, 0));
+            assert!(block.is_water() && !block.is_fluid_source());
+            assert_eq!(block.fluid_level(), x as u8);
+        }
+        assert!(world.get_block(IVec3::new(8, 1, 0)).is_air());
+        assert_eq!(world.get_block(IVec3::new(3, 1, -4)).fluid_level(), 7);
+        assert!(world.get_block(IVec3::new(4, 1, -4)).is_air());
+    }
+
+    #[test]
+    fn lava_spreads_less_far() {
+        let (updates, mut world) = setup();
+        place(&updates, &mut world, IVec3::new(0, 1, 0), BlockId::LAVA, 0);
+        settle(&updates, &mut world, 0);
+
+        let levels: Vec<u8> = (1..=3)
+            .map(|x| world.get_block(IVec3::new(x, 1, 0)).fluid_level())
+            .collect();
+        assert_eq!(levels, [2, 4, 6]);
+        assert!(world.get_block(IVec3::new(4, 1, 0)).is_air());
+    }
+
+    #[test]
+    fn falls_before_spreading() {
+        let (updates, mut world) = setup();
+        place(&updates, &mut world, IVec3::new(0, 4, 0), BlockId::WATER, 0);
+        settle(&updates, &mut world, 0);
+
+        for y in 1..4 {
+            let block = world.get_block(IVec3::new(0, y, 0));
+            assert!(block.is_water() && block.is_falling(), "{y}: {block:?}");
+        }
+        // Only the bottom of the column spreads, and at full strength.
+        assert!(world.get_block(IVec3::new(1, 2, 0)).is_air());
+        assert_eq!(world.get_block(IVec3::new(1, 1, 0)).fluid_level(), 1);
+    }
+
+    #[test]
+    fn drains_once_the_source_is_gone() {
+        let (updates, mut world) = setup();
+        let source = IVec3::new(0, 3, 0);
+        place(&updates, &mut world, source, BlockId::WATER, 0);
+        let now = settle(&updates, &mut world, 0);
+        assert!(fluid_blocks(&world).len() > 100);
+
+        place(&updates, &mut world, source, BlockId::AIR, now);
+        settle(&updates, &mut world, now);
+        assert_eq!(fluid_blocks(&world), []);
+    }
+
+    #[test]
+    fn water_between_two_sources_becomes_a_source() {
+        let (updates, mut world) = setup();
+        place(&updates, &mut world, IVec3::new(0, 1, 0), BlockId::WATER, 0);
+        place(&updates, &mut world, IVec3::new(2, 1, 0), BlockId::WATER, 0);
+        settle(&updates, &mut world, 0);
+        assert!(world.get_block(IVec3::new(1, 1, 0)).is_fluid_source());
+
+        // Lava never does.
+        let (updates, mut world) = setup();
+        place(&updates, &mut world, IVec3::new(0, 1, 0), BlockId::LAVA, 0);
+        place(&updates, &mut world, IVec3::new(2, 1, 0), BlockId::LAVA, 0);
+        settle(&updates, &mut world, 0);
+        assert_eq!(world.get_block(IVec3::new(1, 1, 0)).fluid_level(), 2);
+    }
+
+    #[test]
+    fn lava_meeting_water_turns_to_stone() {
+        let (updates, mut world) = setup();
+        place(&updates, &mut world, IVec3::new(0, 1, 0), BlockId::WATER, 0);
+        place(&updates, &mut world, IVec3::new(4, 1, 0), BlockId::LAVA, 0);
+        settle(&updates, &mut world, 0);
+
+        // The lava got into the cell between them first and hardened into a wall there.
+        assert_eq!(world.get_block(IVec3::new(3, 1, 0)), BlockId::STONE);
+        assert!(world.get_block(IVec3::new(4, 1, 0)).is_fluid_source());
+        for (pos, block) in fluid_blocks(&world) {
+            if block.fluid() == Some(Fluid::Lava) {
+                let neighbors = NEIGHBOR_OFFSETS.map(|offset| world.get_block(pos + offset));
+                assert!(!neighbors.iter().any(|block| block.is_water()), "{pos}");
+            }
+        }
+    }
+
+    #[test]
+    fn waterlogged_blocks_are_sources_that_stay_put() {
+        let (updates, mut world) = setup();
+        let slab = IVec3::new(0, 1, 0);
+        let waterlogged = BlockId::STONE_SLAB.with_waterlogged(true);
+        place(&updates, &mut world, slab, waterlogged, 0);
+        let now = settle(&updates, &mut world, 0);
+
+        assert_eq!(world.get_block(slab), waterlogged);
+        assert_eq!(world.get_block(IVec3::new(1, 1, 0)).fluid_level(), 1);
+        assert_eq!(world.get_block(IVec3::new(7, 1, 0)).fluid_level(), 7);
+
+        place(&updates, &mut world, slab, BlockId::STONE_SLAB, now);
+        settle(&updates, &mut world, now);
+        assert_eq!(fluid_blocks(&world), []);
+    }
+
+    #[test]
+    fn only_chunks_with_pending_updates_are_active() {
+        let (updates, mut world) = setup();
+        world.take_remesh_chunks();
+        assert_eq!(world.ticking_chunks().count(), 0);
+
+        place(
+            &updates,
+            &mut world,
+            IVec3::new(-3, 1, 5),
+            BlockId::WATER,
+            0,
+        );
+        assert_eq!(
+            world.ticking_chunks().collect::<Vec<_>>(),
+            [IVec3::new(-1, 0, 0)]
+        );
+        world.take_remesh_chunks();
+        // Nothing is due before the water's delay is up.
+        assert_eq!(updates.tick(&mut world, 1), 0);
+
+        settle(&updates, &mut world, 1);
+        // The flow crossed into the chunk east of the source, and both were remeshed.
+        let remesh = world.take_remesh_chunks();
+        assert!(remesh.contains(&IVec3::new(-1, 0, 0)));
+        assert!(remesh.contains(&IVec3::new(0, 0, 0)));
+    }
+}
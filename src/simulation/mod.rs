@@ -0,0 +1,8 @@
+pub mod behaviors;
+pub mod block_updates;
+pub mod clock;
+pub mod fluids;
+#[allow(clippy::module_inception)]
+pub mod simulation;
+pub mod timestep;
+pub mod world_time;
@@ -0,0 +1,140 @@
+use glam::{IVec3, Vec3};
+
+use crate::{
+    physics::player::{Player, PlayerInput},
+    simulation::{
+        block_updates::BlockUpdates,
+        clock::{Clock, SystemClock},
+        timestep::FixedTimestep,
+        world_time::{DEFAULT_DAY_LENGTH, START_TIME_OF_DAY, WorldTime},
+    },
+    world::{block::BlockId, world::World},
+};
+
+pub const DEFAULT_TICK_RATE: f32 = 60.0;
+
+/// Game state advanced in fixed ticks, independent of the window and frame rate.
+#[derive(Debug)]
+pub struct Simulation<C: Clock = SystemClock> {
+    pub clock: C,
+    pub timestep: FixedTimestep,
+    pub world: World,
+    pub block_updates: BlockUpdates,
+    pub player: Player,
+    pub tick_count: u64,
+    pub time: WorldTime,
+
+    prev_player_position: Vec3,
+}
+
+impl<C: Clock> Simulation<C> {
+    pub fn new(clock: C, tick_rate: f32, world: World, player: Player) -> Self {
+        let timestep = FixedTimestep::new(tick_rate);
+        Simulation {
+            clock,
+            block_updates: BlockUpdates::new(0, timestep.tick_secs()),
+            timestep,
+            world,
+            prev_player_position: player.position,
+            player,
+            tick_count: 0,
+            time: WorldTime::new(0, START_TIME_OF_DAY, DEFAULT_DAY_LENGTH, tick_rate),
+        }
+    }
+
+    /// Runs every tick that became due since the previous call and returns how many ran.
+    pub fn update(&mut self, input: &PlayerInput) -> u32 {
+        let ticks = self.timestep.advance(self.clock.now());
+        for _ in 0..ticks {
+            self.tick(input);
+        }
+        ticks
+    }
+
+    pub fn tick(&mut self, input: &PlayerInput) {
+        self.prev_player_position = self.player.position;
+        self.player
+            .tick(&self.world, input, self.timestep.tick_secs());
+        self.block_updates.tick(&mut self.world, self.tick_count);
+        self.tick_count += 1;
+        self.time.tick();
+    }
+
+    /// Changes a block so that the blocks around it react.
+    pub fn set_block(&mut self, pos: IVec3, block: BlockId) {
+        let previous = self.world.get_block(pos);
+        self.world.set_block(pos, block);
+        self.block_updates
+            .block_changed(&mut self.world, pos, previous, self.tick_count);
+    }
+
+    /// Time of day blended between the last two ticks, see [`WorldTime`].
+    pub fn time_of_day(&self) -> f32 {
+        self.time.time_of_day(self.timestep.alpha())
+    }
+
+    /// Player position blended between the last two ticks by the leftover frame time.
+    pub fn interpolated_player_position(&self) -> Vec3 {
+        self.prev_player_position
+            .lerp(self.player.position, self.timestep.alpha())
+    }
+
+    pub fn interpolated_eye_position(&self) -> Vec3 {
+        self.interpolated_player_position() + Vec3::Y * self.player.eye_height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::simulation::clock::ManualClock;
+
+    fn flying(tick_rate: f32) -> Simulation<ManualClock> {
+        let mut player = Player::new(Vec3::ZERO);
+        player.toggle_mode();
+        Simulation::new(ManualClock::new(), tick_rate, World::new(), player)
+    }
+
+    const ASCEND: PlayerInput = PlayerInput {
+        direction: Vec3::ZERO,
+        vertical: 1.0,
+        jump: false,
+        sprint: false,
+    };
+
+    #[test]
+    fn result_is_independent_of_frame_rate() {
+        let mut slow = flying(20.0);
+        let mut fast = flying(20.0);
+        slow.update(&ASCEND);
+        fast.update(&ASCEND);
+
+        for _ in 0..10 {
+            slow.clock.advance(Duration::from_millis(100));
+            slow.update(&ASCEND);
+        }
+        for _ in 0..100 {
+            fast.clock.advance(Duration::from_millis(10));
+            fast.update(&ASCEND);
+        }
+
+        assert_eq!(slow.tick_count, 20);
+        assert_eq!(fast.tick_count, 20);
+        assert_eq!(slow.player.position, fast.player.position);
+    }
+
+    #[test]
+    fn interpolates_between_ticks() {
+        let mut simulation = flying(10.0);
+        simulation.update(&ASCEND);
+        simulation.clock.advance(Duration::from_millis(150));
+        assert_eq!(simulation.update(&ASCEND), 1);
+
+        let step = simulation.player.fly_speed * 0.1;
+        assert!((simulation.player.position.y - step).abs() < 1e-5);
+        let interpolated = simulation.interpolated_player_position().y;
+        assert!((interpolated - step * 0.5).abs() < 1e-4, "{interpolated}");
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+/// Accumulates frame time and hands it out as whole ticks of a fixed length.
+#[derive(Debug)]
+pub struct FixedTimestep {
+    tick: Duration,
+    /// Caps the ticks run per frame so a long stall doesn't freeze the game catching up.
+    max_ticks_per_frame: u32,
+    accumulator: Duration,
+    last: Option<Duration>,
+}
+
+impl FixedTimestep {
+    pub fn new(tick_rate: f32) -> Self {
+        FixedTimestep {
+            tick: Duration::from_secs_f64(1.0 / tick_rate as f64),
+            max_ticks_per_frame: 10,
+            accumulator: Duration::ZERO,
+            last: None,
+        }
+    }
+
+    pub fn tick(&self) -> Duration {
+        self.tick
+    }
+
+    pub fn tick_secs(&self) -> f32 {
+        self.tick.as_secs_f32()
+    }
+
+    /// Consumes the time elapsed since the previous call and returns how many ticks are due.
+    pub fn advance(&mut self, now: Duration) -> u32 {
+        let elapsed = self
+            .last
+            .map_or(Duration::ZERO, |last| now.saturating_sub(last));
+        self.last = Some(now);
+        self.accumulator = (self.accumulator + elapsed).min(self.tick * self.max_ticks_per_frame);
+
+        let ticks = (self.accumulator.as_nanos() / self.tick.as_nanos()) as u32;
+        self.accumulator -= self.tick * ticks;
+        ticks
+    }
+
+    /// How far the leftover time is into the next tick, in 0.0..1.0.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.tick.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_time_into_ticks() {
+        let mut timestep = FixedTimestep::new(20.0);
+        assert_eq!(timestep.advance(Duration::ZERO), 0);
+        assert_eq!(timestep.advance(Duration::from_millis(75)), 1);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+        assert_eq!(timestep.advance(Duration::from_millis(200)), 3);
+        assert!(timestep.alpha().abs() < 1e-4);
+    }
+
+    #[test]
+    fn clamps_long_stalls() {
+        let mut timestep = FixedTimestep::new(60.0);
+        timestep.advance(Duration::ZERO);
+        assert_eq!(timestep.advance(Duration::from_secs(5)), 10);
+        assert!(timestep.alpha() < 1.0);
+    }
+}
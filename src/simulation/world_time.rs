@@ -0,0 +1,81 @@
+/// Time of day when a new world starts: mid-morning.
+pub const START_TIME_OF_DAY: f32 = 0.35;
+/// Seconds per day.
+pub const DEFAULT_DAY_LENGTH: f32 = 1200.0;
+
+/// In-game clock, advanced by simulation ticks so it pauses and catches up with the rest of
+/// the simulation. A day runs from midnight at 0 through sunrise at 0.25, noon at 0.5 and
+/// sunset at 0.75.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorldTime {
+    pub day: u64,
+    /// Ticks since midnight.
+    pub tick_of_day: u64,
+    pub ticks_per_day: u64,
+}
+
+impl WorldTime {
+    /// A clock at `time_of_day` on `day`, with days lasting `day_length` seconds of ticks at
+    /// `tick_rate`.
+    pub fn new(day: u64, time_of_day: f32, day_length: f32, tick_rate: f32) -> Self {
+        let ticks_per_day = ((day_length * tick_rate).round() as u64).max(1);
+        let mut time = WorldTime {
+            day,
+            tick_of_day: 0,
+            ticks_per_day,
+        };
+        time.set_time_of_day(time_of_day);
+        time
+    }
+
+    pub fn tick(&mut self) {
+        self.tick_of_day += 1;
+        if self.tick_of_day >= self.ticks_per_day {
+            self.tick_of_day = 0;
+            self.day += 1;
+        }
+    }
+
+    /// Fraction of the day passed, `alpha` ticks past the current one for smooth rendering
+    /// between ticks.
+    pub fn time_of_day(&self, alpha: f32) -> f32 {
+        let ticks = self.tick_of_day as f64 + alpha as f64;
+        (ticks / self.ticks_per_day as f64).fract() as f32
+    }
+
+    /// Jumps to a fraction of the current day.
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        let fraction = time_of_day.rem_euclid(1.0) as f64;
+        let tick = (fraction * self.ticks_per_day as f64).round() as u64;
+        self.tick_of_day = tick % self.ticks_per_day;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_into_the_next_day() {
+        // Ten-second days at four ticks per second.
+        let mut time = WorldTime::new(3, 0.9, 10.0, 4.0);
+        assert_eq!(
+            (time.day, time.tick_of_day, time.ticks_per_day),
+            (3, 36, 40)
+        );
+        for _ in 0..4 {
+            time.tick();
+        }
+        assert_eq!((time.day, time.tick_of_day), (4, 0));
+        assert_eq!(time.time_of_day(0.0), 0.0);
+        assert!((time.time_of_day(0.5) - 0.0125).abs() < 1e-6);
+    }
+
+    #[test]
+    fn time_of_day_is_taken_modulo_one() {
+        let time = WorldTime::new(0, 1.25, 100.0, 1.0);
+        assert_eq!(time.tick_of_day, 25);
+        let time = WorldTime::new(0, -0.25, 100.0, 1.0);
+        assert_eq!(time.tick_of_day, 75);
+    }
+}
@@ -0,0 +1,132 @@
+use glam::{IVec2, Vec2, Vec3};
+
+/// Blocks across one cell of the climate noise; biomes are a few cells wide.
+const CLIMATE_SCALE: f32 = 256.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Plains,
+    Forest,
+    Desert,
+    Swamp,
+    Tundra,
+}
+
+/// How a biome changes the fog, relative to the sky-colored default.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FogOverride {
+    /// Multiplies the fog density.
+    pub density: f32,
+    /// Color blended over the horizon color by `tint_amount`. Linear RGB.
+    pub tint: Vec3,
+    pub tint_amount: f32,
+}
+
+impl FogOverride {
+    pub const NONE: FogOverride = FogOverride {
+        density: 1.0,
+        tint: Vec3::ZERO,
+        tint_amount: 0.0,
+    };
+
+    pub fn lerp(self, other: FogOverride, t: f32) -> FogOverride {
+        FogOverride {
+            density: self.density + (other.density - self.density) * t,
+            tint: self.tint.lerp(other.tint, t),
+            tint_amount: self.tint_amount + (other.tint_amount - self.tint_amount) * t,
+        }
+    }
+}
+
+impl Biome {
+    pub const ALL: [Biome; 5] = [
+        Biome::Plains,
+        Biome::Forest,
+        Biome::Desert,
+        Biome::Swamp,
+        Biome::Tundra,
+    ];
+
+    /// Biome of the column at `(x, z)`, picked from temperature and humidity noise.
+    pub fn at(seed: u64, x: i32, z: i32) -> Biome {
+        let position = Vec2::new(x as f32, z as f32) / CLIMATE_SCALE;
+        let temperature = value_noise(seed, position);
+        let humidity = value_noise(seed ^ 0x9e37_79b9_7f4a_7c15, position + 17.0);
+        match (temperature, humidity) {
+            (t, _) if t < 0.3 => Biome::Tundra,
+            (t, h) if t > 0.65 && h < 0.4 => Biome::Desert,
+            (_, h) if h > 0.7 => Biome::Swamp,
+            (_, h) if h > 0.5 => Biome::Forest,
+            _ => Biome::Plains,
+        }
+    }
+
+    pub fn fog(self) -> FogOverride {
+        match self {
+            Biome::Plains | Biome::Forest => FogOverride::NONE,
+            Biome::Desert => FogOverride {
+                density: 1.5,
+                tint: Vec3::new(0.85, 0.7, 0.45),
+                tint_amount: 0.4,
+            },
+            Biome::Swamp => FogOverride {
+                density: 3.0,
+                tint: Vec3::new(0.35, 0.42, 0.3),
+                tint_amount: 0.6,
+            },
+            Biome::Tundra => FogOverride {
+                density: 2.0,
+                tint: Vec3::new(0.85, 0.88, 0.92),
+                tint_amount: 0.5,
+            },
+        }
+    }
+}
+
+/// Smoothly interpolated hash of the integer lattice, in 0..1.
+fn value_noise(seed: u64, position: Vec2) -> f32 {
+    let cell = position.floor();
+    let t = position - cell;
+    let t = t * t * (3.0 - 2.0 * t);
+    let corner = |offset: IVec2| lattice(seed, cell.as_ivec2() + offset);
+    let bottom = corner(IVec2::ZERO) + (corner(IVec2::X) - corner(IVec2::ZERO)) * t.x;
+    let top = corner(IVec2::Y) + (corner(IVec2::ONE) - corner(IVec2::Y)) * t.x;
+    bottom + (top - bottom) * t.y
+}
+
+fn lattice(seed: u64, cell: IVec2) -> f32 {
+    let mut hash = seed ^ (cell.x as u32 as u64) << 32 ^ cell.y as u32 as u64;
+    hash = (hash ^ (hash >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash = (hash ^ (hash >> 33)).wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^= hash >> 33;
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn biomes_are_deterministic_and_varied() {
+        let sample = |seed| {
+            (-16..16)
+                .flat_map(|x| (-16..16).map(move |z| (x * 64, z * 64)))
+                .map(|(x, z)| Biome::at(seed, x, z))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sample(7), sample(7));
+        assert_ne!(sample(7), sample(8));
+        let found: HashSet<_> = sample(7).into_iter().collect();
+        assert!(found.len() >= 3, "{found:?}");
+    }
+
+    #[test]
+    fn neighboring_columns_mostly_share_a_biome() {
+        let changes = (0..1000)
+            .filter(|&x| Biome::at(1, x, 0) != Biome::at(1, x + 1, 0))
+            .count();
+        assert!(changes < 20, "{changes}");
+    }
+}
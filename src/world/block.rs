@@ -0,0 +1,594 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{IVec3, Vec3};
+
+#[repr(transparent)]
+#[derive(Clone, Copy, Pod, Zeroable, Default, Debug, PartialEq, Eq, Hash)]
+pub struct BlockId(pub u16);
+
+/// How a block's faces are drawn, in the order the layers are rendered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RenderLayer {
+    Opaque,
+    /// Fully opaque or fully transparent texels, alpha tested; draws like opaque geometry.
+    Cutout,
+    /// The top surface of water, reflecting and refracting the opaque scene. Not a block's
+    /// layer: the mesher moves water's top faces into it.
+    Water,
+    /// Blended over what is behind it, after everything opaque, without writing depth.
+    Translucent,
+}
+
+impl RenderLayer {
+    pub const ALL: [RenderLayer; 4] = [Self::Opaque, Self::Cutout, Self::Water, Self::Translucent];
+}
+
+/// Blocks that flow, see `simulation::fluids`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fluid {
+    Water,
+    Lava,
+}
+
+impl Fluid {
+    pub fn block(self) -> BlockId {
+        match self {
+            Self::Water => BlockId::WATER,
+            Self::Lava => BlockId::LAVA,
+        }
+    }
+
+    /// Levels lost per block of sideways flow; lava doesn't get as far.
+    pub fn level_step(self) -> u8 {
+        match self {
+            Self::Water => 1,
+            Self::Lava => 2,
+        }
+    }
+
+    /// Seconds between a change next to the fluid and the fluid reacting to it.
+    pub fn flow_delay(self) -> f32 {
+        match self {
+            Self::Water => 0.25,
+            Self::Lava => 1.5,
+        }
+    }
+
+    /// Whether flowing fluid between two sources becomes a source itself.
+    pub fn forms_sources(self) -> bool {
+        self == Self::Water
+    }
+}
+
+/// The outline a block is drawn with; anything but a cube leaves its neighbors' faces showing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Shape {
+    Cube,
+    /// Half a block, at the bottom or the top, see `Half`.
+    Slab,
+    /// A bottom slab with a step on it at the back, see `Facing`.
+    Stairs,
+    /// Two crossed planes, for plants.
+    Cross,
+    /// A model of its own from `assets/models`, by name.
+    Model(&'static str),
+}
+
+/// Horizontal direction a block faces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Facing {
+    /// Towards -Z.
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Facing {
+    /// Clockwise seen from above.
+    pub const ALL: [Facing; 4] = [Self::North, Self::East, Self::South, Self::West];
+
+    /// Closest facing to the horizontal part of `direction`.
+    pub fn from_direction(direction: Vec3) -> Self {
+        if direction.x.abs() > direction.z.abs() {
+            if direction.x > 0.0 {
+                Self::East
+            } else {
+                Self::West
+            }
+        } else if direction.z > 0.0 {
+            Self::South
+        } else {
+            Self::North
+        }
+    }
+
+    pub fn normal(self) -> IVec3 {
+        match self {
+            Self::North => IVec3::NEG_Z,
+            Self::East => IVec3::X,
+            Self::South => IVec3::Z,
+            Self::West => IVec3::NEG_X,
+        }
+    }
+
+    /// Quarter turns clockwise from north, seen from above.
+    pub fn quarter_turns(self) -> i32 {
+        self as i32
+    }
+
+    /// Turned counter-clockwise seen from above, like `Schematic::rotated`.
+    pub fn rotated(self, quarter_turns: i32) -> Self {
+        Self::ALL[(self as i32 - quarter_turns).rem_euclid(4) as usize]
+    }
+}
+
+/// Which half of its cell a slab or stairs fill.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Half {
+    Bottom,
+    Top,
+    /// Two slabs in one cell; stairs are never double.
+    Double,
+}
+
+/// The axis a log's grain runs along.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    /// First, so that logs without state stand upright.
+    Y,
+    X,
+    Z,
+}
+
+impl Axis {
+    pub fn of(normal: IVec3) -> Self {
+        if normal.x != 0 {
+            Self::X
+        } else if normal.z != 0 {
+            Self::Z
+        } else {
+            Self::Y
+        }
+    }
+}
+
+/// Named parts of a block's state, packed into its state bits in the order the block lists
+/// them, each starting out as its first value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Property {
+    Facing,
+    Half,
+    Axis,
+    /// Water fills the rest of the cell.
+    Waterlogged,
+}
+
+impl Property {
+    fn bits(self) -> u8 {
+        match self {
+            Self::Facing | Self::Half | Self::Axis => 2,
+            Self::Waterlogged => 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BlockProperties {
+    pub name: &'static str,
+    pub solid: bool,
+    /// Base color in sRGB, used where there is no texture (imports, exports, debug views).
+    pub color: [u8; 4],
+    pub layer: RenderLayer,
+    pub fluid: Option<Fluid>,
+    pub shape: Shape,
+    /// What the block's state bits mean, if it names them; fluids and crops use them as is.
+    pub states: &'static [Property],
+}
+
+pub const BLOCKS: &[BlockProperties] = &[
+    BlockProperties {
+        name: "air",
+        solid: false,
+        color: [0, 0, 0, 0],
+        layer: RenderLayer::Opaque,
+        fluid: None,
+        shape: Shape::Cube,
+        states: &[],
+    },
+    BlockProperties {
+        name: "stone",
+        solid: true,
+        color: [125, 125, 125, 255],
+        layer: RenderLayer::Opaque,
+        fluid: None,
+        shape: Shape::Cube,
+        states: &[],
+    },
+    BlockProperties {
+        name: "dirt",
+        solid: true,
+        color: [134, 96, 67, 255],
+        layer: RenderLayer::Opaque,
+        fluid: None,
+        shape: Shape::Cube,
+        states: &[],
+    },
+    BlockProperties {
+        name: "grass",
+        solid: true,
+        color: [95, 159, 53, 255],
+        layer: RenderLayer::Opaque,
+        fluid: None,
+        shape: Shape::Cube,
+        states: &[],
+    },
+    BlockProperties {
+        name: "water",
+        solid: false,
+        color: [48, 96, 200, 160],
+        layer: RenderLayer::Translucent,
+        fluid: Some(Fluid::Water),
+        shape: Shape::Cube,
+        states: &[],
+    },
+    BlockProperties {
+        name: "leaves",
+        solid: true,
+        color: [58, 122, 40, 255],
+        layer: RenderLayer::Cutout,
+        fluid: None,
+        shape: Shape::Cube,
+        states: &[],
+    },
+    BlockProperties {
+        name: "glass",
+        solid: true,
+        color: [200, 225, 235, 90],
+        layer: RenderLayer::Translucent,
+        fluid: None,
+        shape: Shape::Cube,
+        states: &[],
+    },
+    BlockProperties {
+        name: "lava",
+        solid: false,
+        color: [207, 92, 16, 255],
+        layer: RenderLayer::Opaque,
+        fluid: Some(Fluid::Lava),
+        shape: Shape::Cube,
+        states: &[],
+    },
+    BlockProperties {
+        name: "sand",
+        solid: true,
+        color: [219, 207, 163, 255],
+        layer: RenderLayer::Opaque,
+        fluid: None,
+        shape: Shape::Cube,
+        states: &[],
+    },
+    BlockProperties {
+        name: "gravel",
+        solid: true,
+        color: [136, 126, 122, 255],
+        layer: RenderLayer::Opaque,
+        fluid: None,
+        shape: Shape::Cube,
+        states: &[],
+    },
+    BlockProperties {
+        name: "log",
+        solid: true,
+        color: [102, 81, 51, 255],
+        layer: RenderLayer::Opaque,
+        fluid: None,
+        shape: Shape::Cube,
+        states: &[Property::Axis],
+    },
+    BlockProperties {
+        name: "wheat",
+        solid: false,
+        color: [196, 178, 70, 255],
+        layer: RenderLayer::Cutout,
+        fluid: None,
+        shape: Shape::Cross,
+        states: &[],
+    },
+    BlockProperties {
+        name: "stone_slab",
+        solid: true,
+        color: [150, 150, 150, 255],
+        layer: RenderLayer::Opaque,
+        fluid: None,
+        shape: Shape::Slab,
+        states: &[Property::Half, Property::Waterlogged],
+    },
+    BlockProperties {
+        name: "stone_stairs",
+        solid: true,
+        color: [140, 140, 140, 255],
+        layer: RenderLayer::Opaque,
+        fluid: None,
+        shape: Shape::Stairs,
+        states: &[Property::Facing, Property::Half, Property::Waterlogged],
+    },
+    BlockProperties {
+        name: "torch",
+        solid: false,
+        color: [255, 200, 80, 255],
+        layer: RenderLayer::Opaque,
+        fluid: None,
+        shape: Shape::Model("torch"),
+        states: &[],
+    },
+    BlockProperties {
+        name: "table",
+        solid: true,
+        color: [160, 120, 70, 255],
+        layer: RenderLayer::Opaque,
+        fluid: None,
+        shape: Shape::Model("table"),
+        states: &[],
+    },
+];
+
+/// Shared by every color block; their color lives in the id itself.
+pub const COLOR_BLOCK: BlockProperties = BlockProperties {
+    name: "color",
+    solid: true,
+    color: [255, 255, 255, 255],
+    layer: RenderLayer::Opaque,
+    fluid: None,
+    shape: Shape::Cube,
+    states: &[],
+};
+
+impl BlockId {
+    pub const AIR: BlockId = BlockId(0);
+    pub const STONE: BlockId = BlockId(1);
+    pub const DIRT: BlockId = BlockId(2);
+    pub const GRASS: BlockId = BlockId(3);
+    pub const WATER: BlockId = BlockId(4);
+    pub const LEAVES: BlockId = BlockId(5);
+    pub const GLASS: BlockId = BlockId(6);
+    pub const LAVA: BlockId = BlockId(7);
+    pub const SAND: BlockId = BlockId(8);
+    pub const GRAVEL: BlockId = BlockId(9);
+    pub const LOG: BlockId = BlockId(10);
+    pub const WHEAT: BlockId = BlockId(11);
+    pub const STONE_SLAB: BlockId = BlockId(12);
+    pub const STONE_STAIRS: BlockId = BlockId(13);
+    pub const TORCH: BlockId = BlockId(14);
+    pub const TABLE: BlockId = BlockId(15);
+
+    /// Ids with this bit set are color blocks carrying an RGB555 color in the low 15 bits.
+    pub const COLOR_FLAG: u16 = 0x8000;
+    /// Registered blocks carry a few bits of state above the block index, meaning what the
+    /// block makes of them: a fluid's level and whether it is falling, a crop's age, or the
+    /// `Property` values it lists. Every state is an id of its own: a chunk holds it in its
+    /// cell like any other block, and saved chunks give it a palette entry of its own, see
+    /// `chunk_codec::encode`.
+    pub const STATE_MASK: u16 = 0x7f00;
+    pub const STATE_SHIFT: u16 = 8;
+    /// Fluid levels run from 0, the source, to this, the thinnest flow.
+    pub const MAX_FLUID_LEVEL: u8 = 7;
+    /// State bit of fluid falling from above.
+    const FALLING_STATE: u8 = 0x8;
+
+    pub fn from_rgb(rgb: [u8; 3]) -> Self {
+        let [r, g, b] = rgb.map(|channel| (channel >> 3) as u16);
+        BlockId(Self::COLOR_FLAG | (r << 10) | (g << 5) | b)
+    }
+
+    pub fn is_color_block(self) -> bool {
+        self.0 & Self::COLOR_FLAG != 0
+    }
+
+    pub fn properties(self) -> &'static BlockProperties {
+        if self.is_color_block() {
+            return &COLOR_BLOCK;
+        }
+        BLOCKS.get(self.base().0 as usize).unwrap_or(&BLOCKS[0])
+    }
+
+    /// The block without its state.
+    pub fn base(self) -> BlockId {
+        if self.is_color_block() {
+            return self;
+        }
+        BlockId(self.0 & !Self::STATE_MASK)
+    }
+
+    /// State bits, see `STATE_MASK`; always 0 for color blocks.
+    pub fn state(self) -> u8 {
+        if self.is_color_block() {
+            return 0;
+        }
+        ((self.0 & Self::STATE_MASK) >> Self::STATE_SHIFT) as u8
+    }
+
+    pub fn with_state(self, state: u8) -> BlockId {
+        debug_assert!(
+            !self.is_color_block() && state as u16 <= Self::STATE_MASK >> Self::STATE_SHIFT
+        );
+        BlockId(self.base().0 | (state as u16) << Self::STATE_SHIFT)
+    }
+
+    /// Where `property` sits in the state bits, as (shift, bits), if the block has it.
+    fn property_bits(self, property: Property) -> Option<(u8, u8)> {
+        let mut shift = 0;
+        for &listed in self.properties().states {
+            if listed == property {
+                return Some((shift, listed.bits()));
+            }
+            shift += listed.bits();
+        }
+        None
+    }
+
+    /// Raw value of `property`, `None` if the block doesn't have it.
+    pub fn property(self, property: Property) -> Option<u8> {
+        let (shift, bits) = self.property_bits(property)?;
+        Some((self.state() >> shift) & ((1 << bits) - 1))
+    }
+
+    /// This block with `property` set to `value`; blocks without the property stay as they
+    /// are.
+    pub fn with_property(self, property: Property, value: u8) -> BlockId {
+        let Some((shift, bits)) = self.property_bits(property) else {
+            return self;
+        };
+        let mask = ((1 << bits) - 1) << shift;
+        self.with_state((self.state() & !mask) | ((value << shift) & mask))
+    }
+
+    pub fn facing(self) -> Option<Facing> {
+        self.property(Property::Facing)
+            .map(|value| Facing::ALL[value as usize])
+    }
+
+    pub fn with_facing(self, facing: Facing) -> BlockId {
+        self.with_property(Property::Facing, facing as u8)
+    }
+
+    pub fn half(self) -> Option<Half> {
+        self.property(Property::Half).map(|value| match value {
+            0 => Half::Bottom,
+            1 => Half::Top,
+            _ => Half::Double,
+        })
+    }
+
+    pub fn with_half(self, half: Half) -> BlockId {
+        self.with_property(Property::Half, half as u8)
+    }
+
+    pub fn axis(self) -> Option<Axis> {
+        self.property(Property::Axis).map(|value| match value {
+            0 => Axis::Y,
+            1 => Axis::X,
+            _ => Axis::Z,
+        })
+    }
+
+    pub fn with_axis(self, axis: Axis) -> BlockId {
+        self.with_property(Property::Axis, axis as u8)
+    }
+
+    pub fn is_waterlogged(self) -> bool {
+        self.property(Property::Waterlogged) == Some(1)
+    }
+
+    pub fn with_waterlogged(self, waterlogged: bool) -> BlockId {
+        self.with_property(Property::Waterlogged, waterlogged as u8)
+    }
+
+    /// This block turned counter-clockwise seen from above, like `Schematic::rotated`.
+    pub fn rotated(self, quarter_turns: i32) -> BlockId {
+        let mut block = self;
+        if let Some(facing) = self.facing() {
+            block = block.with_facing(facing.rotated(quarter_turns));
+        }
+        if quarter_turns.rem_euclid(2) == 1 {
+            match self.axis() {
+                Some(Axis::X) => block = block.with_axis(Axis::Z),
+                Some(Axis::Z) => block = block.with_axis(Axis::X),
+                _ => {}
+            }
+        }
+        block
+    }
+
+    pub fn fluid(self) -> Option<Fluid> {
+        self.properties().fluid
+    }
+
+    /// This fluid at `level`, falling or not.
+    pub fn with_fluid_state(self, level: u8, falling: bool) -> BlockId {
+        debug_assert!(self.fluid().is_some() && level <= Self::MAX_FLUID_LEVEL);
+        let falling = if falling { Self::FALLING_STATE } else { 0 };
+        self.with_state(level | falling)
+    }
+
+    /// 0 for sources and blocks that aren't fluids.
+    pub fn fluid_level(self) -> u8 {
+        if self.fluid().is_none() {
+            return 0;
+        }
+        self.state() & !Self::FALLING_STATE
+    }
+
+    pub fn is_falling(self) -> bool {
+        self.fluid().is_some() && self.state() & Self::FALLING_STATE != 0
+    }
+
+    pub fn is_fluid_source(self) -> bool {
+        self.fluid().is_some() && self.fluid_level() == 0 && !self.is_falling()
+    }
+
+    /// Height of the fluid's surface within its block: full for sources and falling fluid,
+    /// dropping with the level otherwise.
+    pub fn fluid_height(self) -> f32 {
+        if self.is_falling() {
+            return 1.0;
+        }
+        1.0 - self.fluid_level() as f32 / (Self::MAX_FLUID_LEVEL + 1) as f32
+    }
+
+    /// sRGB color of the block, decoded from the id for color blocks.
+    pub fn color(self) -> [u8; 4] {
+        if !self.is_color_block() {
+            return self.properties().color;
+        }
+        let expand = |shift: u16| {
+            let channel = ((self.0 >> shift) & 0x1f) as u8;
+            (channel << 3) | (channel >> 2)
+        };
+        [expand(10), expand(5), expand(0), 255]
+    }
+
+    /// Registered block with exactly this color, if any.
+    pub fn find_by_color(color: [u8; 4]) -> Option<Self> {
+        BLOCKS
+            .iter()
+            .position(|block| block.solid && block.color == color)
+            .map(|index| BlockId(index as u16))
+    }
+
+    /// Registered block with this name, if any.
+    pub fn find_by_name(name: &str) -> Option<Self> {
+        BLOCKS
+            .iter()
+            .position(|block| block.name == name)
+            .map(|index| BlockId(index as u16))
+    }
+
+    pub fn is_air(self) -> bool {
+        self == Self::AIR
+    }
+
+    pub fn is_solid(self) -> bool {
+        self.properties().solid
+    }
+
+    pub fn layer(self) -> RenderLayer {
+        self.properties().layer
+    }
+
+    /// The block's outline; double slabs are whole cubes.
+    pub fn shape(self) -> Shape {
+        match self.properties().shape {
+            Shape::Slab if self.half() == Some(Half::Double) => Shape::Cube,
+            shape => shape,
+        }
+    }
+
+    /// Whether the block hides the faces of its neighbors: a solid, fully opaque cube.
+    pub fn is_opaque(self) -> bool {
+        self.is_solid() && self.layer() == RenderLayer::Opaque && self.shape() == Shape::Cube
+    }
+
+    pub fn is_water(self) -> bool {
+        self.fluid() == Some(Fluid::Water)
+    }
+}
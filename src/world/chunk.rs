@@ -0,0 +1,98 @@
+use std::collections::BTreeSet;
+
+use glam::IVec3;
+
+use crate::world::block::BlockId;
+
+pub const CHUNK_SIZE: i32 = 32;
+pub const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// A cube of blocks, one `BlockId` with its state per cell. Only saved chunks are stored
+/// compactly, as a palette of the distinct blocks and bit-packed indices into it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chunk {
+    blocks: Box<[BlockId]>,
+    /// Block ticks due in this chunk, by tick, then block index. Saved with the blocks.
+    scheduled: BTreeSet<(u64, u16)>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self::filled(BlockId::AIR)
+    }
+
+    pub fn filled(block: BlockId) -> Self {
+        Chunk {
+            blocks: vec![block; CHUNK_VOLUME].into_boxed_slice(),
+            scheduled: BTreeSet::new(),
+        }
+    }
+
+    pub fn index(local: IVec3) -> usize {
+        debug_assert!(
+            local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(CHUNK_SIZE)).all()
+        );
+        (local.x + local.z * CHUNK_SIZE + local.y * CHUNK_SIZE * CHUNK_SIZE) as usize
+    }
+
+    /// Inverse of `index`.
+    pub fn local_pos(index: usize) -> IVec3 {
+        let index = index as i32;
+        IVec3::new(
+            index % CHUNK_SIZE,
+            index / (CHUNK_SIZE * CHUNK_SIZE),
+            (index / CHUNK_SIZE) % CHUNK_SIZE,
+        )
+    }
+
+    pub fn get(&self, local: IVec3) -> BlockId {
+        self.blocks[Self::index(local)]
+    }
+
+    pub fn set(&mut self, local: IVec3, block: BlockId) {
+        self.blocks[Self::index(local)] = block;
+    }
+
+    pub fn blocks(&self) -> &[BlockId] {
+        &self.blocks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.iter().all(|block| block.is_air())
+    }
+
+    /// Schedules the block at `local` to tick at `tick`.
+    pub fn schedule(&mut self, local: IVec3, tick: u64) {
+        self.scheduled.insert((tick, Self::index(local) as u16));
+    }
+
+    /// Scheduled ticks as (tick, local position), soonest first.
+    pub fn scheduled(&self) -> impl Iterator<Item = (u64, IVec3)> + '_ {
+        self.scheduled
+            .iter()
+            .map(|&(tick, index)| (tick, Self::local_pos(index as usize)))
+    }
+
+    pub fn has_scheduled(&self) -> bool {
+        !self.scheduled.is_empty()
+    }
+
+    /// Removes up to `limit` ticks due by `now` and returns their positions.
+    pub fn take_due(&mut self, now: u64, limit: usize) -> Vec<IVec3> {
+        let mut due = Vec::new();
+        while due.len() < limit
+            && let Some(&(tick, index)) = self.scheduled.first()
+            && tick <= now
+        {
+            self.scheduled.pop_first();
+            due.push(Self::local_pos(index as usize));
+        }
+        due
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
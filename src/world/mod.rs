@@ -0,0 +1,8 @@
+pub mod biome;
+pub mod block;
+pub mod chunk;
+pub mod placement;
+pub mod schematic;
+pub mod storage;
+#[allow(clippy::module_inception)]
+pub mod world;
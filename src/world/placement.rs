@@ -0,0 +1,170 @@
+use glam::{IVec3, Vec3};
+
+use crate::{
+    physics::raycast::RayHit,
+    world::{
+        block::{Axis, BLOCKS, BlockId, Facing, Half, Shape},
+        world::World,
+    },
+};
+
+/// Where `block` goes when put against the side of the block in `hit`, looking along `look`,
+/// and the state it goes in there:
+/// - stairs face the way the player looks, so they can be walked up;
+/// - slabs and stairs take the top half against the underside of a block or the upper half of
+///   its side, and a slab put on the open half of one of its kind makes it a double slab;
+/// - logs lie along the axis of the side they are put against;
+/// - blocks that can be waterlogged are when they go into water that is a source.
+pub fn placement(world: &World, hit: &RayHit, look: Vec3, block: BlockId) -> (IVec3, BlockId) {
+    let target = world.get_block(hit.pos);
+    let open_half = match hit.normal {
+        IVec3::Y => Some(Half::Bottom),
+        IVec3::NEG_Y => Some(Half::Top),
+        _ => None,
+    };
+    if block.shape() == Shape::Slab && target.base() == block.base() && target.half() == open_half {
+        return (
+            hit.pos,
+            target.with_half(Half::Double).with_waterlogged(false),
+        );
+    }
+
+    let pos = hit.pos + hit.normal;
+    let height = hit.point.y - pos.y as f32;
+    let half = if hit.normal == IVec3::NEG_Y || (hit.normal.y == 0 && height > 0.5) {
+        Half::Top
+    } else {
+        Half::Bottom
+    };
+    let placed = block
+        .with_facing(Facing::from_direction(look))
+        .with_half(half)
+        .with_axis(Axis::of(hit.normal))
+        .with_waterlogged(world.get_block(pos) == BlockId::WATER);
+    (pos, placed)
+}
+
+/// Registered blocks a player can hold: everything but air and fluids.
+pub fn placeable_blocks() -> impl Iterator<Item = BlockId> {
+    (0..BLOCKS.len() as u16)
+        .map(BlockId)
+        .filter(|block| !block.is_air() && block.fluid().is_none())
+}
+
+/// The placeable block `step` places along from `held`, wrapping around; the first one if
+/// `held` isn't placeable.
+pub fn cycle_held_block(held: BlockId, step: i32) -> BlockId {
+    let blocks: Vec<BlockId> = placeable_blocks().collect();
+    let Some(index) = blocks.iter().position(|block| *block == held.base()) else {
+        return blocks[0];
+    };
+    blocks[(index as i32 + step).rem_euclid(blocks.len() as i32) as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::raycast::raycast;
+
+    fn place(world: &mut World, origin: Vec3, look: Vec3, block: BlockId) -> IVec3 {
+        let hit = raycast(world, origin, look, 8.0).unwrap();
+        let (pos, block) = placement(world, &hit, look, block);
+        world.set_block(pos, block);
+        pos
+    }
+
+    #[test]
+    fn held_blocks_cycle_through_placeable_blocks() {
+        let placeable: Vec<_> = placeable_blocks().collect();
+        assert!(placeable.contains(&BlockId::STONE_STAIRS) && placeable.contains(&BlockId::TABLE));
+        assert!(!placeable.contains(&BlockId::WATER) && !placeable.contains(&BlockId::AIR));
+
+        let last = *placeable.last().unwrap();
+        assert_eq!(cycle_held_block(last, 1), placeable[0]);
+        assert_eq!(cycle_held_block(placeable[0], -1), last);
+        assert_eq!(cycle_held_block(BlockId::STONE, 1), BlockId::DIRT);
+        assert_eq!(cycle_held_block(BlockId::WATER, 1), placeable[0]);
+    }
+
+    #[test]
+    fn stairs_face_the_way_the_player_looks() {
+        let mut world = World::flat(1);
+        let look = Vec3::new(0.9, -0.5, 0.1);
+        let pos = place(
+            &mut world,
+            Vec3::new(0.5, 1.6, 0.5),
+            look,
+            BlockId::STONE_STAIRS,
+        );
+        let stairs = world.get_block(pos);
+        assert_eq!(pos.y, 0);
+        assert_eq!(stairs.facing(), Some(Facing::East));
+        assert_eq!(stairs.half(), Some(Half::Bottom));
+    }
+
+    #[test]
+    fn halves_follow_where_the_side_was_hit() {
+        let mut world = World::flat(1);
+        world.set_block(IVec3::new(3, 0, 0), BlockId::STONE);
+        let slab = BlockId::STONE_SLAB;
+        let high = place(&mut world, Vec3::new(0.5, 0.8, 0.5), Vec3::X, slab);
+        assert_eq!(high, IVec3::new(2, 0, 0));
+        assert_eq!(world.get_block(high).half(), Some(Half::Top));
+
+        world.set_block(high, BlockId::AIR);
+        place(&mut world, Vec3::new(0.5, 0.2, 0.5), Vec3::X, slab);
+        assert_eq!(world.get_block(high).half(), Some(Half::Bottom));
+
+        // A second slab from above fills the top half.
+        let merged = place(&mut world, Vec3::new(2.5, 3.0, 0.5), Vec3::NEG_Y, slab);
+        assert_eq!(merged, high);
+        assert_eq!(world.get_block(high).half(), Some(Half::Double));
+    }
+
+    #[test]
+    fn logs_lie_along_the_side_they_are_put_against() {
+        let mut world = World::flat(1);
+        world.set_block(IVec3::new(0, 0, -3), BlockId::STONE);
+        let pos = place(
+            &mut world,
+            Vec3::new(0.5, 0.5, 0.5),
+            Vec3::NEG_Z,
+            BlockId::LOG,
+        );
+        assert_eq!(world.get_block(pos).axis(), Some(Axis::Z));
+        let pos = place(
+            &mut world,
+            Vec3::new(3.5, 2.0, 3.5),
+            Vec3::NEG_Y,
+            BlockId::LOG,
+        );
+        assert_eq!(world.get_block(pos), BlockId::LOG);
+    }
+
+    #[test]
+    fn blocks_put_into_water_are_waterlogged() {
+        let mut world = World::flat(1);
+        world.set_block(IVec3::new(0, 0, 0), BlockId::WATER);
+        world.set_block(
+            IVec3::new(1, 0, 0),
+            BlockId::WATER.with_fluid_state(1, false),
+        );
+        let look = Vec3::NEG_Y;
+        let pos = place(
+            &mut world,
+            Vec3::new(0.5, 2.0, 0.5),
+            look,
+            BlockId::STONE_SLAB,
+        );
+        assert!(world.get_block(pos).is_waterlogged());
+        let pos = place(
+            &mut world,
+            Vec3::new(1.5, 2.0, 0.5),
+            look,
+            BlockId::STONE_SLAB,
+        );
+        assert!(!world.get_block(pos).is_waterlogged());
+        let pos = place(&mut world, Vec3::new(0.5, 2.0, 1.5), look, BlockId::STONE);
+        assert_eq!(world.get_block(pos), BlockId::STONE);
+    }
+}
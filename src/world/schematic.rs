@@ -0,0 +1,402 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use glam::{IVec3, UVec3};
+
+use crate::world::{
+    block::{BlockId, Facing},
+    storage::{atomic_write::write_atomic, error::StorageError},
+    world::World,
+};
+
+const MAGIC: &[u8; 4] = b"VXSC";
+const FORMAT_VERSION: u8 = 1;
+/// Palette index marking a masked cell.
+const MASKED: u16 = u16::MAX;
+/// Most cells a schematic may have, 256 blocks cubed, so a file can't claim an allocation of
+/// any size.
+pub const MAX_VOLUME: usize = 1 << 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MirrorAxis {
+    X,
+    Z,
+}
+
+/// A box of blocks copied out of a world. Masked cells (`None`) leave the world untouched when
+/// pasted, so captured air can either carve out space or be ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schematic {
+    pub size: UVec3,
+    cells: Vec<Option<BlockId>>,
+}
+
+/// Number of cells in a box of `size`, or `None` past `MAX_VOLUME`.
+fn volume(size: UVec3) -> Option<usize> {
+    (size.x as usize)
+        .checked_mul(size.y as usize)?
+        .checked_mul(size.z as usize)
+        .filter(|volume| *volume <= MAX_VOLUME)
+}
+
+impl Schematic {
+    /// An all-masked schematic.
+    ///
+    /// # Panics
+    ///
+    /// If `size` holds more than `MAX_VOLUME` cells.
+    pub fn new(size: UVec3) -> Self {
+        let volume = volume(size)
+            .unwrap_or_else(|| panic!("schematic of {size} is over {MAX_VOLUME} cells"));
+        Schematic {
+            size,
+            cells: vec![None; volume],
+        }
+    }
+
+    /// Copies the inclusive box `min..=max`; with `mask_air` air cells are masked out. `None`
+    /// if the box holds more than `MAX_VOLUME` cells.
+    pub fn capture(world: &World, min: IVec3, max: IVec3, mask_air: bool) -> Option<Self> {
+        let (min, max) = (min.min(max), min.max(max));
+        let size = max.checked_sub(min)?.checked_add(IVec3::ONE)?.as_uvec3();
+        volume(size)?;
+        let mut schematic = Self::new(size);
+        for y in 0..schematic.size.y {
+            for z in 0..schematic.size.z {
+                for x in 0..schematic.size.x {
+                    let local = UVec3::new(x, y, z);
+                    let block = world.get_block(min + local.as_ivec3());
+                    if !(mask_air && block.is_air()) {
+                        schematic.set(local, Some(block));
+                    }
+                }
+            }
+        }
+        Some(schematic)
+    }
+
+    fn index(&self, local: UVec3) -> usize {
+        (local.x + local.z * self.size.x + local.y * self.size.x * self.size.z) as usize
+    }
+
+    pub fn get(&self, local: UVec3) -> Option<BlockId> {
+        self.cells[self.index(local)]
+    }
+
+    pub fn set(&mut self, local: UVec3, block: Option<BlockId>) {
+        let index = self.index(local);
+        self.cells[index] = block;
+    }
+
+    /// Unmasked cells with their positions relative to the minimum corner.
+    pub fn blocks(&self) -> impl Iterator<Item = (UVec3, BlockId)> + '_ {
+        let size = self.size;
+        self.cells.iter().enumerate().filter_map(move |(i, cell)| {
+            let i = i as u32;
+            let local = UVec3::new(i % size.x, i / (size.x * size.z), (i / size.x) % size.z);
+            cell.map(|block| (local, block))
+        })
+    }
+
+    /// Writes every unmasked cell with the minimum corner at `offset`.
+    pub fn paste(&self, world: &mut World, offset: IVec3) {
+        for (local, block) in self.blocks() {
+            world.set_block(offset + local.as_ivec3(), block);
+        }
+    }
+
+    /// Rotates counter-clockwise around +Y (seen from above) in 90 degree steps.
+    pub fn rotated(&self, quarter_turns: i32) -> Self {
+        match quarter_turns.rem_euclid(4) {
+            0 => self.clone(),
+            turns => {
+                let mut rotated = Self::new(UVec3::new(self.size.z, self.size.y, self.size.x));
+                for (local, block) in self.blocks() {
+                    let target = UVec3::new(local.z, local.y, self.size.x - 1 - local.x);
+                    rotated.set(target, Some(block.rotated(1)));
+                }
+                rotated.rotated(turns - 1)
+            }
+        }
+    }
+
+    pub fn mirrored(&self, axis: MirrorAxis) -> Self {
+        let mut mirrored = Self::new(self.size);
+        for (local, block) in self.blocks() {
+            let target = match axis {
+                MirrorAxis::X => UVec3::new(self.size.x - 1 - local.x, local.y, local.z),
+                MirrorAxis::Z => UVec3::new(local.x, local.y, self.size.z - 1 - local.z),
+            };
+            let block = match (axis, block.facing()) {
+                (MirrorAxis::X, Some(facing @ (Facing::East | Facing::West)))
+                | (MirrorAxis::Z, Some(facing @ (Facing::North | Facing::South))) => {
+                    block.with_facing(facing.rotated(2))
+                }
+                _ => block,
+            };
+            mirrored.set(target, Some(block));
+        }
+        mirrored
+    }
+
+    pub fn load(path: &Path) -> Result<Self, StorageError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), StorageError> {
+        write_atomic(path, &self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Layout (little endian): magic `VXSC`, `u8` version, three `u32` sizes, `u16` palette
+    /// length and ids, then the lz4-compressed `u16` palette index of every cell, with
+    /// `u16::MAX` for masked cells.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut palette = Vec::new();
+        let mut lookup = HashMap::new();
+        let mut indices = Vec::with_capacity(self.cells.len() * 2);
+        for cell in &self.cells {
+            let index = match cell {
+                Some(block) => *lookup.entry(*block).or_insert_with(|| {
+                    palette.push(*block);
+                    palette.len() as u16 - 1
+                }),
+                None => MASKED,
+            };
+            indices.extend_from_slice(&index.to_le_bytes());
+        }
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(FORMAT_VERSION);
+        for axis in self.size.to_array() {
+            bytes.extend_from_slice(&axis.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(palette.len() as u16).to_le_bytes());
+        for block in palette {
+            bytes.extend_from_slice(&block.0.to_le_bytes());
+        }
+        bytes.extend_from_slice(&lz4_flex::compress_prepend_size(&indices));
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StorageError> {
+        let corrupt = |reason: &str| StorageError::Corrupt(format!("schematic {reason}"));
+        if bytes.len() < 19 || &bytes[..4] != MAGIC {
+            return Err(corrupt("has an invalid header"));
+        }
+        if bytes[4] != FORMAT_VERSION {
+            return Err(corrupt("has an unsupported version"));
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let size = UVec3::new(u32_at(5), u32_at(9), u32_at(13));
+
+        let palette_len = u16::from_le_bytes([bytes[17], bytes[18]]) as usize;
+        let palette_end = 19 + palette_len * 2;
+        let palette: Vec<BlockId> = bytes
+            .get(19..palette_end)
+            .ok_or_else(|| corrupt("palette is truncated"))?
+            .chunks_exact(2)
+            .map(|id| BlockId(u16::from_le_bytes([id[0], id[1]])))
+            .collect();
+
+        let volume = volume(size).ok_or_else(|| corrupt("is too large"))?;
+        // The compressed cells start with their decompressed length, checked before anything
+        // is allocated for them.
+        let cells = &bytes[palette_end..];
+        let length = cells
+            .get(..4)
+            .map(|length| u32::from_le_bytes(length.try_into().unwrap()) as usize);
+        if length != Some(volume * 2) {
+            return Err(corrupt("cell count doesn't match its size"));
+        }
+        let indices = lz4_flex::decompress_size_prepended(cells)
+            .map_err(|_| corrupt("cells failed to decompress"))?;
+        if indices.len() != volume * 2 {
+            return Err(corrupt("cell count doesn't match its size"));
+        }
+
+        let cells = indices
+            .chunks_exact(2)
+            .map(|index| match u16::from_le_bytes([index[0], index[1]]) {
+                MASKED => Ok(None),
+                index => palette
+                    .get(index as usize)
+                    .map(|block| Some(*block))
+                    .ok_or_else(|| corrupt("references a missing palette entry")),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Schematic { size, cells })
+    }
+}
+
+/// A schematic used for world decoration, placed relative to an anchor cell such as the
+/// bottom of a tree trunk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructureTemplate {
+    pub schematic: Schematic,
+    pub anchor: UVec3,
+}
+
+impl StructureTemplate {
+    pub fn load(path: &Path, anchor: UVec3) -> Result<Self, StorageError> {
+        Ok(StructureTemplate {
+            schematic: Schematic::load(path)?,
+            anchor,
+        })
+    }
+
+    /// Pastes the template rotated by `quarter_turns` with its anchor cell at `pos`.
+    pub fn place(&self, world: &mut World, pos: IVec3, quarter_turns: i32) {
+        let mut anchor = self.anchor;
+        let mut size = self.schematic.size;
+        for _ in 0..quarter_turns.rem_euclid(4) {
+            anchor = UVec3::new(anchor.z, anchor.y, size.x - 1 - anchor.x);
+            size = UVec3::new(size.z, size.y, size.x);
+        }
+        self.schematic
+            .rotated(quarter_turns)
+            .paste(world, pos - anchor.as_ivec3());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::Axis;
+
+    /// 3x2x2 asymmetric shape with air inside its bounds.
+    fn sample_world() -> World {
+        let mut world = World::new();
+        world.set_block(IVec3::new(10, 0, 10), BlockId::STONE);
+        world.set_block(IVec3::new(11, 0, 10), BlockId::DIRT);
+        world.set_block(IVec3::new(12, 0, 10), BlockId::GRASS);
+        world.set_block(IVec3::new(10, 1, 11), BlockId::STONE);
+        world
+    }
+
+    fn capture(mask_air: bool) -> Schematic {
+        Schematic::capture(
+            &sample_world(),
+            IVec3::new(10, 0, 10),
+            IVec3::new(12, 1, 11),
+            mask_air,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn refuses_to_capture_oversized_boxes() {
+        let world = sample_world();
+        let capture = |max| Schematic::capture(&world, IVec3::ZERO, max, true);
+        assert!(capture(IVec3::new(255, 255, 256)).is_none());
+        assert!(Schematic::capture(&world, IVec3::MIN, IVec3::MAX, true).is_none());
+    }
+
+    #[test]
+    fn masked_air_keeps_existing_blocks() {
+        let mut target = World::new();
+        target.set_block(IVec3::new(1, 0, 1), BlockId::GRASS);
+
+        capture(true).paste(&mut target, IVec3::ZERO);
+        assert_eq!(target.get_block(IVec3::new(1, 0, 1)), BlockId::GRASS);
+        assert_eq!(target.get_block(IVec3::new(2, 0, 0)), BlockId::GRASS);
+
+        capture(false).paste(&mut target, IVec3::ZERO);
+        assert_eq!(target.get_block(IVec3::new(1, 0, 1)), BlockId::AIR);
+    }
+
+    #[test]
+    fn rotates_counter_clockwise() {
+        let schematic = capture(true);
+        let rotated = schematic.rotated(1);
+        assert_eq!(rotated.size, UVec3::new(2, 2, 3));
+        // +X end of the row moves to -Z.
+        assert_eq!(rotated.get(UVec3::new(0, 0, 0)), Some(BlockId::GRASS));
+        assert_eq!(rotated.get(UVec3::new(0, 0, 2)), Some(BlockId::STONE));
+        assert_eq!(schematic.rotated(4), schematic);
+        assert_eq!(schematic.rotated(-1), schematic.rotated(3));
+    }
+
+    #[test]
+    fn mirrors() {
+        let schematic = capture(true);
+        let mirrored = schematic.mirrored(MirrorAxis::X);
+        assert_eq!(mirrored.get(UVec3::new(0, 0, 0)), Some(BlockId::GRASS));
+        assert_eq!(mirrored.mirrored(MirrorAxis::X), schematic);
+        assert_eq!(
+            schematic.mirrored(MirrorAxis::Z).get(UVec3::new(0, 1, 0)),
+            Some(BlockId::STONE)
+        );
+    }
+
+    #[test]
+    fn oriented_blocks_turn_with_the_schematic() {
+        let mut schematic = Schematic::new(UVec3::new(2, 1, 1));
+        let stairs = BlockId::STONE_STAIRS.with_facing(Facing::East);
+        let log = BlockId::LOG.with_axis(Axis::X);
+        schematic.set(UVec3::new(0, 0, 0), Some(stairs));
+        schematic.set(UVec3::new(1, 0, 0), Some(log));
+
+        let rotated = schematic.rotated(1);
+        assert_eq!(
+            rotated.get(UVec3::new(0, 0, 1)),
+            Some(stairs.with_facing(Facing::North))
+        );
+        assert_eq!(
+            rotated.get(UVec3::new(0, 0, 0)),
+            Some(log.with_axis(Axis::Z))
+        );
+        let mirrored = schematic.mirrored(MirrorAxis::X);
+        assert_eq!(
+            mirrored.get(UVec3::new(1, 0, 0)),
+            Some(stairs.with_facing(Facing::West))
+        );
+        assert_eq!(schematic.mirrored(MirrorAxis::Z), schematic);
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        for mask_air in [true, false] {
+            let schematic = capture(mask_air);
+            assert_eq!(
+                Schematic::from_bytes(&schematic.to_bytes()).unwrap(),
+                schematic
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_sizes_that_overflow_or_claim_too_much() {
+        let bytes = capture(true).to_bytes();
+        for size in [[u32::MAX; 3], [1 << 16, 1 << 16, 1 << 16], [4096, 4096, 2]] {
+            let mut bytes = bytes.clone();
+            for (axis, value) in size.iter().enumerate() {
+                bytes[5 + axis * 4..9 + axis * 4].copy_from_slice(&value.to_le_bytes());
+            }
+            assert!(Schematic::from_bytes(&bytes).is_err(), "{size:?}");
+        }
+
+        // A cell stream claiming 4 GiB is turned down before it is decompressed.
+        let mut bytes = bytes;
+        let palette_len = u16::from_le_bytes([bytes[17], bytes[18]]) as usize;
+        let cells = 19 + palette_len * 2;
+        bytes[cells..cells + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Schematic::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn template_places_anchor_at_position() {
+        let template = StructureTemplate {
+            schematic: capture(true),
+            anchor: UVec3::new(2, 0, 0),
+        };
+        for turns in 0..4 {
+            let mut world = World::new();
+            template.place(&mut world, IVec3::new(5, 5, 5), turns);
+            assert_eq!(
+                world.get_block(IVec3::new(5, 5, 5)),
+                BlockId::GRASS,
+                "{turns}"
+            );
+        }
+    }
+}
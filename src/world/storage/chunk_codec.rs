@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use glam::IVec3;
+
+use crate::world::{
+    block::BlockId,
+    chunk::{CHUNK_VOLUME, Chunk},
+    storage::error::StorageError,
+};
+
+const FORMAT_VERSION: u8 = 2;
+/// Version 1 had no scheduled ticks.
+const OLDEST_VERSION: u8 = 1;
+
+/// Scheduled ticks saved per chunk, soonest first. Eight per block is far more than block
+/// updates ever have waiting.
+const MAX_SCHEDULED: usize = 8 * CHUNK_VOLUME;
+/// Widest palette index: every block of the chunk different.
+const MAX_INDEX_BITS: usize = CHUNK_VOLUME.ilog2() as usize;
+/// Longest chunk encoding: a palette entry for every block, the widest indices and as many
+/// scheduled ticks as are saved.
+pub const MAX_ENCODED_LEN: usize =
+    4 + CHUNK_VOLUME * 2 + CHUNK_VOLUME.div_ceil(64 / MAX_INDEX_BITS) * 8 + 4 + MAX_SCHEDULED * 10;
+
+/// Serializes a chunk as a palette of distinct blocks plus bit-packed palette indices.
+/// Indices never straddle a `u64` word.
+///
+/// Layout (little endian): `u8` version, `u16` palette length, `u16` block id per palette
+/// entry, `u8` bits per index, the packed `u64` words, then a `u32` count of scheduled ticks
+/// and a `u64` tick and `u16` block index for each.
+pub fn encode(chunk: &Chunk) -> Vec<u8> {
+    let mut palette = Vec::new();
+    let mut lookup = HashMap::new();
+    let indices: Vec<u64> = chunk
+        .blocks()
+        .iter()
+        .map(|block| {
+            *lookup.entry(*block).or_insert_with(|| {
+                palette.push(*block);
+                palette.len() as u64 - 1
+            })
+        })
+        .collect();
+
+    let bits = bits_for(palette.len());
+    let mut bytes = Vec::with_capacity(4 + palette.len() * 2 + word_count(bits) * 8);
+    bytes.push(FORMAT_VERSION);
+    bytes.extend_from_slice(&(palette.len() as u16).to_le_bytes());
+    for block in &palette {
+        bytes.extend_from_slice(&block.0.to_le_bytes());
+    }
+    bytes.push(bits as u8);
+
+    if let Some(per_word) = 64usize.checked_div(bits) {
+        for group in indices.chunks(per_word) {
+            let word = group
+                .iter()
+                .enumerate()
+                .fold(0u64, |word, (i, index)| word | (index << (i * bits)));
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    let scheduled: Vec<(u64, IVec3)> = chunk.scheduled().take(MAX_SCHEDULED).collect();
+    bytes.extend_from_slice(&(scheduled.len() as u32).to_le_bytes());
+    for (tick, local) in scheduled {
+        bytes.extend_from_slice(&tick.to_le_bytes());
+        bytes.extend_from_slice(&(Chunk::index(local) as u16).to_le_bytes());
+    }
+    bytes
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Chunk, StorageError> {
+    let mut reader = Reader { bytes, position: 0 };
+
+    let version = reader.u8()?;
+    if !(OLDEST_VERSION..=FORMAT_VERSION).contains(&version) {
+        return Err(StorageError::Corrupt(format!(
+            "unsupported chunk format version {version}"
+        )));
+    }
+
+    let palette_len = reader.u16()? as usize;
+    if palette_len == 0 {
+        return Err(StorageError::Corrupt("empty chunk palette".to_string()));
+    }
+    let palette = (0..palette_len)
+        .map(|_| reader.u16().map(BlockId))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let bits = reader.u8()? as usize;
+    if bits != bits_for(palette_len) {
+        return Err(StorageError::Corrupt(format!(
+            "{bits} bits per index for a palette of {palette_len}"
+        )));
+    }
+
+    let mut chunk = Chunk::filled(palette[0]);
+    let per_word = 64usize.checked_div(bits).unwrap_or(0);
+    let mask = (1u64 << bits) - 1;
+    for word_index in 0..word_count(bits) {
+        let word = reader.u64()?;
+        for i in 0..per_word {
+            let block_index = word_index * per_word + i;
+            if block_index >= CHUNK_VOLUME {
+                break;
+            }
+            let palette_index = ((word >> (i * bits)) & mask) as usize;
+            let block = *palette.get(palette_index).ok_or_else(|| {
+                StorageError::Corrupt(format!("palette index {palette_index} out of range"))
+            })?;
+            chunk.set(Chunk::local_pos(block_index), block);
+        }
+    }
+
+    if version >= 2 {
+        let scheduled = reader.u32()?;
+        if scheduled as usize > MAX_SCHEDULED {
+            return Err(StorageError::Corrupt(format!(
+                "{scheduled} scheduled ticks"
+            )));
+        }
+        for _ in 0..scheduled {
+            let tick = reader.u64()?;
+            let index = reader.u16()? as usize;
+            if index >= CHUNK_VOLUME {
+                return Err(StorageError::Corrupt(format!(
+                    "scheduled tick for block {index}"
+                )));
+            }
+            chunk.schedule(Chunk::local_pos(index), tick);
+        }
+    }
+    Ok(chunk)
+}
+
+fn bits_for(palette_len: usize) -> usize {
+    match palette_len {
+        0 | 1 => 0,
+        len => (usize::BITS - (len - 1).leading_zeros()) as usize,
+    }
+}
+
+fn word_count(bits: usize) -> usize {
+    64usize
+        .checked_div(bits)
+        .map_or(0, |per_word| CHUNK_VOLUME.div_ceil(per_word))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], StorageError> {
+        let end = self.position + N;
+        let slice = self
+            .bytes
+            .get(self.position..end)
+            .ok_or_else(|| StorageError::Corrupt("chunk data truncated".to_string()))?;
+        self.position = end;
+        Ok(slice.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, StorageError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, StorageError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, StorageError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, StorageError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block::{Facing, Half};
+
+    #[test]
+    fn round_trips_palettes_of_every_width() {
+        for distinct in [1u16, 2, 3, 17, 300] {
+            let mut chunk = Chunk::new();
+            for i in 0..CHUNK_VOLUME {
+                let block = BlockId(((i * 7 + i / 5) % distinct as usize) as u16);
+                chunk.set(Chunk::local_pos(i), block);
+            }
+            assert_eq!(decode(&encode(&chunk)).unwrap(), chunk, "{distinct} blocks");
+        }
+    }
+
+    #[test]
+    fn block_states_take_palette_entries_of_their_own() {
+        let mut chunk = Chunk::filled(BlockId::STONE_SLAB);
+        let stairs = BlockId::STONE_STAIRS
+            .with_facing(Facing::West)
+            .with_half(Half::Top)
+            .with_waterlogged(true);
+        chunk.set(IVec3::new(3, 4, 5), stairs);
+        chunk.set(
+            IVec3::new(3, 5, 5),
+            BlockId::STONE_SLAB.with_half(Half::Top),
+        );
+        let bytes = encode(&chunk);
+        assert_eq!(u16::from_le_bytes([bytes[1], bytes[2]]), 3);
+
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded, chunk);
+        let block = decoded.get(IVec3::new(3, 4, 5));
+        assert_eq!(block.facing(), Some(Facing::West));
+        assert_eq!(block.half(), Some(Half::Top));
+        assert!(block.is_waterlogged());
+    }
+
+    #[test]
+    fn round_trips_scheduled_ticks() {
+        let mut chunk = Chunk::filled(BlockId::STONE);
+        chunk.schedule(IVec3::new(31, 0, 5), 1 << 40);
+        chunk.schedule(IVec3::new(0, 31, 0), 7);
+        let decoded = decode(&encode(&chunk)).unwrap();
+        assert_eq!(
+            decoded.scheduled().collect::<Vec<_>>(),
+            [(7, IVec3::new(0, 31, 0)), (1 << 40, IVec3::new(31, 0, 5))]
+        );
+    }
+
+    #[test]
+    fn reads_chunks_saved_before_scheduled_ticks() {
+        let mut chunk = Chunk::new();
+        chunk.set(IVec3::new(4, 5, 6), BlockId::DIRT);
+        let mut bytes = encode(&chunk);
+        bytes[0] = 1;
+        bytes.truncate(bytes.len() - 4);
+        assert_eq!(decode(&bytes).unwrap(), chunk);
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let mut chunk = Chunk::new();
+        chunk.set(IVec3::new(1, 2, 3), BlockId::STONE);
+        let bytes = encode(&chunk);
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
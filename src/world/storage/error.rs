@@ -0,0 +1,30 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    Corrupt(String),
+    MetadataParse(toml::de::Error),
+    MetadataSerialize(toml::ser::Error),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(err) => write!(f, "world storage io error: {err}"),
+            StorageError::Corrupt(reason) => write!(f, "corrupt world data: {reason}"),
+            StorageError::MetadataParse(err) => write!(f, "invalid world metadata: {err}"),
+            StorageError::MetadataSerialize(err) => {
+                write!(f, "failed to serialize world metadata: {err}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<io::Error> for StorageError {
+    fn from(err: io::Error) -> Self {
+        StorageError::Io(err)
+    }
+}
@@ -0,0 +1,84 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    physics::player::MovementMode,
+    simulation::world_time::START_TIME_OF_DAY,
+    world::storage::{atomic_write::write_atomic, error::StorageError},
+};
+
+pub const METADATA_FILE: &str = "world.toml";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerState {
+    /// Feet position.
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],
+    pub flying: bool,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorldMetadata {
+    pub seed: u64,
+    pub spawn: [f32; 3],
+    pub tick_count: u64,
+    /// Days passed on the world clock; older saves start on day 0.
+    #[serde(default)]
+    pub day: u64,
+    #[serde(default = "start_time_of_day")]
+    pub time_of_day: f32,
+    pub player: PlayerState,
+}
+
+fn start_time_of_day() -> f32 {
+    START_TIME_OF_DAY
+}
+
+impl PlayerState {
+    pub fn mode(&self) -> MovementMode {
+        if self.flying {
+            MovementMode::Flying
+        } else {
+            MovementMode::Walking
+        }
+    }
+}
+
+impl WorldMetadata {
+    /// Metadata of a fresh world with the player standing at `spawn`.
+    pub fn new(seed: u64, spawn: [f32; 3]) -> Self {
+        WorldMetadata {
+            seed,
+            spawn,
+            tick_count: 0,
+            day: 0,
+            time_of_day: START_TIME_OF_DAY,
+            player: PlayerState {
+                position: spawn,
+                velocity: [0.0; 3],
+                flying: false,
+                yaw: 0.0,
+                pitch: 0.0,
+            },
+        }
+    }
+
+    pub fn load(dir: &Path) -> Result<Option<Self>, StorageError> {
+        match fs::read_to_string(dir.join(METADATA_FILE)) {
+            Ok(text) => toml::from_str(&text)
+                .map(Some)
+                .map_err(StorageError::MetadataParse),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), StorageError> {
+        let text = toml::to_string_pretty(self).map_err(StorageError::MetadataSerialize)?;
+        write_atomic(&dir.join(METADATA_FILE), text.as_bytes())?;
+        Ok(())
+    }
+}
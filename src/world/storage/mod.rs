@@ -0,0 +1,6 @@
+pub mod atomic_write;
+pub mod chunk_codec;
+pub mod error;
+pub mod metadata;
+pub mod region;
+pub mod world_save;
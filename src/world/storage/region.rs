@@ -0,0 +1,201 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use glam::IVec3;
+
+use crate::world::storage::{
+    atomic_write::write_atomic, chunk_codec::MAX_ENCODED_LEN, error::StorageError,
+};
+
+/// Regions span this many chunks along X and Z and a single chunk along Y.
+pub const REGION_SIZE: i32 = 32;
+pub const REGION_CHUNKS: usize = (REGION_SIZE * REGION_SIZE) as usize;
+
+const MAGIC: &[u8; 4] = b"VXRG";
+const FORMAT_VERSION: u32 = 1;
+const HEADER_LEN: usize = 8 + REGION_CHUNKS * 8;
+
+pub fn region_pos(chunk_pos: IVec3) -> IVec3 {
+    IVec3::new(
+        chunk_pos.x.div_euclid(REGION_SIZE),
+        chunk_pos.y,
+        chunk_pos.z.div_euclid(REGION_SIZE),
+    )
+}
+
+pub fn region_path(dir: &Path, region_pos: IVec3) -> PathBuf {
+    dir.join(format!(
+        "r.{}.{}.{}.vxr",
+        region_pos.x, region_pos.y, region_pos.z
+    ))
+}
+
+fn slot(chunk_pos: IVec3) -> usize {
+    let x = chunk_pos.x.rem_euclid(REGION_SIZE);
+    let z = chunk_pos.z.rem_euclid(REGION_SIZE);
+    (x + z * REGION_SIZE) as usize
+}
+
+/// In-memory copy of a region file: an offset table followed by lz4-compressed chunk blobs.
+///
+/// Layout (little endian): magic `VXRG`, `u32` version, then for each of the 32x32 slots a
+/// `u32` byte offset and `u32` byte length (0 when the chunk isn't stored), then the blobs.
+#[derive(Debug)]
+pub struct Region {
+    pub pos: IVec3,
+    slots: Vec<Option<Vec<u8>>>,
+}
+
+impl Region {
+    pub fn new(pos: IVec3) -> Self {
+        Region {
+            pos,
+            slots: vec![None; REGION_CHUNKS],
+        }
+    }
+
+    /// Reads the region file from `dir`, or returns an empty region if it doesn't exist yet.
+    pub fn open(dir: &Path, pos: IVec3) -> Result<Self, StorageError> {
+        match fs::read(region_path(dir, pos)) {
+            Ok(bytes) => Self::from_bytes(pos, &bytes),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::new(pos)),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn from_bytes(pos: IVec3, bytes: &[u8]) -> Result<Self, StorageError> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(StorageError::Corrupt(format!(
+                "region {pos} has an invalid header"
+            )));
+        }
+        let read_u32 = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+        let version = read_u32(4);
+        if version != FORMAT_VERSION {
+            return Err(StorageError::Corrupt(format!(
+                "region {pos} has unsupported version {version}"
+            )));
+        }
+
+        let mut region = Self::new(pos);
+        for (i, slot) in region.slots.iter_mut().enumerate() {
+            let offset = read_u32(8 + i * 8) as usize;
+            let len = read_u32(12 + i * 8) as usize;
+            if len == 0 {
+                continue;
+            }
+            let blob = bytes.get(offset..offset + len).ok_or_else(|| {
+                StorageError::Corrupt(format!("region {pos} slot {i} points past the end"))
+            })?;
+            *slot = Some(blob.to_vec());
+        }
+        Ok(region)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+
+        let mut body = Vec::new();
+        for slot in &self.slots {
+            let (offset, len) = match slot {
+                Some(blob) => {
+                    let offset = HEADER_LEN + body.len();
+                    body.extend_from_slice(blob);
+                    (offset as u32, blob.len() as u32)
+                }
+                None => (0, 0),
+            };
+            header.extend_from_slice(&offset.to_le_bytes());
+            header.extend_from_slice(&len.to_le_bytes());
+        }
+        header.extend_from_slice(&body);
+        header
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), StorageError> {
+        write_atomic(&region_path(dir, self.pos), &self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Uncompressed chunk data stored for `chunk_pos`, if any.
+    pub fn read_chunk(&self, chunk_pos: IVec3) -> Result<Option<Vec<u8>>, StorageError> {
+        debug_assert_eq!(region_pos(chunk_pos), self.pos);
+        let Some(blob) = self.slots[slot(chunk_pos)].as_deref() else {
+            return Ok(None);
+        };
+        let corrupt = |reason: &str| StorageError::Corrupt(format!("chunk {chunk_pos} {reason}"));
+
+        // The blob starts with its decompressed length, checked before anything is allocated
+        // for it.
+        let (length, compressed) = blob
+            .split_first_chunk::<4>()
+            .ok_or_else(|| corrupt("is truncated"))?;
+        let length = u32::from_le_bytes(*length) as usize;
+        if length > MAX_ENCODED_LEN {
+            return Err(corrupt(&format!("claims to be {length} bytes")));
+        }
+        let mut data = vec![0; length];
+        let written = lz4_flex::decompress_into(compressed, &mut data)
+            .map_err(|err| corrupt(&format!("failed to decompress: {err}")))?;
+        if written != length {
+            return Err(corrupt("is shorter than it claims"));
+        }
+        Ok(Some(data))
+    }
+
+    pub fn write_chunk(&mut self, chunk_pos: IVec3, data: &[u8]) {
+        debug_assert_eq!(region_pos(chunk_pos), self.pos);
+        self.slots[slot(chunk_pos)] = Some(lz4_flex::compress_prepend_size(data));
+    }
+
+    pub fn remove_chunk(&mut self, chunk_pos: IVec3) {
+        self.slots[slot(chunk_pos)] = None;
+    }
+
+    /// Positions of every chunk stored in the region.
+    pub fn chunk_positions(&self) -> impl Iterator<Item = IVec3> + '_ {
+        let origin = self.pos * IVec3::new(REGION_SIZE, 1, REGION_SIZE);
+        self.slots.iter().enumerate().filter_map(move |(i, slot)| {
+            slot.as_ref().map(|_| {
+                let i = i as i32;
+                origin + IVec3::new(i % REGION_SIZE, 0, i / REGION_SIZE)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_chunks() {
+        let chunk_pos = IVec3::new(-3, 1, 40);
+        let mut region = Region::new(region_pos(chunk_pos));
+        region.write_chunk(chunk_pos, b"chunk data");
+        let region = Region::from_bytes(region.pos, &region.to_bytes()).unwrap();
+        assert_eq!(
+            region.read_chunk(chunk_pos).unwrap().as_deref(),
+            Some(&b"chunk data"[..])
+        );
+        assert_eq!(region.read_chunk(chunk_pos + IVec3::X).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_chunks_claiming_huge_sizes() {
+        let chunk_pos = IVec3::ZERO;
+        let mut region = Region::new(chunk_pos);
+        let mut blob = lz4_flex::compress_prepend_size(b"chunk data");
+        blob[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        region.slots[slot(chunk_pos)] = Some(blob);
+        assert!(matches!(
+            region.read_chunk(chunk_pos),
+            Err(StorageError::Corrupt(_))
+        ));
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use glam::IVec3;
+
+use crate::world::{
+    chunk::Chunk,
+    storage::{
+        chunk_codec,
+        error::StorageError,
+        metadata::WorldMetadata,
+        region::{Region, region_pos},
+    },
+    world::World,
+};
+
+/// A world directory: one region file per 32x32 chunk area plus `world.toml` metadata.
+#[derive(Debug)]
+pub struct WorldSave {
+    dir: PathBuf,
+}
+
+impl WorldSave {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, StorageError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(WorldSave { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes every unsaved chunk and the metadata, then marks the world as saved. Only regions
+    /// that contain unsaved chunks are rewritten. Returns the number of chunks written.
+    pub fn save(&self, world: &mut World, metadata: &WorldMetadata) -> Result<usize, StorageError> {
+        let mut by_region: HashMap<IVec3, Vec<IVec3>> = HashMap::new();
+        for chunk_pos in world.unsaved_chunks() {
+            by_region
+                .entry(region_pos(*chunk_pos))
+                .or_default()
+                .push(*chunk_pos);
+        }
+
+        let mut written = 0;
+        for (pos, chunk_positions) in by_region {
+            let mut region = Region::open(&self.dir, pos)?;
+            for chunk_pos in chunk_positions {
+                match world.chunk(chunk_pos) {
+                    Some(chunk) => {
+                        region.write_chunk(chunk_pos, &chunk_codec::encode(chunk));
+                        written += 1;
+                    }
+                    None => region.remove_chunk(chunk_pos),
+                }
+            }
+            region.save(&self.dir)?;
+        }
+
+        metadata.save(&self.dir)?;
+        world.mark_saved();
+        Ok(written)
+    }
+
+    pub fn load_metadata(&self) -> Result<Option<WorldMetadata>, StorageError> {
+        WorldMetadata::load(&self.dir)
+    }
+
+    pub fn load_chunk(&self, chunk_pos: IVec3) -> Result<Option<Chunk>, StorageError> {
+        Region::open(&self.dir, region_pos(chunk_pos))?
+            .read_chunk(chunk_pos)?
+            .map(|data| chunk_codec::decode(&data))
+            .transpose()
+    }
+
+    pub fn load_world(&self) -> Result<World, StorageError> {
+        let mut world = World::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let Some(pos) = parse_region_name(&path) else {
+                continue;
+            };
+            let region = Region::open(&self.dir, pos)?;
+            for chunk_pos in region.chunk_positions() {
+                if let Some(data) = region.read_chunk(chunk_pos)? {
+                    world.insert_chunk(chunk_pos, chunk_codec::decode(&data)?);
+                }
+            }
+        }
+        Ok(world)
+    }
+}
+
+/// Parses `r.<x>.<y>.<z>.vxr`; anything else, including leftover `.tmp` files, is skipped.
+fn parse_region_name(path: &Path) -> Option<IVec3> {
+    let name = path.file_name()?.to_str()?;
+    let coords = name.strip_prefix("r.")?.strip_suffix(".vxr")?;
+    let mut parts = coords.split('.').map(str::parse::<i32>);
+    let pos = IVec3::new(
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+    parts.next().is_none().then_some(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{block::BlockId, storage::metadata::PlayerState};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("rust-voxel-blocks-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn metadata() -> WorldMetadata {
+        WorldMetadata {
+            seed: 42,
+            spawn: [0.5, 2.0, 0.5],
+            tick_count: 1234,
+            day: 2,
+            time_of_day: 0.8,
+            player: PlayerState {
+                position: [10.25, 3.0, -7.5],
+                velocity: [0.0, -1.5, 0.0],
+                flying: true,
+                yaw: 1.25,
+                pitch: -0.5,
+            },
+        }
+    }
+
+    fn assert_same_chunks(a: &World, b: &World) {
+        assert_eq!(a.chunks().count(), b.chunks().count());
+        for (pos, chunk) in a.chunks() {
+            assert_eq!(b.chunk(*pos), Some(chunk), "chunk {pos}");
+        }
+    }
+
+    #[test]
+    fn round_trips_world_and_metadata() {
+        let dir = temp_dir("round-trip");
+        let mut world = World::flat(1);
+        world.set_block(IVec3::new(-1000, 40, 2000), BlockId::DIRT);
+        world.set_block(IVec3::new(5, -70, -3), BlockId::GRASS);
+
+        let save = WorldSave::open(&dir).unwrap();
+        save.save(&mut world, &metadata()).unwrap();
+
+        let loaded = save.load_world().unwrap();
+        assert_same_chunks(&world, &loaded);
+        assert_eq!(save.load_metadata().unwrap(), Some(metadata()));
+        assert_eq!(
+            save.load_chunk(World::chunk_pos(IVec3::new(-1000, 40, 2000)))
+                .unwrap()
+                .unwrap()
+                .get(World::local_pos(IVec3::new(-1000, 40, 2000))),
+            BlockId::DIRT
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saves_only_unsaved_chunks() {
+        let dir = temp_dir("incremental");
+        let save = WorldSave::open(&dir).unwrap();
+        let mut world = World::flat(1);
+        assert_eq!(save.save(&mut world, &metadata()).unwrap(), 4);
+        assert_eq!(save.save(&mut world, &metadata()).unwrap(), 0);
+
+        world.set_block(IVec3::new(3, 3, 3), BlockId::STONE);
+        assert_eq!(save.save(&mut world, &metadata()).unwrap(), 1);
+        assert!(world.unsaved_chunks().is_empty());
+
+        // A temp file left behind by an interrupted save must not affect loading.
+        fs::write(dir.join("r.0.0.0.vxr.tmp"), b"partial").unwrap();
+        assert_same_chunks(&world, &save.load_world().unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
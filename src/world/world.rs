@@ -0,0 +1,171 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use glam::IVec3;
+
+use crate::world::{
+    block::BlockId,
+    chunk::{CHUNK_SIZE, Chunk},
+};
+
+pub const NEIGHBOR_OFFSETS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+#[derive(Debug, Default)]
+pub struct World {
+    chunks: HashMap<IVec3, Chunk>,
+    /// Chunks modified since they were last saved.
+    unsaved: HashSet<IVec3>,
+    /// Chunks whose mesh is out of date, including neighbors of edited border blocks.
+    remesh: HashSet<IVec3>,
+    /// Chunks with block ticks scheduled, in a fixed order so they run the same every time.
+    ticking: BTreeSet<[i32; 3]>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grass floor with its top face at y = 0, spanning `radius` chunks around the origin.
+    pub fn flat(radius: i32) -> Self {
+        let mut world = Self::new();
+        let extent = radius * CHUNK_SIZE;
+        for x in -extent..extent {
+            for z in -extent..extent {
+                world.set_block(IVec3::new(x, -3, z), BlockId::STONE);
+                world.set_block(IVec3::new(x, -2, z), BlockId::DIRT);
+                world.set_block(IVec3::new(x, -1, z), BlockId::GRASS);
+            }
+        }
+        world
+    }
+
+    pub fn chunk_pos(block_pos: IVec3) -> IVec3 {
+        block_pos.div_euclid(IVec3::splat(CHUNK_SIZE))
+    }
+
+    pub fn local_pos(block_pos: IVec3) -> IVec3 {
+        block_pos.rem_euclid(IVec3::splat(CHUNK_SIZE))
+    }
+
+    pub fn chunk(&self, chunk_pos: IVec3) -> Option<&Chunk> {
+        self.chunks.get(&chunk_pos)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (&IVec3, &Chunk)> {
+        self.chunks.iter()
+    }
+
+    /// Inserts a chunk without marking it unsaved, e.g. when it was just loaded from disk.
+    pub fn insert_chunk(&mut self, chunk_pos: IVec3, chunk: Chunk) {
+        if chunk.has_scheduled() {
+            self.ticking.insert(chunk_pos.to_array());
+        }
+        self.chunks.insert(chunk_pos, chunk);
+        self.remesh.insert(chunk_pos);
+        for offset in NEIGHBOR_OFFSETS {
+            self.remesh.insert(chunk_pos + offset);
+        }
+    }
+
+    pub fn unsaved_chunks(&self) -> &HashSet<IVec3> {
+        &self.unsaved
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.unsaved.clear();
+    }
+
+    pub fn mark_all_unsaved(&mut self) {
+        self.unsaved.extend(self.chunks.keys().copied());
+    }
+
+    pub fn remesh_chunks(&self) -> &HashSet<IVec3> {
+        &self.remesh
+    }
+
+    /// Drains the set of chunks that need their mesh rebuilt.
+    pub fn take_remesh_chunks(&mut self) -> HashSet<IVec3> {
+        std::mem::take(&mut self.remesh)
+    }
+
+    /// Schedules the block at `pos` to tick at `tick`. Blocks in chunks that aren't loaded
+    /// don't tick.
+    pub fn schedule_tick(&mut self, pos: IVec3, tick: u64) {
+        let chunk_pos = Self::chunk_pos(pos);
+        let Some(chunk) = self.chunks.get_mut(&chunk_pos) else {
+            return;
+        };
+        chunk.schedule(Self::local_pos(pos), tick);
+        self.ticking.insert(chunk_pos.to_array());
+        self.unsaved.insert(chunk_pos);
+    }
+
+    /// Chunks with block ticks scheduled.
+    pub fn ticking_chunks(&self) -> impl Iterator<Item = IVec3> + '_ {
+        self.ticking.iter().copied().map(IVec3::from_array)
+    }
+
+    /// Removes up to `limit` block ticks due by `now`, chunk by chunk, and returns their
+    /// positions.
+    pub fn take_due_ticks(&mut self, now: u64, limit: usize) -> Vec<IVec3> {
+        let mut due = Vec::new();
+        for chunk_pos in self.ticking.clone() {
+            let chunk_pos = IVec3::from_array(chunk_pos);
+            let Some(chunk) = self.chunks.get_mut(&chunk_pos) else {
+                self.ticking.remove(&chunk_pos.to_array());
+                continue;
+            };
+            let taken = chunk.take_due(now, limit - due.len());
+            if !taken.is_empty() {
+                self.unsaved.insert(chunk_pos);
+            }
+            if !chunk.has_scheduled() {
+                self.ticking.remove(&chunk_pos.to_array());
+            }
+            let origin = chunk_pos * CHUNK_SIZE;
+            due.extend(taken.into_iter().map(|local| origin + local));
+            if due.len() == limit {
+                break;
+            }
+        }
+        due
+    }
+
+    pub fn get_block(&self, pos: IVec3) -> BlockId {
+        self.chunks
+            .get(&Self::chunk_pos(pos))
+            .map_or(BlockId::AIR, |chunk| chunk.get(Self::local_pos(pos)))
+    }
+
+    pub fn set_block(&mut self, pos: IVec3, block: BlockId) {
+        let chunk_pos = Self::chunk_pos(pos);
+        if block.is_air() && !self.chunks.contains_key(&chunk_pos) {
+            return;
+        }
+        self.chunks
+            .entry(chunk_pos)
+            .or_default()
+            .set(Self::local_pos(pos), block);
+        self.unsaved.insert(chunk_pos);
+
+        self.remesh.insert(chunk_pos);
+        let local = Self::local_pos(pos);
+        for offset in NEIGHBOR_OFFSETS {
+            let neighbor = local + offset;
+            if neighbor.cmplt(IVec3::ZERO).any() || neighbor.cmpge(IVec3::splat(CHUNK_SIZE)).any() {
+                self.remesh.insert(chunk_pos + offset);
+            }
+        }
+    }
+
+    pub fn is_solid(&self, pos: IVec3) -> bool {
+        self.get_block(pos).is_solid()
+    }
+}
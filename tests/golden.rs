@@ -0,0 +1,383 @@
+//! Golden-image rendering tests.
+//!
+//! Each scene is rendered headless on a software adapter from a fixed camera and compared
+//! against `tests/fixtures/golden/<name>.png`. A pixel counts as different when its perceptual
+//! color distance exceeds `PIXEL_THRESHOLD`; a scene fails when more than `MAX_DIFF_RATIO` of
+//! its pixels differ, which absorbs rasterizer differences between driver versions. On failure
+//! the actual frame and a diff image are written next to the test binary's temp dir.
+//!
+//! Run with `UPDATE_GOLDEN=1` to (re)write the references. Without a software adapter the
+//! tests are skipped unless `GOLDEN_REQUIRE_ADAPTER=1` is set, as it should be in CI.
+
+use std::{env, path::PathBuf, time::Instant};
+
+use glam::{IVec3, Vec2, Vec3};
+use rust_voxel_blocks::{
+    formats::image::RgbaImage,
+    graphics::{
+        gpu::Gpu,
+        graphics::{Graphics, offscreen_surface_config},
+        render_pass::RenderPass,
+        shaders::{self, ShaderError},
+    },
+    settings::settings::Settings,
+    world::{
+        block::{Axis, BlockId, Facing, Half},
+        storage::metadata::WorldMetadata,
+        world::World,
+    },
+};
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 160;
+
+/// Normalized YIQ distance above which two pixels are considered different.
+const PIXEL_THRESHOLD: f32 = 0.05;
+/// Fraction of differing pixels a scene may have and still pass.
+const MAX_DIFF_RATIO: f32 = 0.005;
+
+struct Scene {
+    name: &'static str,
+    world: World,
+    eye: Vec3,
+    yaw: f32,
+    pitch: f32,
+}
+
+fn headless_graphics(name: &str, world: World, eye: Vec3) -> Option<Graphics> {
+    let mut settings = Settings::default();
+    settings.debug.hot_reload_shaders = false;
+    let gpu = match pollster::block_on(Gpu::headless(wgpu::PowerPreference::None, true)) {
+        Ok(gpu) => gpu,
+        Err(err) if env::var_os("GOLDEN_REQUIRE_ADAPTER").is_some() => panic!("{err}"),
+        Err(err) => {
+            eprintln!("skipping golden test {name}: {err}");
+            return None;
+        }
+    };
+
+    let metadata = WorldMetadata::new(0, eye.into());
+    Some(Graphics::new(
+        gpu,
+        offscreen_surface_config(WIDTH, HEIGHT),
+        &settings,
+        world,
+        metadata,
+    ))
+}
+
+fn render(scene: Scene) -> Option<RgbaImage> {
+    let mut gfx = headless_graphics(scene.name, scene.world, scene.eye)?;
+    gfx.camera.position = scene.eye;
+    gfx.camera.yaw = scene.yaw;
+    gfx.camera.pitch = scene.pitch;
+    gfx.camera.update_rotation(Vec2::ZERO);
+    // Animations such as the water's waves are frozen at their start.
+    gfx.metadata.start_instant = Instant::now();
+    Some(gfx.capture().unwrap())
+}
+
+fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("tests/fixtures/golden/{name}.png"))
+}
+
+fn output_path(name: &str, kind: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("golden/{name}-{kind}.png"))
+}
+
+/// Brightness, and the two chroma axes of YIQ, from sRGB-encoded channels.
+fn yiq(pixel: [u8; 4]) -> [f32; 3] {
+    let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|channel| channel as f32);
+    [
+        r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2,
+        r * 0.595_977_9 - g * 0.274_176_4 - b * 0.321_801_5,
+        r * 0.211_470_2 - g * 0.522_617_2 + b * 0.311_147,
+    ]
+}
+
+/// Perceptual color distance in 0..=1, weighting luma over chroma like pixelmatch.
+fn color_distance(a: [u8; 4], b: [u8; 4]) -> f32 {
+    const MAX_DELTA: f32 = 35_215.0;
+    let [ya, ia, qa] = yiq(a);
+    let [yb, ib, qb] = yiq(b);
+    let (y, i, q) = (ya - yb, ia - ib, qa - qb);
+    ((0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / MAX_DELTA).sqrt()
+}
+
+/// Number of differing pixels, and an image marking them red over a faded expected frame.
+fn diff(expected: &RgbaImage, actual: &RgbaImage) -> (usize, RgbaImage) {
+    let mut image = RgbaImage::new(expected.width, expected.height);
+    let mut count = 0;
+    for y in 0..expected.height {
+        for x in 0..expected.width {
+            let (a, b) = (expected.pixel(x, y), actual.pixel(x, y));
+            let out = if color_distance(a, b) > PIXEL_THRESHOLD {
+                count += 1;
+                [255, 0, 0, 255]
+            } else {
+                let gray = (yiq(a)[0] * 0.25 + 191.0) as u8;
+                [gray, gray, gray, 255]
+            };
+            let index = ((y * expected.width + x) * 4) as usize;
+            image.pixels[index..index + 4].copy_from_slice(&out);
+        }
+    }
+    (count, image)
+}
+
+fn assert_golden(scene: Scene) {
+    let name = scene.name;
+    let Some(actual) = render(scene) else {
+        return;
+    };
+
+    let reference = fixture_path(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save(&reference).unwrap();
+        return;
+    }
+
+    let actual_path = output_path(name, "actual");
+    let expected = RgbaImage::load(&reference).unwrap_or_else(|err| {
+        actual.save(&actual_path).unwrap();
+        panic!(
+            "no reference for {name} ({}: {err}); rendered frame saved to {}, \
+             rerun with UPDATE_GOLDEN=1 to accept it",
+            reference.display(),
+            actual_path.display()
+        )
+    });
+    assert_eq!(
+        (expected.width, expected.height),
+        (actual.width, actual.height),
+        "{name}: reference size differs from the rendered frame"
+    );
+
+    let (count, diff_image) = diff(&expected, &actual);
+    let ratio = count as f32 / (actual.width * actual.height) as f32;
+    if ratio > MAX_DIFF_RATIO {
+        let diff_path = output_path(name, "diff");
+        actual.save(&actual_path).unwrap();
+        diff_image.save(&diff_path).unwrap();
+        panic!(
+            "{name}: {count} pixels ({:.2}%) differ from {}; see {} and {}",
+            ratio * 100.0,
+            reference.display(),
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn flat_world_horizon() {
+    assert_golden(Scene {
+        name: "flat_world_horizon",
+        world: World::flat(2),
+        eye: Vec3::new(0.5, 1.62, 0.5),
+        yaw: 0.0,
+        pitch: 0.0,
+    });
+}
+
+#[test]
+fn structure_from_above() {
+    let mut world = World::flat(1);
+    for y in 0..4 {
+        world.set_block(IVec3::new(2, y, -4), BlockId::STONE);
+    }
+    for x in -3..=0 {
+        world.set_block(IVec3::new(x, 0, -5), BlockId::from_rgb([220, 40, 40]));
+        world.set_block(IVec3::new(x, 1, -5), BlockId::from_rgb([40, 80, 220]));
+    }
+    world.set_block(IVec3::new(-1, 0, -2), BlockId::DIRT);
+
+    assert_golden(Scene {
+        name: "structure_from_above",
+        world,
+        eye: Vec3::new(0.5, 6.0, 3.0),
+        yaw: 0.2,
+        pitch: -0.7,
+    });
+}
+
+#[test]
+fn looking_straight_down() {
+    let mut world = World::flat(1);
+    world.set_block(IVec3::new(0, 0, 0), BlockId::STONE);
+    world.set_block(IVec3::new(1, 0, 1), BlockId::from_rgb([250, 220, 40]));
+
+    assert_golden(Scene {
+        name: "looking_straight_down",
+        world,
+        eye: Vec3::new(0.5, 8.0, 0.5),
+        yaw: 0.0,
+        pitch: -1.5,
+    });
+}
+
+#[test]
+fn underwater() {
+    let mut world = World::flat(1);
+    for x in -4..=4 {
+        for y in 0..3 {
+            for z in -12..=4 {
+                world.set_block(IVec3::new(x, y, z), BlockId::WATER);
+            }
+        }
+    }
+    for y in 0..3 {
+        world.set_block(IVec3::new(1, y, -6), BlockId::STONE);
+    }
+
+    assert_golden(Scene {
+        name: "underwater",
+        world,
+        eye: Vec3::new(0.5, 1.5, 2.5),
+        yaw: 0.0,
+        pitch: -0.1,
+    });
+}
+
+#[test]
+fn broken_shader_is_reported_instead_of_panicking() {
+    let Some(gfx) = headless_graphics("broken_shader", World::new(), Vec3::ZERO) else {
+        return;
+    };
+    let build = |patch: fn(String) -> String| {
+        RenderPass::load(
+            &gfx.device,
+            &gfx.bind_group_layouts_render.as_slice(),
+            gfx.surface_config.format,
+            &|name| shaders::embedded(name).map(patch),
+        )
+    };
+
+    assert!(build(|source| source).is_ok());
+    let renamed = build(|source| source.replace("fn main_fragment", "fn fragment_main"));
+    assert!(matches!(renamed, Err(ShaderError::Pipeline(_))));
+    let err = build(|source| source.replace("frame: u32,", "frame: u32 +"))
+        .err()
+        .expect("syntax error was not reported");
+    assert!(
+        err.to_string()
+            .starts_with("common.wgsl:8: failed to parse shader"),
+        "{err}"
+    );
+}
+
+#[test]
+fn diff_tolerates_small_color_changes() {
+    let mut expected = RgbaImage::new(4, 1);
+    expected.pixels.fill(128);
+    let mut actual = expected.clone();
+    actual.pixels[0] = 131;
+    actual.pixels[4] = 255;
+
+    let (count, image) = diff(&expected, &actual);
+    assert_eq!(count, 1);
+    assert_eq!(image.pixel(1, 0), [255, 0, 0, 255]);
+}
+
+#[test]
+fn glass_and_leaves() {
+    let mut world = World::flat(1);
+    for y in 0..3 {
+        world.set_block(IVec3::new(0, y, -8), BlockId::STONE);
+    }
+    for x in -2..=1 {
+        for y in 0..2 {
+            world.set_block(IVec3::new(x, y, -5), BlockId::GLASS);
+        }
+    }
+    for x in 2..4 {
+        for y in 0..2 {
+            for z in -5..-3 {
+                world.set_block(IVec3::new(x, y, z), BlockId::LEAVES);
+            }
+        }
+    }
+
+    assert_golden(Scene {
+        name: "glass_and_leaves",
+        world,
+        eye: Vec3::new(0.5, 2.5, 1.0),
+        yaw: 0.2,
+        pitch: -0.25,
+    });
+}
+
+#[test]
+fn water_pool_from_above() {
+    let mut world = World::flat(1);
+    for x in -5..=5 {
+        for z in -13..=-3 {
+            world.set_block(IVec3::new(x, -3, z), BlockId::STONE);
+            for y in -2..0 {
+                world.set_block(IVec3::new(x, y, z), BlockId::WATER);
+            }
+        }
+    }
+    for y in -2..3 {
+        world.set_block(IVec3::new(1, y, -9), BlockId::STONE);
+    }
+    for x in -3..0 {
+        world.set_block(IVec3::new(x, -2, -5), BlockId::DIRT);
+    }
+
+    assert_golden(Scene {
+        name: "water_pool_from_above",
+        world,
+        eye: Vec3::new(0.5, 3.0, 1.0),
+        yaw: 0.0,
+        pitch: -0.45,
+    });
+}
+
+#[test]
+fn shaped_blocks() {
+    let mut world = World::flat(1);
+    let stairs = BlockId::STONE_STAIRS;
+    let slab = BlockId::STONE_SLAB;
+    let row = [
+        stairs.with_facing(Facing::North),
+        stairs.with_facing(Facing::East),
+        stairs.with_facing(Facing::South).with_half(Half::Top),
+        slab,
+        slab.with_half(Half::Top),
+        slab.with_half(Half::Double),
+        BlockId::LOG.with_axis(Axis::X),
+    ];
+    for (x, block) in (-3..).zip(row) {
+        world.set_block(IVec3::new(x, 0, -4), block);
+    }
+    for x in -3..=3 {
+        world.set_block(IVec3::new(x, 0, -6), BlockId::WHEAT);
+    }
+
+    assert_golden(Scene {
+        name: "shaped_blocks",
+        world,
+        eye: Vec3::new(0.5, 1.6, -1.0),
+        yaw: 0.0,
+        pitch: -0.35,
+    });
+}
+
+#[test]
+fn model_blocks() {
+    let mut world = World::flat(1);
+    world.set_block(IVec3::new(-1, 0, -3), BlockId::TABLE);
+    world.set_block(IVec3::new(0, 0, -3), BlockId::TABLE);
+    world.set_block(IVec3::new(0, 1, -3), BlockId::TORCH);
+    world.set_block(IVec3::new(2, 0, -3), BlockId::TORCH);
+    world.set_block(IVec3::new(2, 0, -4), BlockId::STONE);
+
+    assert_golden(Scene {
+        name: "model_blocks",
+        world,
+        eye: Vec3::new(0.5, 1.6, -0.5),
+        yaw: 0.0,
+        pitch: -0.4,
+    });
+}